@@ -0,0 +1,465 @@
+use std::{borrow::Cow, fmt::Display};
+
+use super::{
+    lexer::{Literal, Span, Token},
+    parser::parse_number,
+    DuplicateKeys, Json, Map, ParseError, Parser, ParserOptions,
+};
+
+/// A concrete syntax tree of a JSON document, which keeps every token of
+/// the source: whitespace, comments, and numbers and strings as they are
+/// written
+///
+/// Writing the tree with [`Display`] gives back the source byte for byte,
+/// apart from the values that were replaced, so a file can be edited
+/// without disturbing how the rest of it is laid out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cst<'src> {
+    /// The value along with the whitespace and comments around it
+    elements: Vec<Element<'src>>,
+    /// How [`Cst::to_json`] handles members with the same name as an
+    /// earlier member, as the options it was parsed with say
+    duplicate_keys: DuplicateKeys,
+}
+
+/// A part of an array, an object or a whole document in a [`Cst`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Element<'src> {
+    /// Whitespace or a comment
+    Trivia(Cow<'src, str>),
+    /// One of `{`, `}`, `[`, `]`, `,` and `:`
+    Punctuation(char),
+    /// The name of an object member, as it is written and as it reads
+    Name(Cow<'src, str>, String),
+    Value(Node<'src>),
+}
+
+/// A value in a [`Cst`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node<'src> {
+    /// A string, number, `true`, `false` or `null`, as it is written and as
+    /// it reads
+    Scalar(Cow<'src, str>, Json),
+    /// The items of an array, with the brackets, commas and trivia between
+    /// them
+    Array(Vec<Element<'src>>),
+    /// The names and values of the members of an object, with the braces,
+    /// colons, commas and trivia between them
+    Object(Vec<Element<'src>>),
+}
+
+impl<'src> Cst<'src> {
+    /// Parse `source`, failing with the same errors as [`Parser`] does with
+    /// the same `options`
+    pub fn parse(source: &'src [u8], options: ParserOptions) -> Result<Self, ParseError<'src>> {
+        // The tokens that the parser checks are kept, so the source is
+        // lexed once and they can be put together without checking their
+        // order again
+        let mut tokens = Vec::new();
+        Parser::new(source)
+            .options(options)
+            .parse_keeping_tokens(&mut tokens)?;
+
+        let mut document = Vec::new();
+        // The elements of the arrays and objects that are open, innermost
+        // last
+        let mut stack: Vec<Vec<Element>> = Vec::new();
+        for token in tokens {
+            let element = match token {
+                Token::Whitespace(span) | Token::Comment(span) => Element::Trivia(text(&span)),
+                Token::ArrayStart(_) => {
+                    stack.push(vec![Element::Punctuation('[')]);
+                    continue;
+                }
+                Token::ObjectStart(_) => {
+                    stack.push(vec![Element::Punctuation('{')]);
+                    continue;
+                }
+                Token::ArrayEnd(_) => {
+                    let mut elements = stack.pop().unwrap_or_default();
+                    elements.push(Element::Punctuation(']'));
+                    Element::Value(Node::Array(elements))
+                }
+                Token::ObjectEnd(_) => {
+                    let mut elements = stack.pop().unwrap_or_default();
+                    elements.push(Element::Punctuation('}'));
+                    Element::Value(Node::Object(elements))
+                }
+                Token::Comma(_) => Element::Punctuation(','),
+                Token::Colon(_) => Element::Punctuation(':'),
+                Token::Literal(literal) => {
+                    let literal = match expects_name(stack.last().unwrap_or(&document)) {
+                        true => literal.into_name(options.syntax),
+                        false => Err(literal),
+                    };
+                    match literal {
+                        Ok((span, name)) => Element::Name(text(&span), name),
+                        Err(literal) => Element::Value(scalar(literal, options)?),
+                    }
+                }
+            };
+            stack.last_mut().unwrap_or(&mut document).push(element);
+        }
+
+        Ok(Self {
+            elements: document,
+            duplicate_keys: options.duplicate_keys,
+        })
+    }
+
+    /// The value of the document as it reads, with members that have the
+    /// same name as an earlier one handled as the options it was parsed
+    /// with say
+    pub fn to_json(&self) -> Json {
+        self.root().to_json(self.duplicate_keys)
+    }
+
+    /// The elements of the document, which are the value and the trivia
+    /// around it
+    pub fn elements(&self) -> &[Element<'src>] {
+        &self.elements
+    }
+
+    pub fn root(&self) -> &Node<'src> {
+        self.elements
+            .iter()
+            .find_map(Element::node)
+            .expect("a parsed document has a value")
+    }
+
+    pub fn root_mut(&mut self) -> &mut Node<'src> {
+        self.elements
+            .iter_mut()
+            .find_map(Element::node_mut)
+            .expect("a parsed document has a value")
+    }
+}
+
+/// The text of a token, which the lexer checked to be valid UTF-8
+fn text<'src>(span: &Span<'src>) -> Cow<'src, str> {
+    Cow::Borrowed(span.lexeme().unwrap_or_default())
+}
+
+/// The node of a literal that is a value rather than a name
+fn scalar<'src>(
+    literal: Literal<'src>,
+    options: ParserOptions,
+) -> Result<Node<'src>, ParseError<'src>> {
+    Ok(match literal {
+        Literal::String(span, string) => Node::Scalar(text(&span), Json::String(string)),
+        Literal::Number(span, number) => {
+            let value = parse_number(&span, &number, options.syntax)?;
+            Node::Scalar(text(&span), value)
+        }
+        Literal::True(span) => Node::Scalar(text(&span), Json::Boolean(true)),
+        Literal::False(span) => Node::Scalar(text(&span), Json::Boolean(false)),
+        // The parser checked that identifiers only name members
+        Literal::Null(span) | Literal::Identifier(span, _) => Node::Scalar(text(&span), Json::Null),
+    })
+}
+
+/// Whether the next literal in `elements` is the name of a member, which it
+/// is in an object at its start or after a comma
+fn expects_name(elements: &[Element]) -> bool {
+    let mut significant = elements
+        .iter()
+        .rev()
+        .filter(|element| !matches!(element, Element::Trivia(_)));
+    matches!(elements.first(), Some(Element::Punctuation('{')))
+        && matches!(significant.next(), Some(Element::Punctuation('{' | ',')))
+}
+
+impl<'src> Element<'src> {
+    fn node(&self) -> Option<&Node<'src>> {
+        match self {
+            Element::Value(node) => Some(node),
+            _ => None,
+        }
+    }
+
+    fn node_mut(&mut self) -> Option<&mut Node<'src>> {
+        match self {
+            Element::Value(node) => Some(node),
+            _ => None,
+        }
+    }
+}
+
+impl<'src> Node<'src> {
+    /// The elements of the array or object, or nothing for other values
+    pub fn elements(&self) -> &[Element<'src>] {
+        match self {
+            Node::Array(elements) | Node::Object(elements) => elements,
+            Node::Scalar(..) => &[],
+        }
+    }
+
+    /// The value of the member of the object with `name`, which is the last
+    /// one if there are several
+    pub fn get(&self, name: &str) -> Option<&Node<'src>> {
+        let position = self.member(name)?;
+        self.elements()[position].node()
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Node<'src>> {
+        let position = self.member(name)?;
+        match self {
+            Node::Object(elements) => elements[position].node_mut(),
+            _ => None,
+        }
+    }
+
+    /// The item of the array at `index`
+    pub fn item(&self, index: usize) -> Option<&Node<'src>> {
+        match self {
+            Node::Array(elements) => elements.iter().filter_map(Element::node).nth(index),
+            _ => None,
+        }
+    }
+
+    pub fn item_mut(&mut self, index: usize) -> Option<&mut Node<'src>> {
+        match self {
+            Node::Array(elements) => elements.iter_mut().filter_map(Element::node_mut).nth(index),
+            _ => None,
+        }
+    }
+
+    /// The position in the elements of the value of the member with `name`
+    fn member(&self, name: &str) -> Option<usize> {
+        let elements = match self {
+            Node::Object(elements) => elements,
+            _ => return None,
+        };
+        let name = elements
+            .iter()
+            .rposition(|element| matches!(element, Element::Name(_, member) if member == name))?;
+        elements[name..]
+            .iter()
+            .position(|element| matches!(element, Element::Value(_)))
+            .map(|value| name + value)
+    }
+
+    /// Replace the value with `value`, which is written without whitespace
+    pub fn replace(&mut self, value: &Json) {
+        *self = Node::from(value);
+    }
+
+    /// The value as it reads, with members that have the same name as an
+    /// earlier one handled as `duplicate_keys` says. Since the names of
+    /// values are not checked as they are replaced, [`DuplicateKeys::Reject`]
+    /// keeps the last of them, as [`DuplicateKeys::KeepLast`] does.
+    pub fn to_json(&self, duplicate_keys: DuplicateKeys) -> Json {
+        match self {
+            Node::Scalar(_, value) => value.clone(),
+            Node::Array(elements) => Json::Array(
+                elements
+                    .iter()
+                    .filter_map(Element::node)
+                    .map(|node| node.to_json(duplicate_keys))
+                    .collect(),
+            ),
+            Node::Object(elements) => {
+                let mut members = Map::new();
+                let mut name = None;
+                for element in elements {
+                    match element {
+                        Element::Name(_, member) => name = Some(member.clone()),
+                        Element::Value(node) => {
+                            if let Some(name) = name.take() {
+                                duplicate_keys.add(
+                                    &mut members,
+                                    name,
+                                    node.to_json(duplicate_keys),
+                                );
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                Json::Object(members)
+            }
+        }
+    }
+}
+
+impl From<&Json> for Node<'static> {
+    fn from(json: &Json) -> Self {
+        match json {
+            Json::Array(items) => {
+                let mut elements = vec![Element::Punctuation('[')];
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        elements.push(Element::Punctuation(','));
+                    }
+                    elements.push(Element::Value(Node::from(item)));
+                }
+                elements.push(Element::Punctuation(']'));
+                Node::Array(elements)
+            }
+            Json::Object(members) => {
+                let mut elements = vec![Element::Punctuation('{')];
+                for (index, (name, value)) in members.iter().enumerate() {
+                    if index > 0 {
+                        elements.push(Element::Punctuation(','));
+                    }
+                    let written = Json::String(name.clone()).to_string();
+                    elements.push(Element::Name(Cow::Owned(written), name.clone()));
+                    elements.push(Element::Punctuation(':'));
+                    elements.push(Element::Value(Node::from(value)));
+                }
+                elements.push(Element::Punctuation('}'));
+                Node::Object(elements)
+            }
+            scalar => Node::Scalar(Cow::Owned(scalar.to_string()), scalar.clone()),
+        }
+    }
+}
+
+impl<'src> Display for Element<'src> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Element::Trivia(text) | Element::Name(text, _) => write!(f, "{}", text),
+            Element::Punctuation(char) => write!(f, "{}", char),
+            Element::Value(node) => write!(f, "{}", node),
+        }
+    }
+}
+
+impl<'src> Display for Node<'src> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Node::Scalar(text, _) => write!(f, "{}", text),
+            Node::Array(elements) | Node::Object(elements) => elements
+                .iter()
+                .try_for_each(|element| write!(f, "{}", element)),
+        }
+    }
+}
+
+impl<'src> Display for Cst<'src> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.elements
+            .iter()
+            .try_for_each(|element| write!(f, "{}", element))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cst, Node};
+    use crate::json::{DuplicateKeys, Json, ParseError, ParserOptions, Syntax};
+
+    #[test]
+    fn lossless() {
+        let jsonc = ParserOptions {
+            syntax: Syntax::Jsonc,
+            ..Default::default()
+        };
+        let source = "\n// Settings\n{\r\n\t\"name\" : \"caf\\u00e9\\/\", /* inline */\n  \"size\": 1.50E+1,\n  \"list\": [ true , null, ],\n}\n";
+        let mut cst = Cst::parse(source.as_bytes(), jsonc).unwrap();
+        assert_eq!(cst.to_string(), source);
+        assert_eq!(
+            Some(cst.to_json()),
+            Json::from_bytes_with_options(source.as_bytes(), jsonc)
+        );
+
+        // Only the replaced value changes
+        let root = cst.root_mut();
+        root.get_mut("size")
+            .unwrap()
+            .replace(&Json::Number("2".to_string()));
+        let list = root.get_mut("list").unwrap();
+        list.item_mut(1)
+            .unwrap()
+            .replace(&Json::from_string(r#"{"a": ["\n"]}"#).unwrap());
+        assert_eq!(
+            cst.to_string(),
+            source
+                .replace("1.50E+1", "2")
+                .replace("null", r#"{"a":["\n"]}"#)
+        );
+        assert_eq!(
+            cst.root().get("list").unwrap().item(1).unwrap().get("a"),
+            Some(&Node::from(&Json::from_string(r#"["\n"]"#).unwrap()))
+        );
+        assert!(cst.root().get("missing").is_none());
+        assert!(cst.root().item(0).is_none());
+    }
+
+    #[test]
+    fn json5() {
+        let json5 = ParserOptions {
+            syntax: Syntax::Json5,
+            ..Default::default()
+        };
+        let source = "{unquoted: 'single', hex: 0x1F, 'quoted name': +.5,}";
+        let cst = Cst::parse(source.as_bytes(), json5).unwrap();
+        assert_eq!(cst.to_string(), source);
+        assert_eq!(
+            cst.to_json(),
+            Json::from_string(r#"{"unquoted": "single", "hex": 31, "quoted name": 0.5}"#).unwrap()
+        );
+        assert_eq!(cst.root().get("unquoted").unwrap().to_string(), "'single'");
+
+        let source = "{null: null, NaN: NaN, \\u0061: 1}";
+        let cst = Cst::parse(source.as_bytes(), json5).unwrap();
+        assert_eq!(cst.to_string(), source);
+        assert_eq!(cst.root().get("null").unwrap().to_string(), "null");
+        assert_eq!(cst.root().get("NaN").unwrap().to_string(), "NaN");
+        assert_eq!(cst.root().get("a").unwrap().to_string(), "1");
+    }
+
+    #[test]
+    fn errors() {
+        let options = ParserOptions::default();
+        assert!(matches!(
+            Cst::parse(b"[1, // comment\n2]", options),
+            Err(ParseError::TokenizeError(_))
+        ));
+        assert!(matches!(
+            Cst::parse(b"[1 2]", options),
+            Err(ParseError::IllegalArray(_))
+        ));
+
+        // The limits of the options hold while the source is lexed
+        let short_strings = ParserOptions {
+            max_string_length: 3,
+            ..Default::default()
+        };
+        assert!(matches!(
+            Cst::parse(br#"["abcd"]"#, short_strings),
+            Err(ParseError::StringTooLong(_))
+        ));
+    }
+
+    #[test]
+    fn duplicates() {
+        let source = br#"{"a": 1, "b": {"c": 2, "c": 3}, "a": 4}"#;
+        let to_json = |duplicate_keys| {
+            let options = ParserOptions {
+                duplicate_keys,
+                ..Default::default()
+            };
+            Cst::parse(source, options).map(|cst| cst.to_json())
+        };
+
+        for duplicate_keys in [
+            DuplicateKeys::KeepFirst,
+            DuplicateKeys::KeepLast,
+            DuplicateKeys::KeepAll,
+        ] {
+            let options = ParserOptions {
+                duplicate_keys,
+                ..Default::default()
+            };
+            assert_eq!(
+                to_json(duplicate_keys).ok(),
+                Json::from_bytes_with_options(source, options)
+            );
+        }
+        assert!(matches!(
+            to_json(DuplicateKeys::Reject),
+            Err(ParseError::DuplicateKey(_))
+        ));
+    }
+}
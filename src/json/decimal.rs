@@ -0,0 +1,265 @@
+use std::cmp::Ordering;
+
+/// The exact value of a number that is written in decimal, borrowed from
+/// the text of the number
+///
+/// It is kept as its sign, its significant digits and the position of the
+/// decimal point relative to the first of them, so that `-0.0120e3` has the
+/// digits `12` and the point 2 places after the `1`. Every way of writing
+/// zero has no digits and is not negative.
+#[derive(Debug, Clone, Copy)]
+pub struct Decimal<'a> {
+    negative: bool,
+    /// The significant digits are `head` followed by `tail`, which are the
+    /// digits before and after the decimal point with the zeroes that lead
+    /// and trail all of them left out
+    head: &'a str,
+    tail: &'a str,
+    point: i128,
+}
+
+impl<'a> Decimal<'a> {
+    /// The value of `number`, or `None` if it is not written in decimal,
+    /// like `Infinity` and `NaN`, or its exponent does not fit in an `i64`
+    pub fn parse(number: &'a str) -> Option<Self> {
+        let (negative, number) = match number.strip_prefix('-') {
+            Some(number) => (true, number),
+            None => (false, number),
+        };
+        let (mantissa, exponent) = match number.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().ok()?),
+            None => (number, 0),
+        };
+        let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        if integer.len() + fraction.len() == 0
+            || !integer
+                .bytes()
+                .chain(fraction.bytes())
+                .all(|byte| byte.is_ascii_digit())
+        {
+            return None;
+        }
+
+        let (head, tail) = match integer.trim_start_matches('0') {
+            "" => ("", fraction.trim_start_matches('0')),
+            head => (head, fraction),
+        };
+        let leading = integer.len() + fraction.len() - head.len() - tail.len();
+        let point = integer.len() as i128 + exponent as i128 - leading as i128;
+        let (head, tail) = match tail.trim_end_matches('0') {
+            "" => (head.trim_end_matches('0'), ""),
+            tail => (head, tail),
+        };
+
+        if head.is_empty() && tail.is_empty() {
+            return Some(Self {
+                negative: false,
+                head,
+                tail,
+                point: 0,
+            });
+        }
+        Some(Self {
+            negative,
+            head,
+            tail,
+            point,
+        })
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.head.is_empty() && self.tail.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    /// Whether the number has no fractional part, like `1.0` and `2e3`
+    pub fn is_integer(&self) -> bool {
+        self.point >= self.digit_count()
+    }
+
+    /// Whether the number is an integer times `divisor`, which is never
+    /// true of a `divisor` of zero
+    pub fn is_multiple_of(&self, divisor: &Decimal) -> bool {
+        if self.is_zero() {
+            return true;
+        }
+        if divisor.is_zero() {
+            return false;
+        }
+
+        // With the digits as integers, this is `value * 10^shift` over
+        // `divisor`. The digits of the value do not end in a zero, so no
+        // power of ten divides them and a negative shift leaves a fraction.
+        let shift = (self.point - self.digit_count()) - (divisor.point - divisor.digit_count());
+        if shift < 0 {
+            return false;
+        }
+        let value: Vec<u8> = self.digits().collect();
+
+        // `divisor` is `2^twos * 5^fives * rest`, and the factors of two and
+        // five that the power of ten does not cover must divide the value
+        let mut rest: Vec<u8> = divisor.digits().collect();
+        for factor in [2, 5] {
+            let mut count = 0i128;
+            loop {
+                let (quotient, remainder) = divide(&rest, factor);
+                if remainder != 0 {
+                    break;
+                }
+                rest = quotient;
+                count += 1;
+            }
+
+            let mut value = value.clone();
+            for _ in shift..count {
+                let (quotient, remainder) = divide(&value, factor);
+                if remainder != 0 {
+                    return false;
+                }
+                value = quotient;
+            }
+        }
+        divides(&rest, &value)
+    }
+
+    fn digit_count(&self) -> i128 {
+        (self.head.len() + self.tail.len()) as i128
+    }
+
+    /// The values of the significant digits, most significant first
+    fn digits(self) -> impl Iterator<Item = u8> + 'a {
+        self.head
+            .bytes()
+            .chain(self.tail.bytes())
+            .map(|byte| byte - b'0')
+    }
+
+    fn cmp_magnitude(&self, other: &Self) -> Ordering {
+        match (self.is_zero(), other.is_zero()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            (false, false) => self
+                .point
+                .cmp(&other.point)
+                .then_with(|| self.digits().cmp(other.digits())),
+        }
+    }
+}
+
+impl PartialEq for Decimal<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal<'_> {}
+
+impl PartialOrd for Decimal<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => self.cmp_magnitude(other),
+            (true, true) => other.cmp_magnitude(self),
+        }
+    }
+}
+
+/// The quotient and remainder of the integer with the decimal `digits`
+/// over `divisor`, with the quotient's leading zeroes left out
+fn divide(digits: &[u8], divisor: u8) -> (Vec<u8>, u8) {
+    let mut quotient = Vec::with_capacity(digits.len());
+    let mut remainder = 0;
+    for &digit in digits {
+        let value = remainder * 10 + digit;
+        if !quotient.is_empty() || value >= divisor {
+            quotient.push(value / divisor);
+        }
+        remainder = value % divisor;
+    }
+    (quotient, remainder)
+}
+
+/// Whether the integer with the decimal `divisor` digits divides the one
+/// with the `value` digits, neither of which start with a zero
+fn divides(divisor: &[u8], value: &[u8]) -> bool {
+    let at_least = |remainder: &[u8]| (remainder.len(), remainder) >= (divisor.len(), divisor);
+
+    let mut remainder = Vec::with_capacity(divisor.len() + 1);
+    for &digit in value {
+        if !remainder.is_empty() || digit != 0 {
+            remainder.push(digit);
+        }
+        while at_least(&remainder) {
+            // Subtract the divisor, from the least significant digit up
+            let offset = remainder.len() - divisor.len();
+            let mut borrow = 0;
+            for index in (0..remainder.len()).rev() {
+                let subtrahend =
+                    index.checked_sub(offset).map_or(0, |index| divisor[index]) + borrow;
+                borrow = u8::from(remainder[index] < subtrahend);
+                remainder[index] = remainder[index] + borrow * 10 - subtrahend;
+            }
+            let zeroes = remainder.iter().take_while(|&&digit| digit == 0).count();
+            remainder.drain(..zeroes);
+        }
+    }
+    remainder.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Decimal;
+
+    fn decimal(number: &str) -> Decimal<'_> {
+        Decimal::parse(number).unwrap()
+    }
+
+    #[test]
+    fn equality() {
+        assert_eq!(decimal("100"), decimal("1e2"));
+        assert_eq!(decimal("-0.0120e3"), decimal("-12"));
+        assert_eq!(decimal("-0"), decimal("0.000"));
+        assert_ne!(decimal("0.12"), decimal("0.120001"));
+        assert_ne!(decimal("-1"), decimal("1"));
+        assert!(Decimal::parse("Infinity").is_none());
+        assert!(Decimal::parse("1e99999999999999999999").is_none());
+    }
+
+    #[test]
+    fn order() {
+        assert!(decimal("9007199254740993") > decimal("9007199254740992"));
+        assert!(decimal("-9007199254740993") < decimal("-9007199254740992"));
+        assert!(decimal("0.12") < decimal("0.123"));
+        assert!(decimal("-0.5") < decimal("0"));
+        assert!(decimal("1e-400") > decimal("-0.0"));
+    }
+
+    #[test]
+    fn multiples() {
+        assert!(decimal("9007199254740992").is_multiple_of(&decimal("2")));
+        assert!(!decimal("9007199254740993").is_multiple_of(&decimal("2")));
+        assert!(decimal("123456789012345678901234567890").is_multiple_of(&decimal("0.3")));
+        assert!(!decimal("123456789012345678901234567891").is_multiple_of(&decimal("3")));
+        assert!(decimal("1e308").is_multiple_of(&decimal("0.5")));
+        assert!(decimal("0.0075").is_multiple_of(&decimal("0.0025")));
+        assert!(decimal("10").is_multiple_of(&decimal("0.625")));
+        assert!(!decimal("0.0075").is_multiple_of(&decimal("0.002")));
+        assert!(decimal("0").is_multiple_of(&decimal("7")));
+        assert!(!decimal("7").is_multiple_of(&decimal("0")));
+
+        let large = "1".repeat(50);
+        assert!(decimal(&format!("{}0", large)).is_multiple_of(&decimal(&large)));
+        assert!(!decimal(&format!("{}1", large)).is_multiple_of(&decimal(&large)));
+    }
+}
@@ -0,0 +1,220 @@
+use std::fmt::Display;
+
+use super::{lexer::Span, ParseError};
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// Renders a [`ParseError`] for people to read, along with the line of the
+/// source that it is on and a marker under the part of the line that it
+/// points at
+///
+/// ```text
+/// error: expected `:` after object key
+///  --> input.json:1:6
+///   |
+/// 1 | {"a" 1}
+///   |      ^
+/// ```
+pub struct Diagnostic<'a, 'src> {
+    error: &'a ParseError<'src>,
+    file_name: Option<&'a str>,
+    colored: bool,
+}
+
+impl<'a, 'src> Diagnostic<'a, 'src> {
+    pub fn new(error: &'a ParseError<'src>) -> Self {
+        Self {
+            error,
+            file_name: None,
+            colored: false,
+        }
+    }
+
+    /// Name the file that the source was read from in the location
+    pub fn file_name(mut self, file_name: &'a str) -> Self {
+        self.file_name = Some(file_name);
+        self
+    }
+
+    /// Color the output with ANSI escape codes
+    pub fn colored(mut self, colored: bool) -> Self {
+        self.colored = colored;
+        self
+    }
+
+    /// The escape code for `color`, or nothing if the output is not colored
+    fn color(&self, color: &'static str) -> &'static str {
+        if self.colored {
+            color
+        } else {
+            ""
+        }
+    }
+
+    fn fmt_snippet(&self, f: &mut std::fmt::Formatter<'_>, span: &Span) -> std::fmt::Result {
+        let (blue, red, reset) = (self.color(BLUE), self.color(RED), self.color(RESET));
+        let source = span.source();
+        let offset = span.offset().min(source.len());
+
+        let start = source[..offset]
+            .iter()
+            .rposition(|&byte| byte == b'\n')
+            .map_or(0, |newline| newline + 1);
+        let mut end = source[offset..]
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .map_or(source.len(), |newline| offset + newline);
+        if end > start && source[end - 1] == b'\r' {
+            end -= 1;
+        }
+
+        // Invalid UTF-8 is shown as U+FFFD, so that the line can always be
+        // shown
+        let line = String::from_utf8_lossy(&source[start..end]);
+        let before = String::from_utf8_lossy(&source[start..offset.min(end)]);
+        let marked =
+            String::from_utf8_lossy(&source[offset.min(end)..(offset + span.len()).min(end)]);
+
+        // Tabs are kept, so the marker lines up with the line above it
+        let indent: String = before
+            .chars()
+            .map(|char| if char == '\t' { '\t' } else { ' ' })
+            .collect();
+        let marker = "^".repeat(marked.chars().count().max(1));
+
+        let number = (span.line() + 1).to_string();
+        let gutter = " ".repeat(number.len());
+        writeln!(f, "{}{} |{}", blue, gutter, reset)?;
+        writeln!(f, "{}{} |{} {}", blue, number, reset, line)?;
+        write!(
+            f,
+            "{}{} |{} {}{}{}{}",
+            blue, gutter, reset, indent, red, marker, reset
+        )
+    }
+}
+
+impl<'a, 'src> Display for Diagnostic<'a, 'src> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}error{}: {}{}{}",
+            self.color(RED),
+            self.color(RESET),
+            self.color(BOLD),
+            self.error,
+            self.color(RESET)
+        )?;
+
+        let span = self.error.span();
+
+        let gutter = " ".repeat((span.line() + 1).to_string().len());
+        write!(
+            f,
+            "\n{}{}--> {}",
+            self.color(BLUE),
+            gutter,
+            self.color(RESET)
+        )?;
+        if let Some(file_name) = self.file_name {
+            write!(f, "{}:", file_name)?;
+        }
+        writeln!(f, "{}:{}", span.line() + 1, span.column() + 1)?;
+        self.fmt_snippet(f, span)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Diagnostic;
+    use crate::json::Parser;
+
+    fn render(input: &str) -> String {
+        let error = Parser::parse_str(input).unwrap_err();
+        Diagnostic::new(&error).file_name("input.json").to_string()
+    }
+
+    #[test]
+    fn renders() {
+        assert_eq!(
+            render("{\n  \"a\" 1\n}"),
+            "error: expected `:` after object key
+ --> input.json:2:7
+  |
+2 |   \"a\" 1
+  |       ^"
+        );
+
+        // The whole span is marked, and tabs are kept to line up with it
+        assert_eq!(
+            render("[1,\r\n\t-01]"),
+            "error: numbers cannot have leading zeros
+ --> input.json:2:2
+  |
+2 | \t-01]
+  | \t^^^"
+        );
+
+        // Spans at the end of the input are marked after the last character
+        assert_eq!(
+            render("[\"é"),
+            "error: string is never closed
+ --> input.json:1:4
+  |
+1 | [\"é
+  |    ^"
+        );
+
+        assert_eq!(
+            render("  "),
+            "error: unexpected end of input
+ --> input.json:1:3
+  |
+1 |   
+  |   ^"
+        );
+        assert_eq!(
+            render(""),
+            "error: unexpected end of input
+ --> input.json:1:1
+  |
+1 | 
+  | ^"
+        );
+        assert_eq!(
+            render("\r\n\t "),
+            "error: unexpected end of input
+ --> input.json:2:3
+  |
+2 | \t 
+  | \t ^"
+        );
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let error = Parser::parse_bytes(b"[\"a\xffb\"]").unwrap_err();
+        assert_eq!(
+            Diagnostic::new(&error).to_string(),
+            "error: input is not valid UTF-8
+ --> 1:4
+  |
+1 | [\"a\u{fffd}b\"]
+  |    ^"
+        );
+    }
+
+    #[test]
+    fn colors() {
+        let error = Parser::parse_str("[1 2]").unwrap_err();
+        let rendered = Diagnostic::new(&error).colored(true).to_string();
+        assert!(rendered.starts_with(
+            "\x1b[1;31merror\x1b[0m: \x1b[1mexpected `,` or `]` after array item\x1b[0m"
+        ));
+        assert!(rendered.ends_with("\x1b[1;31m^\x1b[0m"));
+        assert!(!Diagnostic::new(&error).to_string().contains('\x1b'));
+    }
+}
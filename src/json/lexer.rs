@@ -1,15 +1,28 @@
-use std::{fmt::Display, iter::Peekable, str::Chars};
+use std::fmt::Display;
 
 use TokenizeError::*;
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenizeError<'src> {
     EOF(Span<'src>),
     InvalidLiteral(Span<'src>),
     NewlineInString(Span<'src>),
+    /// A string contains a control character other than a newline, which
+    /// must be escaped
+    ControlInString(Span<'src>),
     InvalidEscape(Span<'src>),
     UnterminatedString(Span<'src>),
+    UnterminatedComment(Span<'src>),
     IllegalWhitespace(Span<'src>),
+    /// The input is not valid UTF-8 at the start of the span
+    InvalidUtf8(Span<'src>),
+    /// The string that starts the span goes on for longer than the lexer
+    /// allows, and the span ends where that was found
+    StringTooLong(Span<'src>),
+    /// The number that starts the span goes on for longer than the lexer
+    /// allows, and the span ends where that was found
+    NumberTooLong(Span<'src>),
 }
 
 impl<'src> TokenizeError<'src> {
@@ -18,79 +31,126 @@ impl<'src> TokenizeError<'src> {
             EOF(span)
             | InvalidLiteral(span)
             | NewlineInString(span)
+            | ControlInString(span)
             | InvalidEscape(span)
             | UnterminatedString(span)
-            | IllegalWhitespace(span) => span,
+            | UnterminatedComment(span)
+            | IllegalWhitespace(span)
+            | InvalidUtf8(span)
+            | StringTooLong(span)
+            | NumberTooLong(span) => span,
         }
     }
 }
 
+impl<'src> Display for TokenizeError<'src> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EOF(_) => write!(f, "unexpected end of input"),
+            InvalidLiteral(_) => write!(
+                f,
+                "expected a string, number, object, array, `true`, `false` or `null`"
+            ),
+            NewlineInString(_) => write!(f, "newlines in strings must be escaped as `\\n`"),
+            ControlInString(_) => write!(f, "control characters in strings must be escaped"),
+            InvalidEscape(_) => write!(f, "invalid escape in string"),
+            UnterminatedString(_) => write!(f, "string is never closed"),
+            UnterminatedComment(_) => write!(f, "comment is never closed"),
+            IllegalWhitespace(_) => write!(f, "this whitespace is not allowed in JSON"),
+            InvalidUtf8(_) => write!(f, "input is not valid UTF-8"),
+            StringTooLong(_) => write!(f, "string is too long"),
+            NumberTooLong(_) => write!(f, "number is too long"),
+        }
+    }
+}
+
+/// A range of bytes in the source, along with the line and column that it
+/// starts at
+///
+/// Lines and columns start at zero, and columns count characters rather
+/// than bytes.
 #[derive(Clone, PartialEq)]
 pub struct Span<'src> {
-    source: Option<&'src str>,
-    source_offset: usize,
+    source: &'src [u8],
+    offset: usize,
     line: usize,
-    line_offset: usize,
+    column: usize,
     len: usize,
 }
 
 impl<'src> std::fmt::Debug for Span<'src> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Span")
-            .field("source_offset", &self.source_offset)
+            .field("offset", &self.offset)
             .field("line", &self.line)
-            .field("line_offset", &self.line_offset)
+            .field("column", &self.column)
             .field("len", &self.len)
             .finish()
     }
 }
 
 impl<'src> Span<'src> {
-    pub fn new(
-        source: Option<&'src str>,
-        source_offset: usize,
-        line: usize,
-        line_offset: usize,
-        len: usize,
-    ) -> Self {
+    pub fn new(source: &'src [u8], offset: usize, line: usize, column: usize, len: usize) -> Self {
         Self {
             source,
-            source_offset,
+            offset,
             line,
-            line_offset,
+            column,
             len,
         }
     }
 
-    pub fn lexeme(&self) -> Option<String> {
-        self.source.map(|val| {
-            val.chars()
-                .skip(self.source_offset)
-                .take(self.len)
-                .collect()
-        })
+    /// The empty span at the end of `source`
+    pub fn end(source: &'src [u8]) -> Self {
+        let start = source
+            .iter()
+            .rposition(|&byte| byte == b'\n')
+            .map_or(0, |newline| newline + 1);
+        let line = source.iter().filter(|&&byte| byte == b'\n').count();
+        // Bytes that continue a UTF-8 character are not counted
+        let column = source[start..]
+            .iter()
+            .filter(|&&byte| byte & 0xc0 != 0x80)
+            .count();
+        Self::new(source, source.len(), line, column, 0)
+    }
+
+    /// The bytes of the source that this span covers, or `None` if it
+    /// extends past the end of the source
+    pub fn bytes(&self) -> Option<&'src [u8]> {
+        self.source.get(self.offset..self.offset + self.len)
+    }
+
+    /// The text that this span covers, or `None` if it extends past the
+    /// end of the source or is not valid UTF-8
+    pub fn lexeme(&self) -> Option<&'src str> {
+        std::str::from_utf8(self.bytes()?).ok()
     }
 
-    pub fn line_offset(&self) -> usize {
-        self.line_offset
+    /// The offset in bytes of the start of this span in the source
+    pub fn offset(&self) -> usize {
+        self.offset
     }
 
-    pub fn source(&self) -> Option<&str> {
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn source(&self) -> &'src [u8] {
         self.source
     }
 
+    /// The length of this span in bytes
     pub fn len(&self) -> usize {
         self.len
     }
 
-    fn inc_ptr(&mut self, newline: bool) {
-        if !newline {
-            self.line_offset += 1;
-        } else {
-            self.line_offset = 0;
-            self.line += 1;
-        }
-        self.source_offset += 1;
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
     }
 }
 
@@ -99,7 +159,7 @@ impl<'src> Display for Span<'src> {
         if let Some(lexeme) = self.lexeme() {
             write!(f, "{}", lexeme)
         } else {
-            write!(f, "Character {} of input", self.source_offset)
+            write!(f, "Byte {} of input", self.offset)
         }
     }
 }
@@ -111,11 +171,39 @@ pub enum Literal<'src> {
     True(Span<'src>),
     False(Span<'src>),
     Null(Span<'src>),
+    /// A name that is not quoted, which JSON5 allows for object members
+    Identifier(Span<'src>, String),
+}
+
+impl<'src> Literal<'src> {
+    /// The span of the literal and the name it gives an object member, if
+    /// it can name one in `syntax`
+    ///
+    /// JSON5 names members with any identifier, including those that are
+    /// spelled like `null`, `true`, `false`, `Infinity` and `NaN`.
+    pub fn into_name(self, syntax: Syntax) -> Result<(Span<'src>, String), Self> {
+        match self {
+            Literal::String(span, name) | Literal::Identifier(span, name) => Ok((span, name)),
+            Literal::Null(span) | Literal::True(span) | Literal::False(span)
+                if syntax == Syntax::Json5 =>
+            {
+                let name = span.lexeme().unwrap_or_default().to_string();
+                Ok((span, name))
+            }
+            Literal::Number(span, number)
+                if syntax == Syntax::Json5 && matches!(number.as_str(), "Infinity" | "NaN") =>
+            {
+                Ok((span, number))
+            }
+            literal => Err(literal),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token<'src> {
     Whitespace(Span<'src>),
+    Comment(Span<'src>),
     Literal(Literal<'src>),
     ObjectStart(Span<'src>),
     ObjectEnd(Span<'src>),
@@ -135,6 +223,7 @@ impl<'src> Token<'src> {
     pub fn span(&self) -> Span<'src> {
         match self {
             Token::Whitespace(span)
+            | Token::Comment(span)
             | Token::ObjectStart(span)
             | Token::ObjectEnd(span)
             | Token::ArrayStart(span)
@@ -146,281 +235,760 @@ impl<'src> Token<'src> {
                 | Literal::Null(span)
                 | Literal::False(span)
                 | Literal::True(span)
-                | Literal::Number(span, _) => span.clone(),
+                | Literal::Number(span, _)
+                | Literal::Identifier(span, _) => span.clone(),
             },
         }
     }
 }
 
+/// The dialect of JSON that a [`Lexer`] accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Syntax {
+    /// JSON as RFC 8259 defines it
+    #[default]
+    Json,
+    /// JSON with `//` and `/* */` comments and trailing commas, as
+    /// configuration files are often written in
+    Jsonc,
+    /// JSON5, which adds to JSONC single-quoted strings, unquoted member
+    /// names, hexadecimal numbers, `Infinity`, `NaN`, leading `+` signs and
+    /// leading or trailing decimal points
+    Json5,
+}
+
+/// The character at the start of `bytes`, if they start with one that is
+/// valid UTF-8
+fn decode_char(bytes: &[u8]) -> Option<char> {
+    let bytes = &bytes[..bytes.len().min(4)];
+    let valid = match std::str::from_utf8(bytes) {
+        Ok(valid) => valid,
+        Err(error) => std::str::from_utf8(&bytes[..error.valid_up_to()]).ok()?,
+    };
+    valid.chars().next()
+}
+
+/// Whether `char` is whitespace or a line terminator in JSON5, which are
+/// the ones of ECMAScript 5.1: a few control characters, the byte order
+/// mark and the space separators of Unicode
+fn is_json5_whitespace(char: char) -> bool {
+    matches!(
+        char,
+        '\t' | '\n' | '\u{b}' | '\u{c}' | '\r' | '\u{feff}' | '\u{2028}' | '\u{2029}'
+    ) || matches!(
+        char,
+        ' ' | '\u{a0}' | '\u{1680}' | '\u{202f}' | '\u{205f}' | '\u{3000}'
+    ) || ('\u{2000}'..='\u{200a}').contains(&char)
+}
+
+/// Whether `char` can be in a JSON5 identifier, or start one if it is
+/// `first`
+fn is_identifier_char(char: char, first: bool) -> bool {
+    char.is_alphabetic()
+        || matches!(char, '$' | '_')
+        || (!first && (char.is_alphanumeric() || matches!(char, '\u{200c}' | '\u{200d}')))
+}
+
+/// Splits a source into tokens, validating UTF-8 as it goes
 pub struct Lexer<'src> {
-    current_loc: Span<'src>,
+    source: &'src [u8],
+    offset: usize,
+    line: usize,
+    column: usize,
+    syntax: Syntax,
+    max_string_length: usize,
+    max_number_length: usize,
 }
 
 type LiteralResult<'src> = Result<Literal<'src>, TokenizeError<'src>>;
-type SpanResult<'src> = Result<Span<'src>, TokenizeError<'src>>;
 type TokenizeResult<'src> = Result<Vec<Token<'src>>, TokenizeError<'src>>;
 
 impl<'src> Lexer<'src> {
-    pub fn new(source: Option<&'src str>) -> Self {
+    pub fn new(source: &'src [u8]) -> Self {
         Self {
-            current_loc: Span::new(source, 0, 0, 0, 0),
+            source,
+            offset: 0,
+            line: 0,
+            column: 0,
+            syntax: Syntax::default(),
+            max_string_length: usize::MAX,
+            max_number_length: usize::MAX,
         }
     }
 
+    /// Accept the relaxed `syntax` rather than strict JSON
+    pub fn syntax(mut self, syntax: Syntax) -> Self {
+        self.syntax = syntax;
+        self
+    }
+
+    /// Stop with an error as soon as a string or an unquoted name is found
+    /// to take up more than `len` bytes of the source, not counting quotes
+    pub fn max_string_length(mut self, len: usize) -> Self {
+        self.max_string_length = len;
+        self
+    }
+
+    /// Stop with an error as soon as a number is found to take up more than
+    /// `len` bytes of the source
+    pub fn max_number_length(mut self, len: usize) -> Self {
+        self.max_number_length = len;
+        self
+    }
+
     pub fn lex_str(input: &'src str) -> TokenizeResult<'src> {
-        let me = Self::new(Some(input));
+        Self::lex_bytes(input.as_bytes())
+    }
+
+    pub fn lex_bytes(input: &'src [u8]) -> TokenizeResult<'src> {
         let mut tokens = Vec::new();
-        me.lex_into(input.chars(), &mut tokens)?;
+        Self::new(input).lex_into(&mut tokens)?;
         Ok(tokens)
     }
 
-    pub fn lex_chars(chars: Chars<'src>) -> TokenizeResult<'src> {
-        let me = Self::new(None);
-
-        let mut tokens = Vec::new();
+    pub fn lex_into(mut self, tokens: &mut Vec<Token<'src>>) -> Result<(), TokenizeError<'src>> {
+        while let Some(token) = self.next_token()? {
+            tokens.push(token);
+        }
+        Ok(())
+    }
 
-        me.lex_into(chars, &mut tokens)?;
+    /// Lex the next token of the source, or `None` at the end of it
+    pub fn next_token(&mut self) -> Result<Option<Token<'src>>, TokenizeError<'src>> {
+        let byte = match self.peek() {
+            Some(byte) => byte,
+            None => return Ok(None),
+        };
 
-        Ok(tokens)
+        let token = match byte {
+            b'{' => Token::ObjectStart(self.advance(1)),
+            b'}' => Token::ObjectEnd(self.advance(1)),
+            b',' => Token::Comma(self.advance(1)),
+            b'[' => Token::ArrayStart(self.advance(1)),
+            b']' => Token::ArrayEnd(self.advance(1)),
+            b':' => Token::Colon(self.advance(1)),
+            b' ' | b'\n' | b'\r' | b'\t' => Token::Whitespace(self.lex_whitespace()),
+            b'/' if self.syntax != Syntax::Json
+                && matches!(self.source.get(self.offset + 1), Some(b'/' | b'*')) =>
+            {
+                Token::Comment(self.lex_comment()?)
+            }
+            _ if self.syntax == Syntax::Json5 && self.at_whitespace() => {
+                Token::Whitespace(self.lex_whitespace())
+            }
+            _ => match self.lex_literal() {
+                Ok(literal) => Token::Literal(literal),
+                // Nothing is lexed after a limit is gone over
+                Err(error @ (StringTooLong(_) | NumberTooLong(_))) => return Err(error),
+                Err(error) => {
+                    self.skip_invalid(matches!(byte, b'"' | b'\'').then_some(byte));
+                    return Err(error);
+                }
+            },
+        };
+        Ok(Some(token))
     }
 
-    pub fn lex_into(
-        self,
-        chars: Chars<'src>,
-        tokens: &mut Vec<Token<'src>>,
-    ) -> Result<(), TokenizeError<'src>> {
-        let mut chars = chars.peekable();
-        let mut current_loc = self.current_loc;
-        loop {
-            if let Some(next_char) = chars.peek() {
-                let single_chars = ['{', '}', ',', '[', ']', ':'];
-                if single_chars.contains(next_char) {
-                    let char = chars.next().unwrap();
-                    let mut start_loc = current_loc.clone();
-                    start_loc.len = 1;
-
-                    current_loc.inc_ptr(false);
-
-                    if char == '{' {
-                        tokens.push(Token::ObjectStart(start_loc));
-                    } else if char == '}' {
-                        tokens.push(Token::ObjectEnd(start_loc));
-                    } else if char == ',' {
-                        tokens.push(Token::Comma(start_loc));
-                    } else if char == '[' {
-                        tokens.push(Token::ArrayStart(start_loc));
-                    } else if char == ']' {
-                        tokens.push(Token::ArrayEnd(start_loc));
-                    } else if char == ':' {
-                        tokens.push(Token::Colon(start_loc));
-                    }
-                } else if next_char.is_whitespace() {
-                    tokens.push(Token::Whitespace(Self::lex_whitespace(
-                        &mut current_loc,
-                        &mut chars,
-                    )?));
-                } else {
-                    tokens.push(Token::Literal(Self::lex_literal(
-                        &mut current_loc,
-                        &mut chars,
-                    )?));
+    /// Move past the rest of a literal that could not be lexed, so that
+    /// lexing can go on after it: to the end of the string that starts with
+    /// `quote` it is in, or else to the next whitespace or punctuation
+    fn skip_invalid(&mut self, quote: Option<u8>) {
+        let in_string = quote.is_some();
+        let mut escaped = false;
+        while let Some(byte) = self.peek() {
+            match byte {
+                b'\n' | b'\r' if in_string => break,
+                _ if Some(byte) == quote && !escaped => {
+                    self.advance(1);
+                    break;
                 }
-            } else {
-                break;
+                b' ' | b'\t' | b'\n' | b'\r' | b',' | b':' | b'[' | b']' | b'{' | b'}' | b'"'
+                    if !in_string =>
+                {
+                    break
+                }
+                _ => {}
             }
+            escaped = byte == b'\\' && !escaped;
+            let len = decode_char(&self.source[self.offset..]).map_or(1, char::len_utf8);
+            self.advance(len);
         }
-        Ok(())
     }
 
-    fn lex_whitespace(
-        current_loc: &mut Span<'src>,
-        chars: &mut Peekable<impl Iterator<Item = char>>,
-    ) -> SpanResult<'src> {
-        let mut my_loc = current_loc.clone();
+    fn peek(&self) -> Option<u8> {
+        self.source.get(self.offset).copied()
+    }
 
+    /// A span of `len` bytes from the current position
+    fn span(&self, len: usize) -> Span<'src> {
+        Span::new(self.source, self.offset, self.line, self.column, len)
+    }
+
+    /// The span from `start` to the current position
+    fn since(&self, start: Span<'src>) -> Span<'src> {
+        Span {
+            len: self.offset - start.offset,
+            ..start
+        }
+    }
+
+    /// A span of the character at the current position, to report an
+    /// error at
+    fn error_span(&self) -> Span<'src> {
+        let len = decode_char(&self.source[self.offset..]).map_or(1, char::len_utf8);
+        self.span(len)
+    }
+
+    /// Move past the `len` bytes at the current position, which must not
+    /// include a newline unless it is the only one, and return their span
+    fn advance(&mut self, len: usize) -> Span<'src> {
+        let span = self.span(len);
+        if self.source[self.offset] == b'\n' {
+            self.line += 1;
+            self.column = 0;
+        } else {
+            self.column += 1;
+        }
+        self.offset += len;
+        span
+    }
+
+    /// Move past the character at the current position, after checking
+    /// that it is valid UTF-8
+    fn advance_char(&mut self) -> Result<char, TokenizeError<'src>> {
+        let char =
+            decode_char(&self.source[self.offset..]).ok_or_else(|| InvalidUtf8(self.span(1)))?;
+        self.advance(char.len_utf8());
+        Ok(char)
+    }
+
+    /// Whether the current position is at whitespace, which JSON5 allows
+    /// more kinds of than JSON
+    fn at_whitespace(&self) -> bool {
+        match self.peek() {
+            Some(b' ' | b'\n' | b'\r' | b'\t') => true,
+            _ if self.syntax == Syntax::Json5 => {
+                decode_char(&self.source[self.offset..]).is_some_and(is_json5_whitespace)
+            }
+            _ => false,
+        }
+    }
+
+    fn lex_whitespace(&mut self) -> Span<'src> {
+        let start = self.span(0);
+        while self.at_whitespace() {
+            let len = decode_char(&self.source[self.offset..]).map_or(1, char::len_utf8);
+            self.advance(len);
+        }
+        Span {
+            len: self.offset - start.offset,
+            ..start
+        }
+    }
+
+    /// Lex a `//` comment up to the end of its line, or a `/* */` comment
+    fn lex_comment(&mut self) -> Result<Span<'src>, TokenizeError<'src>> {
+        let start = self.span(0);
+        let block = self.source[self.offset + 1] == b'*';
+        self.advance(1);
+        self.advance(1);
         loop {
-            if let Some(char) = chars.peek() {
-                if char.is_whitespace() {
-                    if char != &0x20.into()
-                        && char != &0x0A.into()
-                        && char != &0x0D.into()
-                        && char != &0x09.into()
-                    {
-                        return Err(TokenizeError::IllegalWhitespace(Self::into_err_span(
-                            &my_loc,
-                        )));
-                    }
-                } else {
+            match self.peek() {
+                Some(b'\n' | b'\r') if !block => break,
+                Some(b'*') if block && self.source.get(self.offset + 1) == Some(&b'/') => {
+                    self.advance(1);
+                    self.advance(1);
                     break;
                 }
-
-                my_loc.len += 1;
-                current_loc.inc_ptr(char == &'\n');
-                chars.next();
-            } else {
-                break;
+                Some(_) => {
+                    self.advance_char()?;
+                }
+                None if block => return Err(UnterminatedComment(Span { len: 2, ..start })),
+                None => break,
             }
         }
-        Ok(my_loc)
+        Ok(Span {
+            len: self.offset - start.offset,
+            ..start
+        })
     }
 
-    fn lex_literal(
-        current_loc: &mut Span<'src>,
-        chars: &mut Peekable<impl Iterator<Item = char>>,
-    ) -> LiteralResult<'src> {
-        if let Some(char) = chars.peek() {
-            if char.is_numeric() || char == &'-' {
-                let (span, string) = Self::lex_number(current_loc, chars)?;
+    fn lex_literal(&mut self) -> LiteralResult<'src> {
+        match self.peek() {
+            Some(b'0'..=b'9' | b'-') => {
+                let (span, string) = self.lex_number()?;
                 Ok(Literal::Number(span, string))
-            } else if char == &'"' {
-                let (span, string) = Self::lex_string(current_loc, chars)?;
+            }
+            Some(b'+' | b'.') if self.syntax == Syntax::Json5 => {
+                let (span, string) = self.lex_number()?;
+                Ok(Literal::Number(span, string))
+            }
+            Some(b'"') => {
+                let (span, string) = self.lex_string()?;
                 Ok(Literal::String(span, string))
-            } else if let Some(span) = Self::lex_word_literal(current_loc, chars) {
-                Ok(span)
-            } else {
-                Err(InvalidLiteral(current_loc.clone()))
             }
-        } else {
-            Err(EOF(current_loc.clone()))
+            Some(b'\'') if self.syntax == Syntax::Json5 => {
+                let (span, string) = self.lex_string()?;
+                Ok(Literal::String(span, string))
+            }
+            Some(_) if self.syntax == Syntax::Json5 && self.at_identifier(true) => {
+                self.lex_identifier()
+            }
+            Some(b'n' | b't' | b'f') => self.lex_word_literal(),
+            Some(_) => match decode_char(&self.source[self.offset..]) {
+                Some(char) if char.is_whitespace() => Err(IllegalWhitespace(self.error_span())),
+                Some(_) => Err(InvalidLiteral(self.error_span())),
+                None => Err(InvalidUtf8(self.span(1))),
+            },
+            None => Err(EOF(self.span(0))),
         }
     }
 
-    fn into_err_span(span: &Span<'src>) -> Span<'src> {
-        let mut clone = span.clone();
-        clone.len += 1;
-        clone
-    }
-
-    fn lex_number(
-        current_loc: &mut Span<'src>,
-        chars: &mut Peekable<impl Iterator<Item = char>>,
-    ) -> Result<(Span<'src>, String), TokenizeError<'src>> {
-        let mut start_loc = current_loc.clone();
-        let mut number = String::new();
-        while let Some(char) = chars.peek() {
-            if char.is_numeric() || matches!(char, '-' | 'e' | 'E' | '.' | '+') {
-                number.push(*char);
-                start_loc.len += 1;
-                chars.next();
-                current_loc.inc_ptr(false);
-            } else {
-                break;
+    /// Lex the characters that a number can consist of, which the parser
+    /// checks are in order. In JSON5 those include letters, for hexadecimal
+    /// numbers and `Infinity`.
+    fn lex_number(&mut self) -> Result<(Span<'src>, String), TokenizeError<'src>> {
+        let start = self.span(0);
+        while let Some(byte) = self.peek() {
+            match byte {
+                b'0'..=b'9' | b'-' | b'e' | b'E' | b'.' | b'+' => {}
+                _ if self.syntax == Syntax::Json5 && byte.is_ascii_alphanumeric() => {}
+                _ => break,
+            }
+            self.advance(1);
+            if self.offset - start.offset > self.max_number_length {
+                return Err(NumberTooLong(self.since(start)));
             }
         }
-        Ok((start_loc, number))
+
+        let span = self.since(start);
+        // Numbers only consist of ASCII
+        let number = span.lexeme().unwrap_or_default().to_string();
+        Ok((span, number))
     }
 
-    fn lex_string(
-        current_loc: &mut Span<'src>,
-        chars: &mut Peekable<impl Iterator<Item = char>>,
-    ) -> Result<(Span<'src>, String), TokenizeError<'src>> {
-        let mut start_loc = current_loc.clone();
+    /// Lex a string, decoding its escapes
+    ///
+    /// A `\u` escape of half of a surrogate pair that is not part of a
+    /// whole pair decodes to U+FFFD, as the grammar allows it but it has
+    /// no meaning.
+    fn lex_string(&mut self) -> Result<(Span<'src>, String), TokenizeError<'src>> {
+        let start = self.span(0);
+        let quote = self.source[self.offset];
+        self.advance(1);
+
         let mut string = String::new();
+        // Where the characters that have not been added to `string` start
+        let mut run = self.offset;
+        loop {
+            // Whatever has been lexed apart from the opening quote
+            if self.offset - start.offset - 1 > self.max_string_length {
+                return Err(StringTooLong(self.since(start)));
+            }
+            match self.peek() {
+                Some(byte) if byte == quote => {
+                    string.push_str(self.run(run));
+                    self.advance(1);
+                    break;
+                }
+                Some(b'\\') => {
+                    string.push_str(self.run(run));
+                    self.advance(1);
+                    string.extend(self.lex_escape()?);
+                    run = self.offset;
+                }
+                Some(b'\n' | b'\r') => return Err(NewlineInString(self.span(1))),
+                Some(byte) if byte < 0x20 && self.syntax != Syntax::Json5 => {
+                    return Err(ControlInString(self.span(1)))
+                }
+                Some(byte) if byte.is_ascii() => {
+                    self.advance(1);
+                }
+                Some(_) => {
+                    self.advance_char()?;
+                }
+                None => return Err(UnterminatedString(self.span(1))),
+            }
+        }
 
-        let mut inc = || {
-            start_loc.len += 1;
-            current_loc.inc_ptr(false);
+        Ok((self.since(start), string))
+    }
+
+    /// The characters from `start` to the current position, which were
+    /// checked to be valid UTF-8
+    fn run(&self, start: usize) -> &'src str {
+        std::str::from_utf8(&self.source[start..self.offset]).unwrap_or_default()
+    }
+
+    /// Lex the escape after a backslash in a string, which is of no
+    /// character if it continues the string on the next line
+    fn lex_escape(&mut self) -> Result<Option<char>, TokenizeError<'src>> {
+        let char = match self.peek() {
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\u{8}',
+            Some(b'f') => '\u{c}',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => {
+                self.advance(1);
+                return self.lex_unicode_escape().map(Some);
+            }
+            _ if self.syntax == Syntax::Json5 => return self.lex_json5_escape(),
+            _ => return Err(InvalidEscape(self.error_span())),
         };
+        self.advance(1);
+        Ok(Some(char))
+    }
 
-        let mut in_string = false;
-        loop {
-            if let Some(char) = chars.peek() {
-                if !in_string && char == &'"' {
-                    chars.next();
-                    inc();
-                    in_string = true;
-                } else if char == &'\\' {
-                    string.push(*char);
-                    chars.next();
-                    inc();
-
-                    let next_char = chars.peek();
-                    if let Some(next_char) = next_char {
-                        if next_char == &'\\'
-                            || next_char == &'/'
-                            || next_char == &'b'
-                            || next_char == &'f'
-                            || next_char == &'n'
-                            || next_char == &'r'
-                            || next_char == &'t'
-                            || next_char == &'"'
-                        {
-                            string.push(*next_char);
-                            inc();
-                            chars.next();
-                        } else if next_char == &'u' {
-                            string.push(*next_char);
-                            chars.next();
-                            inc();
-                            let hex_chars: Vec<char> = chars
-                                .take(4)
-                                .filter(|char| {
-                                    char.is_numeric()
-                                        || (char >= &'a' && char <= &'f')
-                                        || (char >= &'A' && char <= &'F')
-                                })
-                                .collect();
-
-                            if hex_chars.len() == 4 {
-                                for c in hex_chars {
-                                    string.push(c);
-                                    inc();
-                                }
-                            } else {
-                                return Err(InvalidEscape(Self::into_err_span(current_loc)));
-                            }
-                        } else {
-                            return Err(InvalidEscape(Self::into_err_span(current_loc)));
-                        }
-                    } else {
-                        return Err(InvalidEscape(Self::into_err_span(current_loc)));
-                    }
-                } else if in_string && char == &'"' {
-                    chars.next();
-                    inc();
-                    return Ok((start_loc, string));
-                } else if char <= &'\n' {
-                    return Err(NewlineInString(Self::into_err_span(current_loc)));
-                } else {
-                    string.push(*char);
-                    chars.next();
-                    inc();
+    /// Lex an escape that only JSON5 has. Characters other than digits,
+    /// `x` and `u` escape themselves.
+    fn lex_json5_escape(&mut self) -> Result<Option<char>, TokenizeError<'src>> {
+        let next_is_digit = self
+            .source
+            .get(self.offset + 1)
+            .is_some_and(u8::is_ascii_digit);
+        let char = match self.peek() {
+            Some(b'v') => '\u{b}',
+            Some(b'0') if !next_is_digit => '\0',
+            Some(b'x') => {
+                self.advance(1);
+                let value = self.lex_hex(2)?;
+                return Ok(char::from_u32(value));
+            }
+            Some(b'\r') => {
+                self.advance(1);
+                if self.peek() == Some(b'\n') {
+                    self.advance(1);
                 }
-            } else {
-                return Err(UnterminatedString(Self::into_err_span(current_loc)));
+                return Ok(None);
+            }
+            Some(b'\n') => {
+                self.advance(1);
+                return Ok(None);
+            }
+            Some(b'0'..=b'9') | None => return Err(InvalidEscape(self.error_span())),
+            Some(_) => {
+                return match self.advance_char()? {
+                    '\u{2028}' | '\u{2029}' => Ok(None),
+                    char => Ok(Some(char)),
+                }
+            }
+        };
+        self.advance(1);
+        Ok(Some(char))
+    }
+
+    /// Lex the hex digits of a `\u` escape, along with the escape of the
+    /// low surrogate that follows if they are of a high one
+    fn lex_unicode_escape(&mut self) -> Result<char, TokenizeError<'src>> {
+        let unit = self.lex_hex(4)?;
+        if !(0xd800..0xdc00).contains(&unit) {
+            return Ok(char::from_u32(unit).unwrap_or(char::REPLACEMENT_CHARACTER));
+        }
+
+        let (offset, line, column) = (self.offset, self.line, self.column);
+        if self.source[offset..].starts_with(b"\\u") {
+            self.advance(1);
+            self.advance(1);
+            if let Ok(low @ 0xdc00..0xe000) = self.lex_hex(4) {
+                let char = 0x10000 + ((unit - 0xd800) << 10) + (low - 0xdc00);
+                return Ok(char::from_u32(char).unwrap_or(char::REPLACEMENT_CHARACTER));
             }
         }
+
+        // Whatever follows the lone high surrogate is lexed as usual
+        (self.offset, self.line, self.column) = (offset, line, column);
+        Ok(char::REPLACEMENT_CHARACTER)
     }
 
-    fn lex_word_literal(
-        current_loc: &mut Span<'src>,
-        chars: &mut Peekable<impl Iterator<Item = char>>,
-    ) -> Option<Literal<'src>> {
-        let mut start_loc = current_loc.clone();
-        let start_char = chars.peek().cloned();
+    /// Lex `digits` hexadecimal digits
+    fn lex_hex(&mut self, digits: usize) -> Result<u32, TokenizeError<'src>> {
+        let mut unit = 0;
+        for _ in 0..digits {
+            match self.peek().and_then(|byte| char::from(byte).to_digit(16)) {
+                Some(digit) => {
+                    unit = unit * 16 + digit;
+                    self.advance(1);
+                }
+                None => return Err(InvalidEscape(self.error_span())),
+            }
+        }
+        Ok(unit)
+    }
+
+    /// Whether the character at the current position can be in a JSON5
+    /// identifier, or start one if it is `first`. A `\u` escape can stand
+    /// for any of them.
+    fn at_identifier(&self, first: bool) -> bool {
+        self.source[self.offset..].starts_with(b"\\u")
+            || decode_char(&self.source[self.offset..])
+                .is_some_and(|char| is_identifier_char(char, first))
+    }
 
-        let test = |expected: &str| {
-            let length = expected.len();
-            let string: String = chars.take(length).collect();
-            if string == expected {
-                for _ in 0..length {
-                    current_loc.inc_ptr(false);
+    /// Lex a JSON5 identifier, decoding its escapes, which is one of the
+    /// literals or numbers if it is named like them without any
+    fn lex_identifier(&mut self) -> LiteralResult<'src> {
+        let start = self.span(0);
+        // `Infinity` and `NaN` are numbers, and names other than those and
+        // `true`, `false` and `null` are strings
+        let max_len = self
+            .max_string_length
+            .max(self.max_number_length)
+            .max("Infinity".len());
+        let mut name = String::new();
+        while self.at_identifier(name.is_empty()) {
+            if self.peek() == Some(b'\\') {
+                let escape = self.span(0);
+                self.advance(1);
+                self.advance(1);
+                match char::from_u32(self.lex_hex(4)?) {
+                    Some(char) if is_identifier_char(char, name.is_empty()) => name.push(char),
+                    _ => return Err(InvalidEscape(self.since(escape))),
                 }
-                start_loc.len = length;
-                Some(start_loc)
             } else {
-                None
+                name.push(self.advance_char()?);
+            }
+            if self.offset - start.offset > max_len {
+                break;
             }
+        }
+
+        let span = self.since(start);
+        Ok(match span.lexeme().unwrap_or_default() {
+            "null" => Literal::Null(span),
+            "true" => Literal::True(span),
+            "false" => Literal::False(span),
+            "Infinity" | "NaN" if span.len() > self.max_number_length => {
+                return Err(NumberTooLong(span))
+            }
+            "Infinity" | "NaN" => Literal::Number(span, name),
+            _ if span.len() > self.max_string_length => return Err(StringTooLong(span)),
+            _ => Literal::Identifier(span, name),
+        })
+    }
+
+    fn lex_word_literal(&mut self) -> LiteralResult<'src> {
+        let rest = &self.source[self.offset..];
+        let (word, literal): (&[u8], fn(Span<'src>) -> Literal<'src>) = match rest.first() {
+            Some(b'n') => (b"null", Literal::Null),
+            Some(b't') => (b"true", Literal::True),
+            _ => (b"false", Literal::False),
         };
 
-        if let Some('n') = start_char {
-            test("null").map(Literal::Null)
-        } else if let Some('t') = start_char {
-            test("true").map(Literal::True)
-        } else if let Some('f') = start_char {
-            test("false").map(Literal::False)
-        } else {
-            None
+        if !rest.starts_with(word) {
+            return Err(InvalidLiteral(self.error_span()));
         }
+
+        let span = self.span(word.len());
+        self.offset += word.len();
+        self.column += word.len();
+        Ok(literal(span))
+    }
+}
+
+impl<'src> Iterator for Lexer<'src> {
+    type Item = Result<Token<'src>, TokenizeError<'src>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_token().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Lexer, Literal, Span, Syntax, Token, TokenizeError};
+
+    #[test]
+    fn spans() {
+        let input = "{\n  \"é\": [1.5, true]\n}";
+        let tokens = Lexer::lex_str(input).unwrap();
+
+        let spans: Vec<(usize, usize, usize, &str)> = tokens
+            .iter()
+            .filter(|token| !matches!(token, Token::Whitespace(_)))
+            .map(|token| {
+                let span = token.span();
+                (
+                    span.offset(),
+                    span.line(),
+                    span.column(),
+                    span.lexeme().unwrap(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            spans,
+            vec![
+                (0, 0, 0, "{"),
+                (4, 1, 2, "\"é\""),
+                (8, 1, 5, ":"),
+                (10, 1, 7, "["),
+                (11, 1, 8, "1.5"),
+                (14, 1, 11, ","),
+                (16, 1, 13, "true"),
+                (20, 1, 17, "]"),
+                (22, 2, 0, "}"),
+            ]
+        );
+
+        let end = Span::end("é\n aé".as_bytes());
+        assert_eq!((end.offset(), end.line(), end.column()), (7, 1, 3));
+        assert!(end.is_empty());
+    }
+
+    #[test]
+    fn literals() {
+        let tokens = Lexer::lex_str(r#"["a\"b", -1e5, null]"#).unwrap();
+        assert!(matches!(
+            &tokens[1],
+            Token::Literal(Literal::String(_, string)) if string == "a\"b"
+        ));
+        assert!(matches!(
+            &tokens[4],
+            Token::Literal(Literal::Number(_, number)) if number == "-1e5"
+        ));
+        assert!(matches!(&tokens[7], Token::Literal(Literal::Null(_))));
+    }
+
+    #[test]
+    fn escapes() {
+        let string = |input| match Lexer::lex_str(input).unwrap().as_slice() {
+            [Token::Literal(Literal::String(_, string))] => string.clone(),
+            tokens => panic!("expected a string, found {:?}", tokens),
+        };
+
+        assert_eq!(string(r#""\"\\\/\b\f\n\r\t""#), "\"\\/\u{8}\u{c}\n\r\t");
+        assert_eq!(string(r#""a\u00e9éb""#), "aééb");
+        assert_eq!(string(r#""\ud83d\ude00""#), "😀");
+        assert_eq!(string("\"\u{7f}é\""), "\u{7f}é");
+
+        // Halves of surrogate pairs on their own have no meaning
+        assert_eq!(string(r#""\ude00\ud83d""#), "\u{fffd}\u{fffd}");
+        assert_eq!(string(r#""\ud83d\n""#), "\u{fffd}\n");
+        assert_eq!(string(r#""\ud83dA""#), "\u{fffd}A");
+
+        // Columns count the characters that escapes are written with
+        let tokens = Lexer::lex_str(r#"["\ud83d\ude00", 1]"#).unwrap();
+        assert_eq!(tokens[4].span().column(), 17);
+    }
+
+    #[test]
+    fn errors() {
+        let error = |input: &'static [u8]| Lexer::lex_bytes(input).unwrap_err();
+
+        assert!(matches!(error(b"[nul]"), TokenizeError::InvalidLiteral(_)));
+        assert!(matches!(error(b"\"\\x\""), TokenizeError::InvalidEscape(_)));
+        assert!(matches!(
+            error(b"\"\\u12g4\""),
+            TokenizeError::InvalidEscape(_)
+        ));
+        assert!(matches!(
+            error(b"\"a\nb\""),
+            TokenizeError::NewlineInString(_)
+        ));
+        assert!(matches!(
+            error(b"\"a\tb\""),
+            TokenizeError::ControlInString(_)
+        ));
+        assert!(matches!(
+            error(b"\"\x1f\""),
+            TokenizeError::ControlInString(_)
+        ));
+        assert!(matches!(error(b"\"\\"), TokenizeError::InvalidEscape(_)));
+        assert!(matches!(
+            error(b"\"abc"),
+            TokenizeError::UnterminatedString(_)
+        ));
+        assert!(matches!(
+            error(b"\x0c1"),
+            TokenizeError::IllegalWhitespace(_)
+        ));
+        assert!(matches!(error(b"#"), TokenizeError::InvalidLiteral(_)));
+        assert!(matches!(
+            error("\u{a0}1".as_bytes()),
+            TokenizeError::IllegalWhitespace(_)
+        ));
+
+        match error(b"[\"a\xffb\"]") {
+            TokenizeError::InvalidUtf8(span) => {
+                assert_eq!((span.offset(), span.len()), (3, 1));
+                assert_eq!(span.lexeme(), None);
+            }
+            error => panic!("expected invalid UTF-8, found {:?}", error),
+        }
+        assert!(matches!(error(b"\xe2\x82"), TokenizeError::InvalidUtf8(_)));
+    }
+
+    #[test]
+    fn syntaxes() {
+        let lex = |input: &'static str, syntax| {
+            let mut lexer = Lexer::new(input.as_bytes()).syntax(syntax);
+            std::iter::from_fn(move || lexer.next_token().transpose()).collect::<Vec<_>>()
+        };
+
+        // Comments are tokens of their own, which JSON does not have
+        let tokens = lex("[1, // one\n/* two */ 2]", Syntax::Jsonc);
+        let comments: Vec<_> = tokens
+            .iter()
+            .filter_map(|token| match token {
+                Ok(Token::Comment(span)) => span.lexeme(),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(comments, vec!["// one", "/* two */"]);
+        assert!(lex("[1, // one\n2]", Syntax::Json)
+            .iter()
+            .any(Result::is_err));
+        assert!(matches!(
+            lex("/* never closed", Syntax::Jsonc)[0],
+            Err(TokenizeError::UnterminatedComment(_))
+        ));
+        assert!(matches!(
+            lex("'a'", Syntax::Jsonc)[0],
+            Err(TokenizeError::InvalidLiteral(_))
+        ));
+
+        let literals: Vec<_> = lex(
+            "{$key_1: 'a\\'\"\\x41\\\n', nul\u{a0}: true, i: -Infinity, n: 0x1F}",
+            Syntax::Json5,
+        )
+        .into_iter()
+        .filter_map(|token| match token.unwrap() {
+            Token::Literal(Literal::String(_, string)) => Some(format!("string {}", string)),
+            Token::Literal(Literal::Identifier(_, name)) => Some(format!("identifier {}", name)),
+            Token::Literal(Literal::Number(_, number)) => Some(format!("number {}", number)),
+            Token::Literal(Literal::True(_)) => Some("true".to_string()),
+            _ => None,
+        })
+        .collect();
+        assert_eq!(
+            literals,
+            vec![
+                "identifier $key_1",
+                "string a'\"A",
+                "identifier nul",
+                "true",
+                "identifier i",
+                "number -Infinity",
+                "identifier n",
+                "number 0x1F",
+            ]
+        );
+        assert!(matches!(
+            lex("'\\1'", Syntax::Json5)[0],
+            Err(TokenizeError::InvalidEscape(_))
+        ));
+        assert!(matches!(
+            lex("\u{85}", Syntax::Json5)[0],
+            Err(TokenizeError::IllegalWhitespace(_))
+        ));
+        assert!(matches!(
+            lex("\u{2029}\u{b}", Syntax::Json5)[..],
+            [Ok(Token::Whitespace(_))]
+        ));
     }
 }
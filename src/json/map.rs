@@ -0,0 +1,276 @@
+use std::{
+    collections::HashMap,
+    hash::{BuildHasher, RandomState},
+};
+
+use hashbrown::{hash_table::Entry, HashTable};
+
+use super::Json;
+
+/// The members of a JSON object, in the order they were written
+///
+/// Names are unique unless the parser was told to keep duplicate names, in
+/// which case looking a name up finds the last member with it, while
+/// iterating visits every member. [`Map::len`] counts every member, and
+/// [`Map::name_count`] every name.
+#[derive(Clone, Default)]
+pub struct Map {
+    members: Vec<(String, Json)>,
+    /// The position of the last member with each name, hashed by the name
+    /// it has in `members`, so that names are only stored there
+    index: HashTable<usize>,
+    hasher: RandomState,
+}
+
+impl Map {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of members, counting each duplicate
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    /// The number of different names, which is the number of members
+    /// unless duplicates were kept
+    pub fn name_count(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// The position of the last member with `name`
+    fn position(&self, name: &str) -> Option<usize> {
+        self.index
+            .find(self.hasher.hash_one(name), |&position| {
+                self.members[position].0 == name
+            })
+            .copied()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Json> {
+        self.position(name)
+            .map(|position| &self.members[position].1)
+    }
+
+    /// The name and value of the member with `name`
+    pub fn get_key_value(&self, name: &str) -> Option<(&String, &Json)> {
+        self.position(name).map(|position| {
+            let (name, value) = &self.members[position];
+            (name, value)
+        })
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.position(name).is_some()
+    }
+
+    /// Set the value of the member with `name`, keeping its position if it
+    /// already exists, and return the value it replaced
+    pub fn insert(&mut self, name: String, value: Json) -> Option<Json> {
+        match self.position(&name) {
+            Some(position) => Some(std::mem::replace(&mut self.members[position].1, value)),
+            None => {
+                self.push(name, value);
+                None
+            }
+        }
+    }
+
+    /// Add a member after the others, even if one with `name` exists
+    pub fn push(&mut self, name: String, value: Json) {
+        let position = self.members.len();
+        let (members, hasher) = (&self.members, &self.hasher);
+        let entry = self.index.entry(
+            hasher.hash_one(&name),
+            |&other| members[other].0 == name,
+            |&other| hasher.hash_one(&members[other].0),
+        );
+        match entry {
+            Entry::Occupied(mut entry) => *entry.get_mut() = position,
+            Entry::Vacant(entry) => {
+                entry.insert(position);
+            }
+        }
+        self.members.push((name, value));
+    }
+
+    /// The values of every member with `name`, in order
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Json> + 'a {
+        self.iter()
+            .filter(move |(member, _)| *member == name)
+            .map(|(_, value)| value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Json)> {
+        self.members.iter().map(|(name, value)| (name, value))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.members.iter().map(|(name, _)| name)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Json> {
+        self.members.iter().map(|(_, value)| value)
+    }
+
+    /// The values of every name, in order, gathered in one pass so that
+    /// comparing maps with duplicates takes linear time
+    fn grouped(&self) -> HashMap<&str, Vec<&Json>> {
+        let mut groups: HashMap<&str, Vec<&Json>> = HashMap::with_capacity(self.name_count());
+        for (name, value) in self.iter() {
+            groups.entry(name).or_default().push(value);
+        }
+        groups
+    }
+}
+
+impl std::fmt::Debug for Map {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// Compares the values of each name, ignoring the order of the names. If
+/// duplicates were kept, every value of a name is compared, in order.
+impl PartialEq for Map {
+    fn eq(&self, other: &Self) -> bool {
+        if self.len() != other.len() || self.name_count() != other.name_count() {
+            return false;
+        }
+        // Without duplicates, each name has one value to compare
+        if self.len() == self.name_count() {
+            return self
+                .iter()
+                .all(|(name, value)| other.get(name) == Some(value));
+        }
+        self.grouped() == other.grouped()
+    }
+}
+
+impl std::ops::Index<&str> for Map {
+    type Output = Json;
+
+    fn index(&self, name: &str) -> &Json {
+        self.get(name)
+            .unwrap_or_else(|| panic!("no member named {:?}", name))
+    }
+}
+
+impl FromIterator<(String, Json)> for Map {
+    fn from_iter<I: IntoIterator<Item = (String, Json)>>(members: I) -> Self {
+        let mut map = Self::new();
+        for (name, value) in members {
+            map.insert(name, value);
+        }
+        map
+    }
+}
+
+impl From<HashMap<String, Json>> for Map {
+    fn from(members: HashMap<String, Json>) -> Self {
+        members.into_iter().collect()
+    }
+}
+
+impl IntoIterator for Map {
+    type Item = (String, Json);
+    type IntoIter = std::vec::IntoIter<(String, Json)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.members.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Map {
+    type Item = (&'a String, &'a Json);
+    type IntoIter = Box<dyn Iterator<Item = (&'a String, &'a Json)> + 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new(self.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Map;
+    use crate::json::{DuplicateKeys, Json, ParserOptions};
+
+    #[test]
+    fn members() {
+        let mut map = Map::new();
+        assert_eq!(map.insert("b".to_string(), Json::Null), None);
+        map.insert("a".to_string(), Json::Boolean(true));
+        assert_eq!(
+            map.insert("b".to_string(), Json::Boolean(false)),
+            Some(Json::Null)
+        );
+        assert_eq!(map.keys().collect::<Vec<_>>(), vec!["b", "a"]);
+        assert_eq!(map.len(), 2);
+
+        map.push("a".to_string(), Json::Null);
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.name_count(), 2);
+        assert_eq!(map["a"], Json::Null);
+        assert_eq!(
+            map.get_all("a").collect::<Vec<_>>(),
+            vec![&Json::Boolean(true), &Json::Null]
+        );
+        assert!(!map.contains_key("c"));
+
+        // Duplicates are counted and compared
+        let mut other: Map = vec![
+            ("a".to_string(), Json::Null),
+            ("b".to_string(), Json::Boolean(false)),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(map.name_count(), other.name_count());
+        assert_ne!(map, other);
+        other.insert("a".to_string(), Json::Boolean(true));
+        other.push("a".to_string(), Json::Null);
+        assert_eq!(map, other);
+    }
+
+    #[test]
+    fn duplicates() {
+        let keep_all = |input: &str| {
+            let options = ParserOptions {
+                duplicate_keys: DuplicateKeys::KeepAll,
+                ..Default::default()
+            };
+            match Json::from_string_with_options(input, options) {
+                Some(Json::Object(object)) => object,
+                json => panic!("expected an object, found {:?}", json),
+            }
+        };
+
+        let object = keep_all(r#"{"a": 1, "b": 2, "a": 3, "a": 4}"#);
+        assert_eq!(object.len(), 4);
+        assert_eq!(object.iter().count(), object.len());
+        assert_eq!(object.keys().count(), object.len());
+        assert_eq!(object.name_count(), 2);
+        assert_eq!(object["a"], Json::Number("4".to_string()));
+
+        assert_eq!(object, keep_all(r#"{"a": 1, "a": 3, "b": 2, "a": 4}"#));
+        assert_ne!(object, keep_all(r#"{"a": 3, "b": 2, "a": 1, "a": 4}"#));
+        assert_ne!(object, keep_all(r#"{"b": 2, "a": 4}"#));
+        assert_ne!(
+            keep_all(r#"{"a": 1, "a": 1, "b": 2}"#),
+            keep_all(r#"{"a": 1, "b": 2, "b": 2}"#)
+        );
+
+        // Many distinct names with a duplicate among them
+        let many = |last: usize| {
+            let members: Vec<_> = (0..20_000)
+                .map(|name| format!(r#""{}": 0"#, name))
+                .collect();
+            keep_all(&format!(r#"{{{}, "{}": 1}}"#, members.join(", "), last))
+        };
+        assert_eq!(many(7), many(7));
+        assert_ne!(many(7), many(8));
+    }
+}
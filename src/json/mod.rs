@@ -1,10 +1,20 @@
+mod cst;
+pub use cst::{Cst, Element, Node};
+
+mod decimal;
+pub use decimal::Decimal;
+
+mod diagnostic;
+pub use diagnostic::Diagnostic;
+
 mod lexer;
-pub use lexer::Lexer;
+pub use lexer::{Lexer, Syntax};
 
-mod parser;
-pub use parser::Parser;
+mod map;
+pub use map::Map;
 
-use std::collections::HashMap;
+mod parser;
+pub use parser::{DuplicateKeys, ParseError, Parser, ParserOptions};
 
 #[derive(Debug, Clone)]
 pub enum KeyPart {
@@ -12,18 +22,30 @@ pub enum KeyPart {
     Index(usize),
 }
 
-#[derive(Debug, Clone)]
+impl std::fmt::Display for KeyPart {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyPart::Identifier(identifier) => {
+                write!(f, "{}", identifier.replace('~', "~0").replace('/', "~1"))
+            }
+            KeyPart::Index(index) => write!(f, "{}", index),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct Key {
     depth: usize,
     parts: Vec<KeyPart>,
 }
 
-impl Default for Key {
-    fn default() -> Self {
-        Self {
-            depth: 0,
-            parts: Vec::new(),
+/// Formats the key as a JSON Pointer (RFC 6901)
+impl std::fmt::Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for part in &self.parts {
+            write!(f, "/{}", part)?;
         }
+        Ok(())
     }
 }
 
@@ -42,7 +64,7 @@ impl Key {
     }
 
     pub fn first_part(&self) -> &KeyPart {
-        self.parts.iter().skip(self.depth).next().unwrap()
+        self.parts.get(self.depth).unwrap()
     }
 
     pub fn descend(&mut self) -> bool {
@@ -81,20 +103,70 @@ impl Key {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub enum Json {
-    Object(HashMap<String, Json>),
+    Object(Map),
     Array(Vec<Json>),
-    Number {
-        integer: i64,
-        fraction: (u32, u64),
-        exponent: i64,
-    },
+    /// A number as it is written, which the parser checks to be valid.
+    /// JSON5 numbers are rewritten as JSON, apart from `Infinity`,
+    /// `-Infinity` and `NaN`. [`Json::number_parts`] splits it into its
+    /// sign, integer, fraction and exponent.
+    Number(String),
     String(String),
     Boolean(bool),
     Null,
 }
 
+/// Writes the value as compact JSON. `Infinity`, `-Infinity` and `NaN`,
+/// which only JSON5 input has and JSON has no way to write, are written as
+/// they are, so the output is JSON5 rather than JSON if it has them.
+impl std::fmt::Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut string = String::new();
+
+        self.to_string_rec(&mut string);
+
+        write!(f, "{}", string)
+    }
+}
+
+/// The parts that a number is written in: `-12.0034e-5` is negative, with
+/// the integer 12, the fraction `0034` and the exponent -5
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberParts {
+    pub negative: bool,
+    /// The digits before the decimal point, without the sign
+    pub integer: i64,
+    /// The number of zeroes that the fraction starts with, and the value of
+    /// its digits
+    pub fraction: (u32, u64),
+    pub exponent: i64,
+}
+
+/// Compares values as JSON, so numbers are equal if they have the same
+/// value, however they are written. Numbers are compared exactly, as
+/// [`Decimal`]s. `Infinity` and `-Infinity`, which have no decimal value,
+/// are only equal to themselves, and `NaN` is equal to nothing.
+impl PartialEq for Json {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Json::Object(left), Json::Object(right)) => left == right,
+            (Json::Array(left), Json::Array(right)) => left == right,
+            (Json::Number(left), Json::Number(right)) => {
+                match (Decimal::parse(left), Decimal::parse(right)) {
+                    (Some(left), Some(right)) => left == right,
+                    (None, None) => left == right && left != "NaN",
+                    _ => false,
+                }
+            }
+            (Json::String(left), Json::String(right)) => left == right,
+            (Json::Boolean(left), Json::Boolean(right)) => left == right,
+            (Json::Null, Json::Null) => true,
+            _ => false,
+        }
+    }
+}
+
 impl From<&str> for Json {
     fn from(input: &str) -> Self {
         Self::String(input.to_string())
@@ -122,26 +194,81 @@ impl Json {
         }
     }
 
-    pub fn to_string(&self) -> String {
-        let mut string = String::new();
+    /// The value of this number as a float, or `None` if this is not a
+    /// number
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(number) => number.parse().ok(),
+            _ => None,
+        }
+    }
 
-        self.to_string_rec(&mut string);
+    /// The exact value of this number, or `None` if this is not a number
+    /// or it is not written in decimal
+    pub fn as_decimal(&self) -> Option<Decimal<'_>> {
+        match self {
+            Json::Number(number) => Decimal::parse(number),
+            _ => None,
+        }
+    }
 
-        string
+    /// The value of this number if it is written as an integer that fits
+    /// in an `i64`
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Json::Number(number) => number.parse().ok(),
+            _ => None,
+        }
     }
 
-    pub fn from_string<'input>(input: &'input str) -> Option<Json> {
-        let mut tokens = Vec::new();
+    /// The parts that this number is written in, or `None` if this is not a
+    /// number or one of them does not fit
+    pub fn number_parts(&self) -> Option<NumberParts> {
+        let number = match self {
+            Json::Number(number) => number.as_str(),
+            _ => return None,
+        };
+        let (negative, number) = match number.strip_prefix('-') {
+            Some(number) => (true, number),
+            None => (false, number),
+        };
+        let (mantissa, exponent) = match number.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, exponent.parse().ok()?),
+            None => (number, 0),
+        };
+        let (integer, fraction) = match mantissa.split_once('.') {
+            Some((integer, fraction)) => {
+                let zeroes = fraction.bytes().take_while(|&byte| byte == b'0').count();
+                (integer, (zeroes as u32, fraction.parse().ok()?))
+            }
+            None => (mantissa, (0, 0)),
+        };
+        Some(NumberParts {
+            negative,
+            integer: integer.parse().ok()?,
+            fraction,
+            exponent,
+        })
+    }
 
-        if let Err(_) = Lexer::new(Some(input)).lex_into(input.chars(), &mut tokens) {
-            return None;
-        }
+    pub fn from_string(input: &str) -> Option<Json> {
+        Self::from_bytes(input.as_bytes())
+    }
 
-        if let Ok(value) = Parser::parse_tokens(&tokens) {
-            value
-        } else {
-            None
-        }
+    /// Parse `input`, which must be UTF-8
+    pub fn from_bytes(input: &[u8]) -> Option<Json> {
+        Parser::parse_bytes(input).ok()
+    }
+
+    /// Parse `input`, failing if it exceeds any of the limits of `options`
+    pub fn from_string_with_options(input: &str, options: ParserOptions) -> Option<Json> {
+        Self::from_bytes_with_options(input.as_bytes(), options)
+    }
+
+    /// Parse `input`, which must be UTF-8, failing if it exceeds any of the
+    /// limits of `options`
+    pub fn from_bytes_with_options(input: &[u8], options: ParserOptions) -> Option<Json> {
+        Parser::new(input).options(options).parse().ok()
     }
 
     fn to_string_rec(&self, buffer: &mut String) {
@@ -150,7 +277,8 @@ impl Json {
                 buffer.push('{');
                 let mut peekable = map.iter().peekable();
                 while let Some((key, value)) = peekable.next() {
-                    buffer.push_str(format!("\"{}\":", key).as_str());
+                    write_string(key, buffer);
+                    buffer.push(':');
                     value.to_string_rec(buffer);
                     if peekable.peek().is_some() {
                         buffer.push(',');
@@ -169,33 +297,8 @@ impl Json {
                 }
                 buffer.push(']');
             }
-            Json::Number {
-                integer,
-                fraction: (leading_zeroes, frac_number),
-                exponent,
-            } => {
-                let value = match (frac_number, exponent) {
-                    (0, 0) => format!("{}", integer),
-                    (frac, 0) => format!(
-                        "{}.{}{}",
-                        integer,
-                        (0..*leading_zeroes).map(|_| '0').collect::<String>(),
-                        frac
-                    ),
-                    (0, exp) => format!("{}e{}", integer, exp),
-                    (frac, exp) => {
-                        format!(
-                            "{}.{}{}e{}",
-                            integer,
-                            (0..*leading_zeroes).map(|_| '0').collect::<String>(),
-                            frac,
-                            exp
-                        )
-                    }
-                };
-                buffer.push_str(value.as_str())
-            }
-            Json::String(string) => buffer.push_str(format!("\"{}\"", string).as_str()),
+            Json::Number(number) => buffer.push_str(number),
+            Json::String(string) => write_string(string, buffer),
             Json::Boolean(bool) => {
                 if *bool {
                     buffer.push_str("true")
@@ -207,3 +310,115 @@ impl Json {
         }
     }
 }
+
+/// Write `string` in quotes, with the characters that JSON strings cannot
+/// hold as they are escaped
+fn write_string(string: &str, buffer: &mut String) {
+    buffer.push('"');
+    for char in string.chars() {
+        match char {
+            '"' => buffer.push_str("\\\""),
+            '\\' => buffer.push_str("\\\\"),
+            '\n' => buffer.push_str("\\n"),
+            '\r' => buffer.push_str("\\r"),
+            '\t' => buffer.push_str("\\t"),
+            '\u{8}' => buffer.push_str("\\b"),
+            '\u{c}' => buffer.push_str("\\f"),
+            '\0'..='\u{1f}' => buffer.push_str(&format!("\\u{:04x}", char as u32)),
+            char => buffer.push(char),
+        }
+    }
+    buffer.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Json, NumberParts, ParserOptions, Syntax};
+
+    fn number(input: &str) -> Json {
+        Json::from_string(input).unwrap()
+    }
+
+    #[test]
+    fn numbers() {
+        // Integers past the precision of a float are still told apart
+        assert_ne!(number("9007199254740993"), number("9007199254740992"));
+        assert_eq!(number("9007199254740993"), number("9007199254740993"));
+        assert_ne!(
+            number("123456789012345678901234567890"),
+            number("123456789012345678901234567891")
+        );
+        assert_eq!(number("-0"), number("0"));
+        assert_ne!(number("-1"), number("1"));
+        assert_eq!(number("1.0"), number("1"));
+        assert_eq!(number("1e2"), number("100"));
+        assert_eq!(number("-0.0120e3"), number("-12"));
+        assert_eq!(number("0.0"), number("-0e5"));
+        assert_ne!(number("1.5"), number("15e-2"));
+
+        // Numbers without a decimal value are not equal to each other
+        assert_ne!(Json::Number("abc".into()), Json::Number("xyz".into()));
+        assert_ne!(Json::Number("NaN".into()), Json::Number("NaN".into()));
+        assert_eq!(
+            Json::Number("Infinity".into()),
+            Json::Number("Infinity".into())
+        );
+
+        // Floats are compared exactly too, so equality is transitive
+        assert_ne!(number("9007199254740993"), number("9007199254740992.0"));
+        assert_eq!(number("9007199254740992.0"), number("9007199254740992"));
+        assert_ne!(
+            number("0.1"),
+            number("0.1000000000000000055511151231257827")
+        );
+
+        assert_eq!(number("-42").as_i64(), Some(-42));
+        assert_eq!(number("1.5").as_i64(), None);
+        assert_eq!(number("9223372036854775808").as_i64(), None);
+
+        assert_eq!(
+            number("-12.0034e-5").number_parts(),
+            Some(NumberParts {
+                negative: true,
+                integer: 12,
+                fraction: (2, 34),
+                exponent: -5,
+            })
+        );
+        assert_eq!(
+            number("7").number_parts(),
+            Some(NumberParts {
+                negative: false,
+                integer: 7,
+                fraction: (0, 0),
+                exponent: 0,
+            })
+        );
+        assert_eq!(
+            number("-0.5").number_parts(),
+            Some(NumberParts {
+                negative: true,
+                integer: 0,
+                fraction: (0, 5),
+                exponent: 0,
+            })
+        );
+        assert_ne!(number("-0.5").number_parts(), number("0.5").number_parts());
+        assert_eq!(number("1e99999999999999999999").number_parts(), None);
+    }
+
+    #[test]
+    fn display() {
+        let options = ParserOptions {
+            syntax: Syntax::Json5,
+            ..Default::default()
+        };
+        let json = Json::from_string_with_options("[Infinity, -Infinity, NaN, 0x10]", options);
+
+        // The output is only JSON if the input has no numbers that JSON lacks
+        let output = json.unwrap().to_string();
+        assert_eq!(output, "[Infinity,-Infinity,NaN,16]");
+        assert_eq!(Json::from_string(&output), None);
+        assert!(Json::from_string_with_options(&output, options).is_some());
+    }
+}
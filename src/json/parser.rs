@@ -1,8 +1,6 @@
-use std::collections::HashMap;
-
 use super::{
-    lexer::{Literal, Span, Token, TokenizeError},
-    Json,
+    lexer::{Lexer, Literal, Span, Syntax, Token, TokenizeError},
+    Json, Map,
 };
 
 #[derive(Debug, Clone)]
@@ -21,7 +19,27 @@ pub enum ParseError<'src> {
     ExtraComma(Span<'src>),
     UnopenedObject(Span<'src>),
     UnopenedArray(Span<'src>),
-    NoMoreTokens,
+    /// The array or object that starts at the span is nested deeper than
+    /// the parser allows
+    TooDeep(Span<'src>),
+    /// The input, which the span covers, is larger than the parser reads
+    InputTooLarge(Span<'src>),
+    /// The string at the span is longer than the parser allows
+    StringTooLong(Span<'src>),
+    /// The number at the span is written with more characters than the
+    /// parser allows
+    NumberTooLong(Span<'src>),
+    /// The name at the span is of a member past the most an object may
+    /// have
+    TooManyMembers(Span<'src>),
+    /// The value that starts at the span is an item past the most an array
+    /// may have
+    TooManyItems(Span<'src>),
+    /// The name at the span is of a member that an earlier member of the
+    /// same object has
+    DuplicateKey(Span<'src>),
+    /// The input ends, at the empty span, before it has a value
+    NoMoreTokens(Span<'src>),
 }
 
 impl<'src> From<TokenizeError<'src>> for ParseError<'src> {
@@ -31,7 +49,7 @@ impl<'src> From<TokenizeError<'src>> for ParseError<'src> {
 }
 
 impl<'src> ParseError<'src> {
-    pub fn span(&self) -> Option<&Span<'src>> {
+    pub fn span(&self) -> &Span<'src> {
         match self {
             Self::InvalidNumber(span)
             | Self::UnclosedArray(span)
@@ -45,279 +63,1271 @@ impl<'src> ParseError<'src> {
             | Self::ExtraComma(span)
             | Self::UnopenedObject(span)
             | Self::UnopenedArray(span)
-            | Self::IllegalLeadingZero(span) => Some(span),
-            Self::TokenizeError(error) => Some(error.span()),
-            Self::NoMoreTokens => None,
+            | Self::TooDeep(span)
+            | Self::InputTooLarge(span)
+            | Self::StringTooLong(span)
+            | Self::NumberTooLong(span)
+            | Self::TooManyMembers(span)
+            | Self::TooManyItems(span)
+            | Self::DuplicateKey(span)
+            | Self::IllegalLeadingZero(span)
+            | Self::NoMoreTokens(span) => span,
+            Self::TokenizeError(error) => error.span(),
+        }
+    }
+
+    /// Whether the error is for going over a limit of [`ParserOptions`],
+    /// which parsing does not recover from
+    fn is_limit(&self) -> bool {
+        matches!(
+            self,
+            Self::TooDeep(_)
+                | Self::InputTooLarge(_)
+                | Self::StringTooLong(_)
+                | Self::NumberTooLong(_)
+                | Self::TooManyMembers(_)
+                | Self::TooManyItems(_)
+        )
+    }
+}
+
+impl<'src> std::fmt::Display for ParseError<'src> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TokenizeError(error) => write!(f, "{}", error),
+            Self::InvalidNumber(span) => write!(f, "invalid number `{}`", span),
+            Self::UnclosedArray(_) => write!(f, "array is never closed"),
+            Self::UnclosedObject(_) => write!(f, "object is never closed"),
+            Self::IllegalArray(_) => write!(f, "expected `,` or `]` after array item"),
+            Self::IllegalObject(_) => write!(f, "expected `,` or `}}` after object member"),
+            Self::LeftOverTokens(_) => write!(f, "expected end of input after the value"),
+            Self::InvalidKeyType(_) => write!(f, "expected a string as object key"),
+            Self::ColonExpected(_) => write!(f, "expected `:` after object key"),
+            Self::IllegalLeadingZero(_) => write!(f, "numbers cannot have leading zeros"),
+            Self::ExtraColon(span)
+            | Self::ExtraComma(span)
+            | Self::UnopenedObject(span)
+            | Self::UnopenedArray(span) => write!(f, "expected a value, found `{}`", span),
+            Self::TooDeep(_) => write!(f, "arrays and objects are nested too deeply"),
+            Self::InputTooLarge(_) => write!(f, "input is too large"),
+            Self::StringTooLong(_) => write!(f, "string is too long"),
+            Self::NumberTooLong(_) => write!(f, "number is too long"),
+            Self::TooManyMembers(_) => write!(f, "object has too many members"),
+            Self::TooManyItems(_) => write!(f, "array has too many items"),
+            Self::DuplicateKey(span) => write!(f, "duplicate object key {}", span),
+            Self::NoMoreTokens(_) => write!(f, "unexpected end of input"),
+        }
+    }
+}
+
+/// What a [`Parser`] does with a member of an object that has the same name
+/// as an earlier member
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeys {
+    /// Fail with [`ParseError::DuplicateKey`]
+    Reject,
+    /// Ignore the later members
+    KeepFirst,
+    /// Give the earlier member the value of the later ones
+    #[default]
+    KeepLast,
+    /// Keep every member, so that [`Map::get_all`] finds all of the values
+    KeepAll,
+}
+
+impl DuplicateKeys {
+    /// Add the member `name` to `members`, which only has duplicate names
+    /// for [`DuplicateKeys::KeepAll`]. Names are checked for
+    /// [`DuplicateKeys::Reject`] before their values are parsed.
+    pub(super) fn add(self, members: &mut Map, name: String, value: Json) {
+        match self {
+            DuplicateKeys::KeepFirst if members.contains_key(&name) => {}
+            DuplicateKeys::KeepAll => members.push(name, value),
+            _ => {
+                members.insert(name, value);
+            }
+        }
+    }
+}
+
+/// Limits on the input that a [`Parser`] accepts, and the syntax it is in
+///
+/// Parsing fails with a [`ParseError`] that points at the offending part of
+/// the input as soon as a limit is exceeded, so input from untrusted
+/// sources can be parsed without it using up the stack or memory. Lengths
+/// are in bytes. By default only the depth is limited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParserOptions {
+    /// How deeply arrays and objects may be nested
+    ///
+    /// Parsing does not recurse, so any depth can be parsed, but dropping,
+    /// cloning, comparing and writing a [`Json`] and validating it against a
+    /// schema recurse once per level. A value that is nested more deeply
+    /// than the stack of the thread can recurse through crashes the process
+    /// with a stack overflow when it is used or dropped, so this should only
+    /// be raised as far as the stack allows. On an 8 MiB stack, dropping
+    /// alone overflows somewhere past 30,000 levels in a debug build and
+    /// past 100,000 in a release build, and the other operations use more
+    /// stack per level.
+    pub max_depth: usize,
+    /// How large the input may be, in bytes including whitespace, which is
+    /// checked before any of it is read
+    pub max_input_size: usize,
+    /// How long strings and member names may be, without their quotes
+    pub max_string_length: usize,
+    /// How long the text of a number may be
+    pub max_number_length: usize,
+    /// How many members an object may have
+    pub max_object_members: usize,
+    /// How many items an array may have
+    pub max_array_length: usize,
+    /// How members with the same name as an earlier member are handled
+    pub duplicate_keys: DuplicateKeys,
+    /// The dialect of JSON that is accepted
+    pub syntax: Syntax,
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: 128,
+            max_input_size: usize::MAX,
+            max_string_length: usize::MAX,
+            max_number_length: usize::MAX,
+            max_object_members: usize::MAX,
+            max_array_length: usize::MAX,
+            duplicate_keys: DuplicateKeys::default(),
+            syntax: Syntax::default(),
         }
     }
 }
 
-pub struct Parser;
+/// An array or object that is being parsed
+enum Frame<'src> {
+    Array {
+        start: Span<'src>,
+        items: Vec<Json>,
+    },
+    /// An object, and the name of the member whose value is being parsed,
+    /// which is missing while recovering from an error in the name
+    Object {
+        start: Span<'src>,
+        members: Map,
+        name: Option<String>,
+    },
+}
+
+impl<'src> Frame<'src> {
+    /// The error for the array or object never being closed
+    fn unclosed(&self) -> ParseError<'src> {
+        match self {
+            Frame::Array { start, .. } => ParseError::UnclosedArray(start.clone()),
+            Frame::Object { start, .. } => ParseError::UnclosedObject(start.clone()),
+        }
+    }
 
-impl Parser {
-    pub fn parse_tokens<'src>(tokens_in: &'src [Token]) -> Result<Option<Json>, ParseError<'src>> {
-        let tokens = tokens_in
-            .iter()
-            .filter(|tok| !matches!(tok, Token::Whitespace(_)));
+    fn is_closed_by(&self, token: &Token) -> bool {
+        matches!(
+            (self, token),
+            (Frame::Array { .. }, Token::ArrayEnd(_)) | (Frame::Object { .. }, Token::ObjectEnd(_))
+        )
+    }
 
-        let (result, mut iter) = Self::parse_first_token(tokens)?;
-        if let Some(token) = iter.next() {
+    /// The array or object with what was parsed of it
+    fn finish(self) -> Json {
+        match self {
+            Frame::Array { items, .. } => Json::Array(items),
+            Frame::Object { members, .. } => Json::Object(members),
+        }
+    }
+}
+
+/// Where parsing goes on after recovering from an error
+enum Recovery {
+    /// Another item or member of the array or object on top of the stack
+    /// follows
+    Next,
+    /// The array or object that was on top of the stack ended
+    Finished(Json),
+}
+
+/// Parses JSON from tokens that are pulled from a lexer as they are needed
+///
+/// Whitespace is skipped as it is lexed, so only the token that is being
+/// looked at is ever held on to. Nested arrays and objects are kept on a
+/// stack rather than parsed recursively, so deeply nested input cannot
+/// overflow the call stack.
+pub struct Parser<'src, T> {
+    tokens: T,
+    /// The input that the tokens are lexed from
+    source: &'src [u8],
+    peeked: Option<Token<'src>>,
+    options: ParserOptions,
+    /// The errors recovered from so far, if the parser recovers from errors
+    errors: Option<Vec<ParseError<'src>>>,
+}
+
+impl<'src> Parser<'src, Lexer<'src>> {
+    pub fn new(source: &'src [u8]) -> Self {
+        Self {
+            tokens: Lexer::new(source),
+            source,
+            peeked: None,
+            options: ParserOptions::default(),
+            errors: None,
+        }
+    }
+
+    /// Limit the input that is accepted, and set the syntax that it is
+    /// lexed in
+    pub fn options(mut self, options: ParserOptions) -> Self {
+        self.tokens = self
+            .tokens
+            .syntax(options.syntax)
+            .max_string_length(options.max_string_length)
+            .max_number_length(options.max_number_length);
+        self.options = options;
+        self
+    }
+
+    /// Lex and parse `source` in a single pass
+    pub fn parse_bytes(source: &'src [u8]) -> Result<Json, ParseError<'src>> {
+        Self::new(source).parse()
+    }
+
+    pub fn parse_str(source: &'src str) -> Result<Json, ParseError<'src>> {
+        Self::parse_bytes(source.as_bytes())
+    }
+
+    /// Parse, and keep every token that is lexed, whitespace and comments
+    /// included, in `tokens`
+    pub(super) fn parse_keeping_tokens(
+        self,
+        tokens: &mut Vec<Token<'src>>,
+    ) -> Result<Json, ParseError<'src>> {
+        Parser {
+            tokens: self.tokens.inspect(|token| {
+                if let Ok(token) = token {
+                    tokens.push(token.clone());
+                }
+            }),
+            source: self.source,
+            peeked: None,
+            options: self.options,
+            errors: None,
+        }
+        .parse()
+    }
+
+    /// Parse tokens that were already lexed
+    pub fn parse_tokens(tokens: &[Token<'src>]) -> Result<Json, ParseError<'src>> {
+        Parser {
+            tokens: tokens.iter().cloned().map(Ok),
+            source: tokens.first().map_or(&[], |token| token.span().source()),
+            peeked: None,
+            options: ParserOptions::default(),
+            errors: None,
+        }
+        .parse()
+    }
+}
+
+impl<'src, T> Parser<'src, T>
+where
+    T: Iterator<Item = Result<Token<'src>, TokenizeError<'src>>>,
+{
+    /// Parse the single value that the tokens make up
+    pub fn parse(mut self) -> Result<Json, ParseError<'src>> {
+        self.check_size()?;
+        let result = self.parse_value()?;
+        if let Some(token) = self.next()? {
             Err(ParseError::LeftOverTokens(token.span()))
         } else {
             Ok(result)
         }
     }
 
-    fn parse_first_token<'src, T>(mut tokens: T) -> Result<(Option<Json>, T), ParseError<'src>>
-    where
-        T: Iterator<Item = &'src Token<'src>> + Clone,
-    {
-        let value = if let Some(token) = tokens.next() {
-            match token {
-                Token::Literal(Literal::Number(span, value)) => {
-                    Some(Self::parse_number(span, value)?)
+    /// Parse the single value that the tokens make up, going on after errors
+    /// to find all of them
+    ///
+    /// After an error, tokens are skipped up to the next `,`, `]` or `}` of
+    /// the array or object that the error is in, and a value that could not
+    /// be parsed is replaced with `null`. Members whose names could not be
+    /// parsed are left out. Going over a limit of [`ParserOptions`] still
+    /// stops parsing, and gives `null`.
+    pub fn parse_recovering(mut self) -> (Json, Vec<ParseError<'src>>) {
+        self.errors = Some(Vec::new());
+        if let Err(error) = self.check_size() {
+            return (Json::Null, vec![error]);
+        }
+        let json = match self.parse_value() {
+            Ok(json) => {
+                match self.next_recovering() {
+                    Ok(Some(token)) => {
+                        self.record(ParseError::LeftOverTokens(token.span()));
+                        while let Ok(Some(_)) = self.next_recovering() {}
+                    }
+                    Ok(None) => {}
+                    Err(error) => self.record(error),
                 }
-                Token::Literal(Literal::String(_, value)) => Some(Json::String(value.clone())),
-                Token::Literal(Literal::Null(_)) => Some(Json::Null),
-                Token::Literal(Literal::False(_)) => Some(Json::Boolean(false)),
-                Token::Literal(Literal::True(_)) => Some(Json::Boolean(true)),
-                Token::Whitespace(_) => None,
-                Token::ArrayStart(start) => {
-                    let res = Self::parse_array(start, tokens)?;
-                    return Ok(res);
+                json
+            }
+            Err(error) => {
+                self.record(error);
+                Json::Null
+            }
+        };
+        (json, self.errors.unwrap_or_default())
+    }
+
+    /// The next token that is not whitespace
+    fn next(&mut self) -> Result<Option<Token<'src>>, ParseError<'src>> {
+        if let Some(token) = self.peeked.take() {
+            return Ok(Some(token));
+        }
+
+        for token in self.tokens.by_ref() {
+            match token {
+                // The lexer checks the limits it is given
+                Err(TokenizeError::StringTooLong(span)) => {
+                    return Err(ParseError::StringTooLong(span))
                 }
-                Token::ObjectStart(start) => {
-                    let res = Self::parse_object(start, tokens)?;
-                    return Ok(res);
+                Err(TokenizeError::NumberTooLong(span)) => {
+                    return Err(ParseError::NumberTooLong(span))
                 }
-                Token::Colon(span) => return Err(ParseError::ExtraColon(span.clone())),
-                Token::Comma(_span) => None,
-                Token::ObjectEnd(span) => return Err(ParseError::UnopenedObject(span.clone())),
-                Token::ArrayEnd(span) => return Err(ParseError::UnopenedArray(span.clone())),
+                Err(error) => return Err(error.into()),
+                Ok(Token::Whitespace(_) | Token::Comment(_)) => continue,
+                Ok(token) => return Ok(Some(token)),
             }
-        } else {
-            return Err(ParseError::NoMoreTokens);
-        };
+        }
+        Ok(None)
+    }
 
-        Ok((value, tokens))
+    /// Fail if the input is larger than the parser reads
+    fn check_size(&self) -> Result<(), ParseError<'src>> {
+        if self.source.len() > self.options.max_input_size {
+            let span = Span::new(self.source, 0, 0, 0, self.source.len());
+            return Err(ParseError::InputTooLarge(span));
+        }
+        Ok(())
     }
 
-    fn parse_number<'src>(input: &Span<'src>, value: &String) -> Result<Json, ParseError<'src>> {
-        let (integer, rest, has_fraction, has_exponent) = {
-            let (split_char, has_fraction, has_exponent) = if value.contains(".") {
-                (Some("."), true, value.contains("E") || value.contains("e"))
-            } else if value.contains("e") {
-                (Some("e"), false, true)
-            } else if value.contains("E") {
-                (Some("E"), false, true)
-            } else {
-                (None, false, false)
-            };
+    fn peek(&mut self) -> Result<Option<&Token<'src>>, ParseError<'src>> {
+        if self.peeked.is_none() {
+            self.peeked = self.next()?;
+        }
+        Ok(self.peeked.as_ref())
+    }
 
-            if let Some(split_char) = split_char {
-                let mut parts = value.split(split_char);
-                let res = (parts.next(), parts.next(), has_fraction, has_exponent);
-                if parts.next().is_none() {
-                    res
-                } else {
-                    return Err(ParseError::InvalidNumber(input.clone()));
+    /// The next token that is not whitespace, recording the tokens that
+    /// could not be lexed before it
+    fn next_recovering(&mut self) -> Result<Option<Token<'src>>, ParseError<'src>> {
+        loop {
+            match self.next() {
+                Err(ParseError::TokenizeError(error)) => {
+                    self.record(ParseError::TokenizeError(error))
                 }
-            } else {
-                (Some(value.as_str()), None, has_fraction, has_exponent)
+                result => return result,
             }
-        };
+        }
+    }
+
+    fn peek_recovering(&mut self) -> Result<Option<&Token<'src>>, ParseError<'src>> {
+        if self.peeked.is_none() {
+            self.peeked = self.next_recovering()?;
+        }
+        Ok(self.peeked.as_ref())
+    }
 
-        let (fraction, exponent) = if let Some(rest) = rest {
-            let split_char = if rest.contains("e") {
-                Some("e")
-            } else if rest.contains("E") {
-                Some("E")
-            } else {
-                None
+    /// Parse the value that starts at the next token
+    fn parse_value(&mut self) -> Result<Json, ParseError<'src>> {
+        let mut stack = Vec::new();
+        loop {
+            let mut value = match self.open_value(&mut stack) {
+                Ok(value) => value,
+                Err(error) => match self.recover(error, &mut stack, Some(Json::Null))? {
+                    Recovery::Next => continue,
+                    Recovery::Finished(container) => container,
+                },
             };
 
-            if let Some(split_char) = split_char {
-                let mut parts = rest.split(split_char);
-                let res = if has_fraction {
-                    (parts.next(), parts.next())
-                } else {
-                    (None, parts.next())
+            // Complete the containers that this value was the last item of
+            loop {
+                let frame = match stack.last_mut() {
+                    Some(frame) => frame,
+                    None => return Ok(value),
                 };
-
-                if parts.next().is_none() {
-                    res
-                } else {
-                    return Err(ParseError::InvalidNumber(input.clone()));
+                self.add(frame, value);
+                match self.close_value(frame) {
+                    Ok(Some(container)) => {
+                        stack.pop();
+                        value = container;
+                    }
+                    Ok(None) => break,
+                    Err(error) => match self.recover(error, &mut stack, None)? {
+                        Recovery::Next => break,
+                        Recovery::Finished(container) => value = container,
+                    },
                 }
-            } else if has_fraction {
-                (Some(rest), None)
-            } else if has_exponent {
-                (None, Some(rest))
-            } else {
-                (None, None)
             }
-        } else {
-            (None, None)
-        };
+        }
+    }
 
-        let test_input = |mut value: &'_ str, allow_leading_zero: bool| {
-            let negative = if value.starts_with("-") {
-                value = &value[1..];
-                -1
-            } else {
-                1
+    /// Parse tokens until a value is complete, pushing the arrays and
+    /// objects that are opened along the way onto `stack`
+    fn open_value(&mut self, stack: &mut Vec<Frame<'src>>) -> Result<Json, ParseError<'src>> {
+        loop {
+            let token = match self.next()? {
+                Some(token) => token,
+                None => {
+                    return Err(stack.last().map_or_else(
+                        || ParseError::NoMoreTokens(Span::end(self.source)),
+                        Frame::unclosed,
+                    ))
+                }
             };
 
-            if value.len() == 0 {
-                return Err(ParseError::InvalidNumber(input.clone()));
+            if let Token::ArrayStart(start) | Token::ObjectStart(start) = &token {
+                if stack.len() >= self.options.max_depth {
+                    return Err(ParseError::TooDeep(start.clone()));
+                }
             }
 
-            let leading_zeros = value
-                .chars()
-                .take_while(|char| char.is_numeric() && char == &'0')
-                .count() as u32;
-
-            let number = if let Ok(val) = i64::from_str_radix(value, 10) {
-                val
-            } else {
-                return Err(ParseError::InvalidNumber(input.clone()));
+            let value = match token {
+                Token::Literal(Literal::Number(span, value)) => {
+                    parse_number(&span, &value, self.options.syntax)?
+                }
+                Token::Literal(Literal::String(_, value)) => Json::String(value),
+                Token::Literal(Literal::Null(_)) => Json::Null,
+                Token::Literal(Literal::False(_)) => Json::Boolean(false),
+                Token::Literal(Literal::True(_)) => Json::Boolean(true),
+                // Identifiers can only name members
+                Token::Literal(Literal::Identifier(span, _)) => {
+                    return Err(TokenizeError::InvalidLiteral(span).into())
+                }
+                Token::ArrayStart(start) => {
+                    // The array is pushed first, so errors in its first item
+                    // are recovered from inside of it
+                    stack.push(Frame::Array {
+                        start,
+                        items: Vec::new(),
+                    });
+                    let max_array_length = self.options.max_array_length;
+                    match self.peek()? {
+                        Some(Token::ArrayEnd(_)) => {
+                            self.next()?;
+                            stack.pop();
+                            Json::Array(Vec::new())
+                        }
+                        Some(token) if max_array_length == 0 => {
+                            return Err(ParseError::TooManyItems(token.span()))
+                        }
+                        _ => continue,
+                    }
+                }
+                Token::ObjectStart(start) => {
+                    let first = self.member_name(&start, &Map::new(), true);
+                    let members = Map::new();
+                    match first {
+                        Ok(None) => Json::Object(members),
+                        Ok(name) => {
+                            stack.push(Frame::Object {
+                                start,
+                                members,
+                                name,
+                            });
+                            continue;
+                        }
+                        Err(error) => {
+                            stack.push(Frame::Object {
+                                start,
+                                members,
+                                name: None,
+                            });
+                            return Err(error);
+                        }
+                    }
+                }
+                Token::Colon(_) => return Err(self.unexpected(token, ParseError::ExtraColon)),
+                Token::ObjectEnd(_) => {
+                    return Err(self.unexpected(token, ParseError::UnopenedObject))
+                }
+                // A trailing comma, as arrays are only opened here if they
+                // are not empty
+                Token::ArrayEnd(_)
+                    if self.options.syntax != Syntax::Json
+                        && matches!(stack.last(), Some(Frame::Array { .. })) =>
+                {
+                    stack.pop().map_or(Json::Null, Frame::finish)
+                }
+                Token::ArrayEnd(_) => return Err(self.unexpected(token, ParseError::UnopenedArray)),
+                Token::Comma(_) | Token::Whitespace(_) | Token::Comment(_) => {
+                    return Err(self.unexpected(token, ParseError::ExtraComma))
+                }
             };
+            return Ok(value);
+        }
+    }
 
-            if number != 0 && leading_zeros != 0 && !allow_leading_zero {
-                return Err(ParseError::IllegalLeadingZero(input.clone()));
+    /// Add `value` to the container of `frame`
+    fn add(&self, frame: &mut Frame<'src>, value: Json) {
+        match frame {
+            Frame::Array { items, .. } => items.push(value),
+            Frame::Object { members, name, .. } => {
+                // The value is dropped if its name could not be parsed
+                let member = match name.take() {
+                    Some(member) => member,
+                    None => return,
+                };
+                self.options.duplicate_keys.add(members, member, value);
             }
+        }
+    }
 
-            Ok((leading_zeros, negative * number))
-        };
+    /// Parse what follows a value in the container of `frame`, and return
+    /// the container if it ends, or `None` if another value follows
+    fn close_value(&mut self, frame: &mut Frame<'src>) -> Result<Option<Json>, ParseError<'src>> {
+        match frame {
+            Frame::Array { start, items } => match self.next()? {
+                Some(Token::ArrayEnd(_)) => Ok(Some(Json::Array(std::mem::take(items)))),
+                Some(Token::Comma(_)) if items.len() >= self.options.max_array_length => {
+                    let trailing_commas = self.options.syntax != Syntax::Json;
+                    match self.peek()? {
+                        Some(Token::ArrayEnd(_)) if trailing_commas => Ok(None),
+                        Some(token) => Err(ParseError::TooManyItems(token.span())),
+                        None => Err(ParseError::UnclosedArray(start.clone())),
+                    }
+                }
+                Some(Token::Comma(_)) => Ok(None),
+                Some(token) => Err(self.unexpected(token, ParseError::IllegalArray)),
+                None => Err(ParseError::UnclosedArray(start.clone())),
+            },
+            Frame::Object {
+                start,
+                members,
+                name,
+            } => match self.member_name(start, members, false)? {
+                Some(next) => {
+                    *name = Some(next);
+                    Ok(None)
+                }
+                None => Ok(Some(Json::Object(std::mem::take(members)))),
+            },
+        }
+    }
 
-        let integer = if let Some(integer) = integer {
-            test_input(integer, false)?.1
-        } else {
-            return Err(ParseError::InvalidNumber(input.clone()));
+    /// Parse the name of the next member of the object that starts at
+    /// `start` and has `members` so far, up to and including the colon
+    /// after it, or `None` if the object ends instead. Names other than the
+    /// `first` follow a comma.
+    fn member_name(
+        &mut self,
+        start: &Span<'src>,
+        members: &Map,
+        first: bool,
+    ) -> Result<Option<String>, ParseError<'src>> {
+        let first_token = match self.next()? {
+            Some(token) => token,
+            None => return Err(ParseError::UnclosedObject(start.clone())),
         };
 
-        let fraction = if let Some(fraction) = fraction {
-            let value = test_input(fraction, true)?;
-
-            if value.1 < 0 {
-                return Err(ParseError::InvalidNumber(input.clone()));
-            } else {
-                Some((value.0, value.1 as u64))
-            }
-        } else {
-            None
+        let possible_name = match first_token {
+            Token::ObjectEnd(_) => return Ok(None),
+            token if first => token,
+            Token::Comma(_) => match self.next()? {
+                Some(Token::ObjectEnd(_)) if self.options.syntax != Syntax::Json => {
+                    return Ok(None)
+                }
+                Some(token) => token,
+                None => return Err(ParseError::UnclosedObject(start.clone())),
+            },
+            token => return Err(self.unexpected(token, ParseError::IllegalObject)),
         };
 
-        let exponent = if let Some(exponent) = exponent {
-            Some(test_input(exponent, false)?.1)
-        } else {
-            None
+        let name = match possible_name {
+            Token::Literal(literal) => match literal.into_name(self.options.syntax) {
+                Ok((span, _)) if members.len() >= self.options.max_object_members => {
+                    return Err(ParseError::TooManyMembers(span))
+                }
+                Ok((span, name))
+                    if self.options.duplicate_keys == DuplicateKeys::Reject
+                        && members.contains_key(&name) =>
+                {
+                    return Err(ParseError::DuplicateKey(span))
+                }
+                Ok((_, name)) => name,
+                Err(literal) => {
+                    return Err(self.unexpected(Token::Literal(literal), ParseError::InvalidKeyType))
+                }
+            },
+            token => return Err(self.unexpected(token, ParseError::InvalidKeyType)),
         };
 
-        Ok(Json::Number {
-            integer,
-            fraction: fraction.unwrap_or((0, 0)),
-            exponent: exponent.unwrap_or(0),
-        })
+        match self.next()? {
+            Some(Token::Colon(_)) => Ok(Some(name)),
+            Some(token) => Err(self.unexpected(token, ParseError::ColonExpected)),
+            None => Err(ParseError::UnclosedObject(start.clone())),
+        }
     }
 
-    fn parse_object<'src, T>(
-        start: &Span<'src>,
-        mut object_tok: T,
-    ) -> Result<(Option<Json>, T), ParseError<'src>>
-    where
-        T: Iterator<Item = &'src Token<'src>> + Clone,
-    {
-        let mut data = HashMap::new();
+    /// The error for `token` not being expected where it is. The token is put
+    /// back, so recovering from the error starts at it.
+    fn unexpected(
+        &mut self,
+        token: Token<'src>,
+        error: fn(Span<'src>) -> ParseError<'src>,
+    ) -> ParseError<'src> {
+        let error = error(token.span());
+        self.peeked = Some(token);
+        error
+    }
+
+    /// Record `error` and skip to where parsing can go on, or return it if
+    /// the parser does not recover from errors. `placeholder` is added to
+    /// the container on top of `stack` in place of a value that could not be
+    /// parsed.
+    fn recover(
+        &mut self,
+        error: ParseError<'src>,
+        stack: &mut Vec<Frame<'src>>,
+        placeholder: Option<Json>,
+    ) -> Result<Recovery, ParseError<'src>> {
+        if self.errors.is_none() || error.is_limit() {
+            return Err(error);
+        }
+        self.record(error);
+
+        match stack.last_mut() {
+            Some(frame) => {
+                if let Some(placeholder) = placeholder {
+                    self.add(frame, placeholder);
+                }
+            }
+            None => {
+                // Nothing can follow a value that is not in a container
+                while self.next_recovering()?.is_some() {}
+                return Ok(Recovery::Finished(Json::Null));
+            }
+        }
+
         loop {
-            let first_token = if let Some(tok) = object_tok.next() {
-                tok
-            } else {
-                return Err(ParseError::UnclosedObject(start.clone()));
+            self.skip_to_separator()?;
+            let token = self.peek_recovering()?.cloned();
+            let closes_any = token
+                .as_ref()
+                .is_some_and(|token| stack.iter().any(|frame| frame.is_closed_by(token)));
+            let frame = match stack.last_mut() {
+                Some(frame) => frame,
+                None => return Ok(Recovery::Finished(Json::Null)),
             };
-
-            let name = {
-                let possible_name = if matches!(first_token, Token::ObjectEnd(_)) {
-                    break;
-                } else if data.is_empty() {
-                    first_token
-                } else if !matches!(first_token, Token::Comma(_)) {
-                    return Err(ParseError::IllegalObject(first_token.span()));
-                } else {
-                    if let Some(next) = object_tok.next() {
-                        next
-                    } else {
-                        return Err(ParseError::IllegalObject(first_token.span()));
+            match token {
+                Some(Token::Comma(_)) => match frame {
+                    Frame::Array { .. } => {
+                        self.next()?;
+                        return Ok(Recovery::Next);
                     }
-                };
+                    Frame::Object {
+                        start,
+                        members,
+                        name,
+                    } => match self.member_name(start, members, false) {
+                        Ok(next) => {
+                            *name = next;
+                            return Ok(Recovery::Next);
+                        }
+                        Err(error) if error.is_limit() => return Err(error),
+                        Err(error) => self.record(error),
+                    },
+                },
+                Some(token) if frame.is_closed_by(&token) => {
+                    self.next()?;
+                    return Ok(Recovery::Finished(
+                        stack.pop().map_or(Json::Null, Frame::finish),
+                    ));
+                }
+                // A container further down is closed, so this one never is
+                Some(_) if closes_any => {
+                    self.record(frame.unclosed());
+                    return Ok(Recovery::Finished(
+                        stack.pop().map_or(Json::Null, Frame::finish),
+                    ));
+                }
+                Some(token) => {
+                    self.record(match token {
+                        Token::ObjectEnd(span) => ParseError::UnopenedObject(span),
+                        token => ParseError::UnopenedArray(token.span()),
+                    });
+                    self.next()?;
+                }
+                None => {
+                    self.record(frame.unclosed());
+                    return Ok(Recovery::Finished(
+                        stack.pop().map_or(Json::Null, Frame::finish),
+                    ));
+                }
+            }
+        }
+    }
 
-                if let Token::Literal(Literal::String(_, name)) = possible_name {
-                    name
-                } else {
-                    return Err(ParseError::InvalidKeyType(first_token.span()));
+    /// Skip tokens up to the next `,`, `]` or `}` that is not inside of an
+    /// array or object being skipped
+    fn skip_to_separator(&mut self) -> Result<(), ParseError<'src>> {
+        let mut depth = 0usize;
+        loop {
+            match self.peek_recovering()? {
+                None => return Ok(()),
+                Some(Token::Comma(_) | Token::ArrayEnd(_) | Token::ObjectEnd(_)) if depth == 0 => {
+                    return Ok(())
                 }
-            };
+                Some(Token::ArrayStart(_) | Token::ObjectStart(_)) => depth += 1,
+                Some(Token::ArrayEnd(_) | Token::ObjectEnd(_)) => depth -= 1,
+                Some(_) => {}
+            }
+            self.next()?;
+        }
+    }
 
-            if !matches!(object_tok.next(), Some(Token::Colon(_))) {
-                return Err(ParseError::ColonExpected(object_tok.next().unwrap().span()));
+    /// Keep `error` to return from [`Parser::parse_recovering`], unless an
+    /// error was just recorded at the same place
+    fn record(&mut self, error: ParseError<'src>) {
+        if let Some(errors) = &mut self.errors {
+            let offset = |error: &ParseError| error.span().offset();
+            if errors.last().map(offset) != Some(offset(&error)) {
+                errors.push(error);
             }
+        }
+    }
+}
 
-            let (parsed, non_cons_tokens) = Self::parse_first_token(object_tok.clone())?;
-            object_tok = non_cons_tokens;
+/// Check that `value` is a number in `syntax`
+///
+/// JSON5 numbers are rewritten as JSON, apart from `Infinity`,
+/// `-Infinity` and `NaN`, which JSON has no way to write and are kept as
+/// they are.
+pub(super) fn parse_number<'src>(
+    input: &Span<'src>,
+    value: &str,
+    syntax: Syntax,
+) -> Result<Json, ParseError<'src>> {
+    if syntax != Syntax::Json5 {
+        check_number(input, value)?;
+        return Ok(Json::Number(value.to_string()));
+    }
 
-            if let Some(parsed) = parsed {
-                data.insert(name.clone(), parsed);
-            } else {
-                return Err(ParseError::NoMoreTokens);
+    let (sign, magnitude) = match value.as_bytes().first() {
+        Some(b'+') => ("", &value[1..]),
+        Some(b'-') => ("-", &value[1..]),
+        _ => ("", value),
+    };
+    let hex = magnitude
+        .strip_prefix("0x")
+        .or_else(|| magnitude.strip_prefix("0X"));
+    let number = match (magnitude, hex) {
+        ("Infinity", _) => format!("{}Infinity", sign),
+        ("NaN", _) => "NaN".to_string(),
+        (_, Some(hex)) if !hex.is_empty() && hex.bytes().all(|byte| byte.is_ascii_hexdigit()) => {
+            format!("{}{}", sign, hex_to_decimal(hex))
+        }
+        (_, Some(_)) | ("" | ".", _) => return Err(ParseError::InvalidNumber(input.clone())),
+        _ if magnitude.starts_with(['+', '-']) => {
+            return Err(ParseError::InvalidNumber(input.clone()))
+        }
+        _ => {
+            // Decimal points may lead or trail the digits, but not stand in
+            // for them
+            let (mantissa, exponent) =
+                magnitude.split_at(magnitude.find(['e', 'E']).unwrap_or(magnitude.len()));
+            if !mantissa.bytes().any(|byte| byte.is_ascii_digit()) {
+                return Err(ParseError::InvalidNumber(input.clone()));
             }
+            let zero = if mantissa.starts_with('.') { "0" } else { "" };
+            let mantissa = mantissa.strip_suffix('.').unwrap_or(mantissa);
+            let number = format!("{}{}{}{}", sign, zero, mantissa, exponent);
+            check_number(input, &number)?;
+            number
         }
+    };
+    Ok(Json::Number(number))
+}
 
-        Ok((Some(Json::Object(data)), object_tok))
+/// The decimal digits of the hexadecimal number `hex`, which can be too
+/// large for any integer type
+fn hex_to_decimal(hex: &str) -> String {
+    // The decimal digits, least significant first
+    let mut digits = vec![0];
+    for hex_digit in hex.chars().filter_map(|char| char.to_digit(16)) {
+        let mut carry = hex_digit;
+        for digit in &mut digits {
+            let value = *digit * 16 + carry;
+            *digit = value % 10;
+            carry = value / 10;
+        }
+        while carry > 0 {
+            digits.push(carry % 10);
+            carry /= 10;
+        }
     }
+    digits
+        .iter()
+        .rev()
+        .filter_map(|&digit| char::from_digit(digit, 10))
+        .collect()
+}
 
-    fn parse_array<'src, T>(
-        start: &Span<'src>,
-        mut array_tokens: T,
-    ) -> Result<(Option<Json>, T), ParseError<'src>>
-    where
-        T: Iterator<Item = &'src Token<'src>> + Clone,
-    {
-        let mut data = Vec::new();
+/// Check that `value` is a number as RFC 8259 defines it
+fn check_number<'src>(input: &Span<'src>, value: &str) -> Result<(), ParseError<'src>> {
+    let bytes = value.as_bytes();
+    let mut position = 0;
+    // Move past the digits at `position` and return how many there were
+    let digits = |position: &mut usize| {
+        let start = *position;
+        while bytes.get(*position).is_some_and(u8::is_ascii_digit) {
+            *position += 1;
+        }
+        *position - start
+    };
 
-        loop {
-            if data.is_empty() {
-                if let Some(tok) = array_tokens.clone().next() {
-                    if matches!(tok, Token::ArrayEnd(_)) {
-                        array_tokens.next();
-                        break;
-                    }
-                } else {
-                    return Err(ParseError::UnclosedArray(start.clone()));
-                }
-            } else {
-                if let Some(tok) = array_tokens.next() {
-                    if matches!(tok, Token::ArrayEnd(_)) {
-                        break;
-                    } else if !matches!(tok, Token::Comma(_)) {
-                        return Err(ParseError::ExtraComma(tok.span().clone()));
-                    }
-                } else {
-                    return Err(ParseError::UnclosedArray(start.clone()));
-                }
-            };
+    if bytes.first() == Some(&b'-') {
+        position += 1;
+    }
+    let integer = position;
+    match digits(&mut position) {
+        0 => return Err(ParseError::InvalidNumber(input.clone())),
+        count if count > 1 && bytes[integer] == b'0' => {
+            return Err(ParseError::IllegalLeadingZero(input.clone()))
+        }
+        _ => {}
+    }
 
-            let (parsed, non_cons_tokens) = Self::parse_first_token(array_tokens.clone())?;
-            array_tokens = non_cons_tokens;
+    if bytes.get(position) == Some(&b'.') {
+        position += 1;
+        if digits(&mut position) == 0 {
+            return Err(ParseError::InvalidNumber(input.clone()));
+        }
+    }
 
-            if let Some(entry) = parsed {
-                data.push(entry);
-            } else {
-                return Err(ParseError::IllegalArray(
-                    array_tokens.next().unwrap().span().clone(),
-                ));
+    if let Some(b'e' | b'E') = bytes.get(position) {
+        position += 1;
+        if let Some(b'+' | b'-') = bytes.get(position) {
+            position += 1;
+        }
+        if digits(&mut position) == 0 {
+            return Err(ParseError::InvalidNumber(input.clone()));
+        }
+    }
+
+    if position == bytes.len() {
+        Ok(())
+    } else {
+        Err(ParseError::InvalidNumber(input.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DuplicateKeys, ParseError, Parser, ParserOptions, Syntax};
+    use crate::json::{Json, Lexer};
+
+    #[test]
+    fn values() {
+        let json =
+            Parser::parse_str(r#" {"a": [1, 2.5, -3e2], "b": {"c": null}, "d": true} "#).unwrap();
+        assert_eq!(
+            json,
+            Json::from_string(r#"{"d": true, "b": {"c": null}, "a": [1, 2.5, -300]}"#).unwrap()
+        );
+
+        // Parsing tokens that were already lexed gives the same value
+        let input = r#"[{}, [], "x", false]"#;
+        let tokens = Lexer::lex_str(input).unwrap();
+        assert_eq!(
+            Parser::parse_tokens(&tokens).unwrap(),
+            Parser::parse_str(input).unwrap()
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let error = |input| Parser::parse_str(input).unwrap_err();
+        let lexeme = |error: ParseError<'static>| error.span().lexeme().unwrap();
+
+        assert!(matches!(error("[1, 2"), ParseError::UnclosedArray(_)));
+        assert!(matches!(error(r#"{"a": 1"#), ParseError::UnclosedObject(_)));
+        assert!(matches!(error(r#"{"a""#), ParseError::UnclosedObject(_)));
+        assert!(matches!(error("[,"), ParseError::ExtraComma(_)));
+        assert!(matches!(error(""), ParseError::NoMoreTokens(_)));
+        assert!(matches!(error("]"), ParseError::UnopenedArray(_)));
+        assert!(matches!(error("[1 @]"), ParseError::TokenizeError(_)));
+        assert_eq!(lexeme(error(r#"{"a" 1}"#)), "1");
+        assert_eq!(lexeme(error(r#"{1: 1}"#)), "1");
+        assert_eq!(lexeme(error("[1 2]")), "2");
+        assert_eq!(lexeme(error("1 2")), "2");
+        assert_eq!(lexeme(error("[1, 01]")), "01");
+        assert_eq!(lexeme(error(",")), ",");
+        assert_eq!(lexeme(error("[1,,2]")), ",");
+        assert_eq!(lexeme(error(r#"{"a": ,}"#)), ",");
+    }
+
+    #[test]
+    fn numbers() {
+        let number = |input| match Parser::parse_str(input) {
+            Ok(Json::Number(number)) => Ok(number),
+            Ok(json) => panic!("expected a number, found {:?}", json),
+            Err(error) => Err(error),
+        };
+
+        for valid in [
+            "0",
+            "-0",
+            "-0.5",
+            "1E+2",
+            "1e-2",
+            "1e007",
+            "123.456e78",
+            "-1.0E2",
+        ] {
+            assert_eq!(number(valid).unwrap(), valid);
+        }
+        assert_eq!(Json::from_string("-0.5").unwrap().as_f64(), Some(-0.5));
+        // Numbers are not limited to what fits in 64 bits
+        let long = format!("-0.{}1", "0".repeat(80));
+        assert_eq!(number(&long).unwrap(), long);
+        assert!(number("123456789012345678901234567890").is_ok());
+
+        for invalid in [
+            "-", "1.", "-.5", "1e", "1e+", "1.e2", "1eE2", "1-2", "0.1.2",
+        ] {
+            assert!(
+                matches!(number(invalid), Err(ParseError::InvalidNumber(_))),
+                "{}",
+                invalid
+            );
+        }
+        assert!(matches!(
+            number("-012"),
+            Err(ParseError::IllegalLeadingZero(_))
+        ));
+        assert!(matches!(number("+1"), Err(ParseError::TokenizeError(_))));
+        assert!(matches!(number(".5"), Err(ParseError::TokenizeError(_))));
+    }
+
+    #[test]
+    fn display() {
+        let input = r#"{"a\"b": ["\\", "\n\u0001", -0.5e-3, "é"]}"#;
+        let json = Json::from_string(input).unwrap();
+        assert_eq!(
+            json.to_string(),
+            r#"{"a\"b":["\\","\n\u0001",-0.5e-3,"é"]}"#
+        );
+        assert_eq!(Json::from_string(&json.to_string()).unwrap(), json);
+    }
+
+    #[test]
+    fn depth() {
+        let max_depth = ParserOptions::default().max_depth;
+        let nested = |depth| "[".repeat(depth) + &"]".repeat(depth);
+        // Where the array or object that is too deep starts, if it is
+        let too_deep = |input: &str, max_depth| {
+            let options = ParserOptions {
+                max_depth,
+                ..Default::default()
+            };
+            match Parser::new(input.as_bytes()).options(options).parse() {
+                Err(ParseError::TooDeep(span)) => Some(span.offset()),
+                _ => None,
             }
+        };
+
+        assert!(Parser::parse_str(&nested(max_depth)).is_ok());
+        assert_eq!(too_deep(&nested(max_depth + 1), max_depth), Some(max_depth));
+
+        // Far deeper input than could be parsed recursively
+        assert_eq!(too_deep(&"[".repeat(100_000), 100), Some(100));
+        assert_eq!(too_deep(&r#"{"a": "#.repeat(100_000), 100), Some(600));
+        assert_eq!(too_deep(&nested(1_000), usize::MAX), None);
+
+        assert_eq!(too_deep("[[1]]", 1), Some(1));
+        assert_eq!(too_deep("[1, {}]", 1), Some(4));
+        assert_eq!(too_deep("[1, []]", 2), None);
+        assert_eq!(too_deep("1", 0), None);
+    }
+
+    #[test]
+    fn limits() {
+        let limited = ParserOptions {
+            max_input_size: 32,
+            max_string_length: 3,
+            max_number_length: 4,
+            max_object_members: 2,
+            max_array_length: 2,
+            ..Default::default()
+        };
+        let parse = |input: &'static str| Parser::new(input.as_bytes()).options(limited).parse();
+        let lexeme = |input| parse(input).unwrap_err().span().lexeme().unwrap();
+
+        assert!(parse(r#"{"abc": [1.25, -100], "d": []}"#).is_ok());
+
+        assert!(matches!(
+            parse(r#"["abcd"]"#),
+            Err(ParseError::StringTooLong(_))
+        ));
+        assert_eq!(lexeme(r#"{"abcd": 1}"#), r#""abcd"#);
+        assert!(matches!(parse("12345"), Err(ParseError::NumberTooLong(_))));
+        assert_eq!(lexeme("[1e100]"), "1e100");
+
+        // Lexing stops at the first byte past the limit, even if the string
+        // or number is never closed
+        let long = |input: String| {
+            let options = ParserOptions {
+                max_string_length: 3,
+                max_number_length: 4,
+                syntax: Syntax::Json5,
+                ..Default::default()
+            };
+            let error = Parser::new(input.as_bytes())
+                .options(options)
+                .parse()
+                .unwrap_err();
+            let span = error.span();
+            (span.offset(), span.len())
+        };
+        assert_eq!(long(format!("[\"{}", "a".repeat(100_000))), (1, 5));
+        assert_eq!(long(format!("[{}]", "9".repeat(100_000))), (1, 5));
+        assert_eq!(long(format!("{{{}: 1}}", "a".repeat(100_000))), (1, 9));
+        assert!(matches!(
+            parse(r#"{"a": 1, "b": 2, "c": 3}"#),
+            Err(ParseError::TooManyMembers(_))
+        ));
+        assert_eq!(lexeme(r#"{"a": 1, "b": 2, "c": 3}"#), r#""c""#);
+        assert!(matches!(
+            parse("[1, 2, 3]"),
+            Err(ParseError::TooManyItems(_))
+        ));
+        assert_eq!(lexeme("[1, 2, [3]]"), "[");
+        assert!(matches!(parse("[1, 2,"), Err(ParseError::UnclosedArray(_))));
+
+        // Whitespace counts towards the size of the input
+        let too_large = |input: &str| {
+            let result = Parser::new(input.as_bytes()).options(limited).parse();
+            matches!(result, Err(ParseError::InputTooLarge(_)))
+        };
+        assert!(!too_large(&format!("{}1", " ".repeat(31))));
+        assert!(too_large(&format!("{}1", " ".repeat(32))));
+        assert!(too_large(&format!("1{}", " ".repeat(32))));
+        // The size is checked before anything is lexed
+        assert!(matches!(
+            parse(r#"   "abc"                    "abc" ["#),
+            Err(ParseError::InputTooLarge(span)) if span.offset() == 0 && span.len() == 35
+        ));
+
+        let empty = ParserOptions {
+            max_object_members: 0,
+            max_array_length: 0,
+            ..Default::default()
+        };
+        let parse = |input: &'static str| Parser::new(input.as_bytes()).options(empty).parse();
+        assert!(parse("[{}, []]").is_err());
+        assert!(parse("{}").is_ok());
+        assert!(parse("[]").is_ok());
+        assert!(matches!(parse("[1]"), Err(ParseError::TooManyItems(_))));
+        assert!(matches!(
+            parse(r#"{"a": 1}"#),
+            Err(ParseError::TooManyMembers(_))
+        ));
+
+        assert_eq!(
+            Json::from_string_with_options("[1, 2]", limited),
+            Json::from_string("[1, 2]")
+        );
+        assert_eq!(Json::from_string_with_options("[1, 2, 3]", limited), None);
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        let input = r#"{"a": 1, "b": {"a": 2}, "a": 3, "a": 4}"#;
+        let parse = |duplicate_keys| {
+            let options = ParserOptions {
+                duplicate_keys,
+                ..Default::default()
+            };
+            Parser::new(input.as_bytes()).options(options).parse()
+        };
+        let members = |duplicate_keys| match parse(duplicate_keys) {
+            Ok(Json::Object(object)) => object
+                .iter()
+                .map(|(name, value)| format!("{}: {}", name, value))
+                .collect::<Vec<_>>(),
+            result => panic!("expected an object, found {:?}", result),
+        };
+
+        // The name of the second member is the offending one
+        assert!(matches!(
+            parse(DuplicateKeys::Reject),
+            Err(ParseError::DuplicateKey(span)) if span.offset() == 24
+        ));
+        assert_eq!(
+            members(DuplicateKeys::KeepFirst),
+            vec!["a: 1", r#"b: {"a":2}"#]
+        );
+        assert_eq!(
+            members(DuplicateKeys::KeepLast),
+            vec!["a: 4", r#"b: {"a":2}"#]
+        );
+        assert_eq!(
+            members(DuplicateKeys::KeepAll),
+            vec!["a: 1", r#"b: {"a":2}"#, "a: 3", "a: 4"]
+        );
+        assert_ne!(parse(DuplicateKeys::KeepAll).ok(), Json::from_string(input));
+    }
+
+    #[test]
+    fn recovering() {
+        let recover = |input: &'static str| {
+            let (json, errors) = Parser::new(input.as_bytes()).parse_recovering();
+            let errors = errors
+                .iter()
+                .map(|error| (error.span().offset(), error.to_string()))
+                .collect::<Vec<_>>();
+            (json.to_string(), errors)
+        };
+        let offsets = |input| {
+            recover(input)
+                .1
+                .into_iter()
+                .map(|(offset, _)| offset)
+                .collect::<Vec<_>>()
+        };
+
+        // Values that cannot be parsed are replaced with null
+        assert_eq!(recover("[1, @, 2]").0, "[1,null,2]");
+        assert_eq!(recover(r#"["\q", "ok", tru]"#).0, r#"[null,"ok",null]"#);
+        assert_eq!(offsets("[1,,2, ]"), vec![3, 7]);
+
+        // Members whose names cannot be parsed are left out
+        let (json, errors) = recover(r#"{"a" 1, "b": 2, 3: 4, "c": }"#);
+        assert_eq!(json, r#"{"b":2,"c":null}"#);
+        assert_eq!(
+            errors,
+            vec![
+                (5, "expected `:` after object key".to_string()),
+                (16, "expected a string as object key".to_string()),
+                (27, "expected a value, found `}`".to_string()),
+            ]
+        );
+
+        // Closing an outer container closes the ones inside of it
+        assert_eq!(recover(r#"{"a": [1, 2}"#).0, r#"{"a":[1,2]}"#);
+        assert_eq!(offsets(r#"{"a": [1, 2}"#), vec![11, 6]);
+        assert_eq!(recover("[1, [2, 3").0, "[1,[2,3]]");
+        assert_eq!(offsets("[1, [2, 3"), vec![4, 0]);
+        assert_eq!(offsets("[1}"), vec![2, 0]);
+        assert_eq!(offsets("[1 2] 3"), vec![3, 6]);
+
+        // Valid input has no errors
+        let input = r#"{"a": [1, {"b": null}]}"#;
+        let (json, errors) = Parser::new(input.as_bytes()).parse_recovering();
+        assert_eq!(Some(json), Json::from_string(input));
+        assert!(errors.is_empty());
+
+        // Going over a limit stops parsing
+        let options = ParserOptions {
+            max_string_length: 1,
+            ..Default::default()
+        };
+        let (json, errors) = Parser::new(br#"[1, @, "long"]"#)
+            .options(options)
+            .parse_recovering();
+        assert_eq!(json, Json::Null);
+        assert!(matches!(
+            errors.as_slice(),
+            [ParseError::TokenizeError(_), ParseError::StringTooLong(_)]
+        ));
+    }
+
+    #[test]
+    fn syntaxes() {
+        let parse = |input: &'static str, syntax| {
+            let options = ParserOptions {
+                syntax,
+                ..Default::default()
+            };
+            Parser::new(input.as_bytes()).options(options).parse()
+        };
+        let jsonc = r#"{
+            // The name
+            "name": "a", /* trailing commas */
+            "list": [1, 2,],
+        }"#;
+        assert!(parse(jsonc, Syntax::Json).is_err());
+        assert_eq!(
+            parse(jsonc, Syntax::Jsonc).ok(),
+            Json::from_string(r#"{"name": "a", "list": [1, 2]}"#)
+        );
+        for invalid in ["[1,,]", "[,]", "{,}", r#"{"a": 1,,}"#, "[1] // after\n,"] {
+            assert!(parse(invalid, Syntax::Jsonc).is_err(), "{}", invalid);
         }
 
-        Ok((Some(Json::Array(data)), array_tokens))
+        let json5 = r#"{unquoted: 'single', hex: -0xff, plus: +1, dots: [.5, 5., 5.e1]}"#;
+        assert!(parse(json5, Syntax::Jsonc).is_err());
+        assert_eq!(
+            parse(json5, Syntax::Json5).unwrap().to_string(),
+            r#"{"unquoted":"single","hex":-255,"plus":1,"dots":[0.5,5,5e1]}"#
+        );
+        let number = |input| match parse(input, Syntax::Json5) {
+            Ok(Json::Number(number)) => Some(number),
+            _ => None,
+        };
+        assert_eq!(
+            number("0x10000000000000000").unwrap(),
+            "18446744073709551616"
+        );
+        assert_eq!(number("-Infinity").unwrap(), "-Infinity");
+        assert!(Json::Number(number("NaN").unwrap())
+            .as_f64()
+            .unwrap()
+            .is_nan());
+        for invalid in [
+            "0x",
+            "0xg",
+            "+-1",
+            ".",
+            "+",
+            "01",
+            "1e",
+            "Infinityx",
+            ".e1",
+            "-.e1",
+            "+.E1",
+        ] {
+            assert_eq!(number(invalid), None, "{}", invalid);
+        }
+        // Identifiers can only name members
+        assert!(parse("[unquoted]", Syntax::Json5).is_err());
+
+        // Any identifier names a member, even one spelled like a literal,
+        // and it can be written with escapes
+        assert_eq!(
+            parse(
+                "{null: 1, true: 2, false: 3, Infinity: 4, NaN: 5, a\\u0062: 6, \\u0063: 7}",
+                Syntax::Json5
+            )
+            .unwrap()
+            .to_string(),
+            r#"{"null":1,"true":2,"false":3,"Infinity":4,"NaN":5,"ab":6,"c":7}"#
+        );
+        for invalid in [
+            "{-Infinity: 1}",
+            "{+NaN: 1}",
+            "{a\\u002d: 1}",
+            "{\\u0031: 1}",
+        ] {
+            assert!(parse(invalid, Syntax::Json5).is_err(), "{}", invalid);
+        }
+        assert!(parse("{null: 1}", Syntax::Jsonc).is_err());
+        // An escaped identifier is never a literal
+        assert!(parse("[\\u006eull]", Syntax::Json5).is_err());
+        // Only the whitespace of JSON5 is allowed
+        assert!(parse("[1,\u{2028}\u{3000}2]", Syntax::Json5).is_ok());
+        assert!(parse("[1,\u{85}2]", Syntax::Json5).is_err());
     }
 }
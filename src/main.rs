@@ -1,6 +1,10 @@
-use std::{fs::File, io::Read, time::Instant};
+use std::{
+    fs::File,
+    io::{IsTerminal, Read},
+    time::Instant,
+};
 
-use jsonschema::json::{Lexer, Parser};
+use jsonschema::json::{Diagnostic, Parser, ParserOptions, Syntax};
 
 fn main() -> Result<(), String> {
     let mut args = std::env::args();
@@ -12,34 +16,46 @@ fn main() -> Result<(), String> {
         return Err("No file provided".to_string());
     };
 
-    let string = {
+    let data = {
         let start = Instant::now();
         let mut file =
-            File::open(file_name).map_err(|_| "Could not find input file".to_string())?;
+            File::open(&file_name).map_err(|_| "Could not find input file".to_string())?;
         let mut data = Vec::new();
-        file.read_to_end(&mut data).ok();
-
-        let string = String::from_utf8(data)
-            .map_err(|e| format!("Failed to read data to string. {:?}", e))?;
+        file.read_to_end(&mut data)
+            .map_err(|e| format!("Failed to read file. {:?}", e))?;
 
         let end = Instant::now();
 
         println!("Read file in {} ms", (end - start).as_millis());
 
-        string
+        data
     };
 
-    println!("Lexing");
-    let start = Instant::now();
-    let tokens = Lexer::lex_str(&string).map_err(|e| format!("Failed to lex. {:?}", e))?;
-    let end = Instant::now();
-    println!("Lexed in {} ms", (end - start).as_millis());
-
     println!("Parsing");
     let start = Instant::now();
-    let parsed = Parser::parse_tokens(&tokens).map_err(|e| format!("Failed to parse: {:?}", e))?;
+    // Comments and the like are only accepted in files named for them
+    let syntax = match file_name.rsplit('.').next() {
+        Some("jsonc") => Syntax::Jsonc,
+        Some("json5") => Syntax::Json5,
+        _ => Syntax::Json,
+    };
+    let options = ParserOptions {
+        syntax,
+        ..Default::default()
+    };
+    let (parsed, errors) = Parser::new(&data).options(options).parse_recovering();
+    if !errors.is_empty() {
+        let colored = std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+        for error in &errors {
+            let diagnostic = Diagnostic::new(error)
+                .file_name(&file_name)
+                .colored(colored);
+            eprintln!("{}\n", diagnostic);
+        }
+        std::process::exit(1);
+    }
     let end = Instant::now();
-    println!("Lexed in {} ms", (end - start).as_millis());
+    println!("Parsed in {} ms", (end - start).as_millis());
 
     println!("{:?}", parsed);
 
@@ -0,0 +1,399 @@
+use crate::json::{Json, Key};
+
+use super::{
+    registry::{Registry, Target},
+    Annotation, AnnotationValue,
+};
+
+/// A single step in the path from the root schema to a keyword
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum LocationPart<'schema> {
+    Keyword(&'schema str),
+    Index(usize),
+}
+
+impl std::fmt::Display for LocationPart<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LocationPart::Keyword(keyword) => {
+                write!(f, "{}", keyword.replace('~', "~0").replace('/', "~1"))
+            }
+            LocationPart::Index(index) => write!(f, "{}", index),
+        }
+    }
+}
+
+fn pointer(parts: &[LocationPart]) -> String {
+    parts.iter().map(|part| format!("/{}", part)).collect()
+}
+
+/// The result of evaluating a single schema or keyword against a
+/// single location in the input
+#[derive(Debug, Clone)]
+pub(crate) struct OutputNode<'schema> {
+    pub keyword_location: String,
+    pub absolute_keyword_location: Option<String>,
+    pub instance_location: Key,
+    pub valid: bool,
+    pub annotations: Vec<Annotation<'schema>>,
+    pub children: Vec<OutputNode<'schema>>,
+}
+
+impl OutputNode<'_> {
+    fn discard_non_errors(&mut self) {
+        self.annotations.retain(|annotation| annotation.is_error());
+        for child in &mut self.children {
+            child.discard_non_errors();
+        }
+    }
+}
+
+/// A schema resource that is currently being evaluated
+#[derive(Debug, Clone, Copy)]
+struct Resource<'schema> {
+    base_uri: &'schema str,
+    /// The JSON Pointer from the root of the resource to where evaluation
+    /// entered it
+    pointer: &'schema str,
+    /// The length of the location when the resource was entered
+    depth: usize,
+}
+
+/// A part of an input value that a keyword evaluated, which
+/// `unevaluatedProperties` and `unevaluatedItems` leave alone
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Evaluated<'schema> {
+    Property(&'schema str),
+    /// The items before an index, which `prefixItems` applied to
+    Items(usize),
+    /// Every item
+    AllItems,
+    /// An item that was valid against `contains`
+    Contained(usize),
+}
+
+/// The annotations that had been produced at some point during
+/// validation, so that later ones can be discarded
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Checkpoint {
+    annotations: usize,
+    children: usize,
+}
+
+/// State that is threaded through a single validation run
+#[derive(Debug, Default)]
+pub(crate) struct ValidationContext<'schema> {
+    annotations: Vec<Annotation<'schema>>,
+    location: Vec<LocationPart<'schema>>,
+    /// Every schema resource that is currently being evaluated, outermost
+    /// first, which is also the dynamic scope of `$dynamicRef`
+    resources: Vec<Resource<'schema>>,
+    /// The schemas that references resolve to
+    registry: Option<&'schema Registry>,
+    /// The length of `annotations` at every schema or keyword that is
+    /// currently being evaluated
+    marks: Vec<usize>,
+    /// The parts of input values that the valid schemas evaluated, along
+    /// with the values, while a schema with `unevaluatedProperties` or
+    /// `unevaluatedItems` is being evaluated
+    evaluated: Vec<(&'schema Json, Evaluated<'schema>)>,
+    /// The length of `evaluated` at every schema that is currently being
+    /// evaluated
+    schemas: Vec<usize>,
+    /// How many of the schemas that are currently being evaluated have
+    /// `unevaluatedProperties` or `unevaluatedItems`
+    tracking: usize,
+    /// Whether only the outcome of validation is of interest, in which
+    /// case no annotations are produced and validation stops as soon as
+    /// the outcome is known
+    fail_fast: bool,
+    record: bool,
+    nodes: Vec<OutputNode<'schema>>,
+    root: Option<OutputNode<'schema>>,
+}
+
+impl<'schema> ValidationContext<'schema> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a context that does not produce any annotations, and that
+    /// lets validators stop at the first failure
+    pub fn fail_fast() -> Self {
+        Self {
+            fail_fast: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_fail_fast(&self) -> bool {
+        self.fail_fast
+    }
+
+    /// Create a context that records an [`OutputNode`] for every schema
+    /// and keyword that is evaluated
+    pub fn recording() -> Self {
+        Self {
+            record: true,
+            ..Self::default()
+        }
+    }
+
+    /// Create a context that validates a value which is not part of the
+    /// input, such as a property name, within the same registry and
+    /// dynamic scope as this one
+    ///
+    /// The new context does not produce any annotations.
+    pub fn detached<'a>(&self) -> ValidationContext<'a>
+    where
+        'schema: 'a,
+    {
+        ValidationContext {
+            resources: self.resources.clone(),
+            registry: self.registry,
+            ..ValidationContext::fail_fast()
+        }
+    }
+
+    /// Evaluate within `registry`, as if the schema resources at
+    /// `base_uris` had been entered
+    pub fn within(
+        mut self,
+        registry: Option<&'schema Registry>,
+        base_uris: &[&'schema str],
+    ) -> Self {
+        self.registry = registry;
+        for base_uri in base_uris {
+            self.enter_resource(base_uri, "");
+        }
+        self
+    }
+
+    pub fn enter_registry(&mut self, registry: &'schema Registry) {
+        self.registry = Some(registry);
+    }
+
+    /// Find the schema that the reference to `uri` resolves to
+    pub fn resolve(&self, uri: &str, dynamic: bool) -> Option<&'schema Target> {
+        let scope = self.resources.iter().map(|resource| resource.base_uri);
+        self.registry?.resolve(uri, dynamic, scope)
+    }
+
+    /// Add the annotation produced by `annotation`, unless annotations are
+    /// not being collected
+    pub fn annotate<F>(&mut self, annotation: F)
+    where
+        F: FnOnce() -> Annotation<'schema>,
+    {
+        if !self.fail_fast {
+            self.push(annotation());
+        }
+    }
+
+    fn push(&mut self, annotation: Annotation<'schema>) {
+        match self.nodes.last_mut() {
+            Some(node) if self.record => node.annotations.push(annotation),
+            _ => self.annotations.push(annotation),
+        }
+    }
+
+    /// Record `annotation` as if it was produced by the sibling `keyword`
+    /// of the keyword that is currently being evaluated
+    pub fn annotate_for_sibling<F>(
+        &mut self,
+        keyword: &'schema str,
+        key_to_input: &Key,
+        annotation: F,
+    ) where
+        F: FnOnce() -> Annotation<'schema>,
+    {
+        if !self.record {
+            self.annotate(annotation);
+            return;
+        }
+
+        let current = self.location.pop();
+        self.location.push(LocationPart::Keyword(keyword));
+        self.enter(key_to_input);
+        self.push(annotation());
+        self.exit(false);
+        self.location.pop();
+        self.location.extend(current);
+    }
+
+    pub fn push_location(&mut self, part: LocationPart<'schema>) {
+        self.location.push(part);
+    }
+
+    pub fn pop_location(&mut self) {
+        self.location.pop();
+    }
+
+    /// Start evaluating the schema resource at `base_uri`, from the
+    /// schema at `pointer` within it
+    pub fn enter_resource(&mut self, base_uri: &'schema str, pointer: &'schema str) {
+        self.resources.push(Resource {
+            base_uri,
+            pointer,
+            depth: self.location.len(),
+        });
+    }
+
+    pub fn exit_resource(&mut self) {
+        self.resources.pop();
+    }
+
+    pub fn keyword_location(&self) -> String {
+        pointer(&self.location)
+    }
+
+    /// The location of the current keyword, relative to the resource it
+    /// is part of, if that resource has a base URI
+    pub fn absolute_keyword_location(&self) -> Option<String> {
+        let resource = self.resources.last()?;
+        let base_uri = resource.base_uri.split('#').next().unwrap_or_default();
+        if base_uri.is_empty() {
+            return None;
+        }
+
+        Some(format!(
+            "{}#{}{}",
+            base_uri,
+            resource.pointer,
+            pointer(&self.location[resource.depth..])
+        ))
+    }
+
+    /// Start evaluating a schema or keyword at the current location
+    pub fn enter(&mut self, key_to_input: &Key) {
+        if self.fail_fast {
+            return;
+        }
+
+        self.marks.push(self.annotations.len());
+        if self.record {
+            self.nodes.push(OutputNode {
+                keyword_location: self.keyword_location(),
+                absolute_keyword_location: self.absolute_keyword_location(),
+                instance_location: key_to_input.copy_of(),
+                valid: true,
+                annotations: Vec::new(),
+                children: Vec::new(),
+            });
+        }
+    }
+
+    /// Finish evaluating the schema or keyword that was last entered
+    ///
+    /// If it was not valid, all non-error annotations that it produced are
+    /// discarded. If it was valid, the errors produced by its subschemas,
+    /// such as the failed branches of an `anyOf`, are discarded instead.
+    pub fn exit(&mut self, valid: bool) {
+        if self.fail_fast {
+            return;
+        }
+
+        let mark = self.marks.pop().unwrap_or_default();
+        if mark < self.annotations.len() {
+            let produced = self.annotations.split_off(mark);
+            self.annotations.extend(
+                produced
+                    .into_iter()
+                    .filter(|annotation| annotation.is_error() != valid),
+            );
+        }
+
+        if let Some(mut node) = self.nodes.pop() {
+            if !valid {
+                node.discard_non_errors();
+            }
+            node.valid = valid;
+            match self.nodes.last_mut() {
+                Some(parent) => parent.children.push(node),
+                None => self.root = Some(node),
+            }
+        }
+    }
+
+    /// Start evaluating a schema, which `tracks` what its keywords evaluate
+    /// if it has `unevaluatedProperties` or `unevaluatedItems`
+    pub fn enter_schema(&mut self, tracks: bool) {
+        self.schemas.push(self.evaluated.len());
+        if tracks {
+            self.tracking += 1;
+        }
+    }
+
+    /// Finish evaluating the schema that was last entered, forgetting what
+    /// it evaluated if it was not valid
+    pub fn exit_schema(&mut self, valid: bool, tracks: bool) {
+        let mark = self.schemas.pop().unwrap_or_default();
+        if !valid {
+            self.evaluated.truncate(mark);
+        }
+        if tracks {
+            self.tracking -= 1;
+        }
+    }
+
+    /// Whether what keywords evaluate is needed, in which case keywords
+    /// like `anyOf` and `contains` cannot stop as soon as their outcome is
+    /// known, since every subschema that is valid evaluates parts of the
+    /// input
+    pub fn is_tracking(&self) -> bool {
+        self.tracking > 0
+    }
+
+    /// Note that a keyword evaluated `evaluated` of `input`
+    pub fn evaluate(&mut self, input: &'schema Json, evaluated: Evaluated<'schema>) {
+        if self.is_tracking() {
+            self.evaluated.push((input, evaluated));
+        }
+    }
+
+    /// What the keywords of the schema that is being evaluated, and their
+    /// subschemas, evaluated of `input` so far
+    pub fn evaluated<'a>(
+        &'a self,
+        input: &'a Json,
+    ) -> impl Iterator<Item = Evaluated<'schema>> + 'a {
+        let mark = self.schemas.last().copied().unwrap_or_default();
+        self.evaluated[mark..]
+            .iter()
+            .filter(move |(value, _)| std::ptr::eq(*value, input))
+            .map(|(_, evaluated)| *evaluated)
+    }
+
+    /// How much has been evaluated so far, to discard what is evaluated
+    /// after it with [`ValidationContext::forget_evaluated`]
+    pub fn evaluated_mark(&self) -> usize {
+        self.evaluated.len()
+    }
+
+    pub fn forget_evaluated(&mut self, mark: usize) {
+        self.evaluated.truncate(mark);
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            annotations: self.annotations.len(),
+            children: self.nodes.last().map_or(0, |node| node.children.len()),
+        }
+    }
+
+    /// Discard everything that was produced since `checkpoint`
+    pub fn rollback(&mut self, checkpoint: Checkpoint) {
+        self.annotations.truncate(checkpoint.annotations);
+        if let Some(node) = self.nodes.last_mut() {
+            node.children.truncate(checkpoint.children);
+        }
+    }
+
+    pub fn into_annotations(self) -> Vec<Annotation<'schema>> {
+        self.annotations
+    }
+
+    pub fn into_output(self) -> Option<OutputNode<'schema>> {
+        self.root
+    }
+}
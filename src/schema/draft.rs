@@ -0,0 +1,120 @@
+//! The versions of the specification that schemas can be written against
+
+use std::fmt::Display;
+
+/// A version of the JSON Schema specification
+///
+/// The draft of a schema document decides which keywords it may use, and
+/// what some of those keywords mean.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Draft {
+    Draft4,
+    Draft6,
+    Draft7,
+    Draft201909,
+    #[default]
+    Draft202012,
+}
+
+impl Draft {
+    /// The URI of the metaschema of this draft, as used in `$schema`
+    pub fn uri(&self) -> &'static str {
+        match self {
+            Draft::Draft4 => "http://json-schema.org/draft-04/schema",
+            Draft::Draft6 => "http://json-schema.org/draft-06/schema",
+            Draft::Draft7 => "http://json-schema.org/draft-07/schema",
+            Draft::Draft201909 => "https://json-schema.org/draft/2019-09/schema",
+            Draft::Draft202012 => "https://json-schema.org/draft/2020-12/schema",
+        }
+    }
+
+    /// Look up a draft by the URI of its metaschema, with or without an
+    /// empty fragment
+    pub fn from_uri(uri: &str) -> Option<Self> {
+        let uri = uri.strip_suffix('#').unwrap_or(uri);
+        [
+            Draft::Draft4,
+            Draft::Draft6,
+            Draft::Draft7,
+            Draft::Draft201909,
+            Draft::Draft202012,
+        ]
+        .into_iter()
+        .find(|draft| draft.uri() == uri)
+    }
+
+    /// The keyword that sets the base URI of a schema resource
+    pub fn id_keyword(&self) -> &'static str {
+        match self {
+            Draft::Draft4 => "id",
+            _ => "$id",
+        }
+    }
+
+    /// Whether `$ref` makes every other keyword next to it be ignored
+    pub fn ref_overrides_siblings(&self) -> bool {
+        *self <= Draft::Draft7
+    }
+
+    /// Whether `keyword` has a meaning in this draft
+    ///
+    /// Only keywords that were added or removed after draft-04 are listed,
+    /// every other keyword is part of every draft.
+    pub fn supports(&self, keyword: &str) -> bool {
+        let (added, removed) = match keyword {
+            "const" | "contains" | "propertyNames" => (Draft::Draft6, None),
+            "if" | "contentEncoding" | "contentMediaType" => (Draft::Draft7, None),
+            "$anchor" | "$recursiveRef" | "$recursiveAnchor" | "dependentRequired"
+            | "dependentSchemas" | "minContains" | "maxContains" | "contentSchema" => {
+                (Draft::Draft201909, None)
+            }
+            "prefixItems" | "$dynamicRef" | "$dynamicAnchor" => (Draft::Draft202012, None),
+            "dependencies" | "additionalItems" => (Draft::Draft4, Some(Draft::Draft202012)),
+            _ => (Draft::Draft4, None),
+        };
+        added <= *self && removed.is_none_or(|removed| *self < removed)
+    }
+}
+
+impl Display for Draft {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Draft::Draft4 => "draft-04",
+            Draft::Draft6 => "draft-06",
+            Draft::Draft7 => "draft-07",
+            Draft::Draft201909 => "2019-09",
+            Draft::Draft202012 => "2020-12",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Draft;
+
+    #[test]
+    fn uris() {
+        assert_eq!(
+            Draft::from_uri("http://json-schema.org/draft-07/schema#"),
+            Some(Draft::Draft7)
+        );
+        assert_eq!(
+            Draft::from_uri("https://json-schema.org/draft/2019-09/schema"),
+            Some(Draft::Draft201909)
+        );
+        assert_eq!(Draft::from_uri("https://example.com/schema"), None);
+    }
+
+    #[test]
+    fn keywords() {
+        assert!(!Draft::Draft4.supports("const"));
+        assert!(Draft::Draft6.supports("const"));
+        assert!(!Draft::Draft7.supports("dependentRequired"));
+        assert!(Draft::Draft7.supports("dependencies"));
+        assert!(!Draft::Draft6.supports("contentMediaType"));
+        assert!(!Draft::Draft7.supports("contentSchema"));
+        assert!(!Draft::Draft202012.supports("dependencies"));
+        assert!(Draft::Draft202012.supports("minimum"));
+    }
+}
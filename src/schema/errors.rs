@@ -0,0 +1,663 @@
+use crate::json::{Json, Key, KeyPart};
+
+use super::{
+    keywords::{
+        annotations::{
+            ArrayError, ArrayErrorKind, LogicError, LogicErrorKind, ObjectError, ObjectErrorKind,
+            PropertyError, PropertyErrorKind, ReferenceError,
+        },
+        ArrayKeyword, LogicApplier, ObjectKeyword,
+    },
+    Annotation, AnnotationValue, JsonSchema, Registry, ValidationContext,
+};
+
+/// A unit of work that is still left to do
+enum Step<'schema> {
+    /// Evaluate `schema` against `input`, within the schema resources in
+    /// `scope`
+    Evaluate {
+        schema: &'schema JsonSchema,
+        input: &'schema Json,
+        key: Key,
+        scope: Vec<&'schema str>,
+    },
+    /// Produce `error`
+    Error(Annotation<'schema>),
+    /// Note how many errors have been produced, for the `ErrorIfFailed`
+    /// that ends the steps after it
+    Mark,
+    /// Produce `error` if any errors were produced since the matching
+    /// `Mark`
+    ErrorIfFailed(Annotation<'schema>),
+    /// Take the groups of steps that the iterator gives one at a time, so
+    /// that the items or members of the input are only scheduled as they
+    /// are reached
+    Each(Box<dyn Iterator<Item = Vec<Step<'schema>>> + 'schema>),
+}
+
+/// The steps to evaluate `input` at `key` against `schema`, followed by
+/// `error` if that produced any errors
+fn checked<'schema>(
+    schema: &'schema JsonSchema,
+    input: &'schema Json,
+    key: Key,
+    scope: Vec<&'schema str>,
+    error: Annotation<'schema>,
+) -> Vec<Step<'schema>> {
+    vec![
+        Step::Mark,
+        Step::Evaluate {
+            schema,
+            input,
+            key,
+            scope,
+        },
+        Step::ErrorIfFailed(error),
+    ]
+}
+
+/// An iterator over the errors produced by validating a piece of input
+///
+/// Errors are produced on demand: the input is only evaluated as far as is
+/// necessary to find the next error. Only errors that cause validation to
+/// fail are produced, so subschemas of an `anyOf` that passes do not
+/// produce any errors.
+pub struct ErrorIter<'schema> {
+    steps: Vec<Step<'schema>>,
+    produced: usize,
+    /// How many errors had been produced at each `Mark` whose
+    /// `ErrorIfFailed` has not been reached yet
+    marks: Vec<usize>,
+    registry: Option<&'schema Registry>,
+}
+
+impl<'schema> ErrorIter<'schema> {
+    pub(crate) fn new(schema: &'schema JsonSchema, input: &'schema Json) -> Self {
+        Self {
+            steps: vec![Step::Evaluate {
+                schema,
+                input,
+                key: Key::default(),
+                scope: Vec::new(),
+            }],
+            produced: 0,
+            marks: Vec::new(),
+            registry: None,
+        }
+    }
+
+    /// Add `steps` so that they are taken in the order they are given
+    fn schedule<I>(&mut self, steps: I)
+    where
+        I: IntoIterator<Item = Step<'schema>>,
+        I::IntoIter: DoubleEndedIterator,
+    {
+        self.steps.extend(steps.into_iter().rev());
+    }
+
+    /// Whether `input` is valid against `schema`, within the schema
+    /// resources in `scope`
+    fn is_valid(
+        &self,
+        schema: &'schema JsonSchema,
+        input: &'schema Json,
+        scope: &[&'schema str],
+    ) -> bool {
+        let mut context = ValidationContext::fail_fast().within(self.registry, scope);
+        schema.validate_json(&mut Key::default(), input, &mut context)
+    }
+
+    fn evaluate(
+        &mut self,
+        schema: &'schema JsonSchema,
+        input: &'schema Json,
+        key: Key,
+        scope: Vec<&'schema str>,
+    ) {
+        let subschema = |schema| Step::Evaluate {
+            schema,
+            input,
+            key: key.copy_of(),
+            scope: scope.clone(),
+        };
+
+        match schema {
+            JsonSchema::Boolean(true) => {}
+            JsonSchema::Root { schema, registry } => {
+                self.registry = Some(registry);
+                self.schedule([subschema(schema)]);
+            }
+            JsonSchema::Resource { id, schema } => {
+                let mut scope = scope.clone();
+                scope.push(id);
+                self.schedule([Step::Evaluate {
+                    schema,
+                    input,
+                    key,
+                    scope,
+                }]);
+            }
+            JsonSchema::Reference(reference) => {
+                let uri = reference.uri();
+                let target = self.registry.and_then(|registry| {
+                    registry.resolve(uri, reference.is_dynamic(), scope.iter().copied())
+                });
+
+                match target {
+                    Some(target) => {
+                        let mut scope = scope.clone();
+                        scope.push(&target.base_uri);
+                        self.schedule([Step::Evaluate {
+                            schema: &target.schema,
+                            input,
+                            key,
+                            scope,
+                        }]);
+                    }
+                    None => self.schedule([Step::Error(ReferenceError { key, reference }.into())]),
+                }
+            }
+            // `unevaluatedProperties` and `unevaluatedItems` depend on what
+            // the other keywords evaluate, so their schema is evaluated all
+            // at once
+            JsonSchema::Keywords(_) if schema.has_unevaluated() => {
+                self.eager(schema, input, key, &scope)
+            }
+            JsonSchema::Keywords(keywords) => {
+                let steps: Vec<_> = keywords.iter().map(subschema).collect();
+                self.schedule(steps);
+            }
+            JsonSchema::Properties(properties) => {
+                let object = match input {
+                    Json::Object(object) => object,
+                    _ => return,
+                };
+
+                let mut steps = Vec::new();
+                for property in properties {
+                    match object.get_key_value(property.name()) {
+                        Some((name, value)) => {
+                            let mut property_key = key.copy_of();
+                            property_key.push(KeyPart::Identifier(name.clone()));
+                            let error = PropertyError {
+                                property,
+                                key: property_key.copy_of(),
+                                kind: PropertyErrorKind::Invalid,
+                            };
+                            steps.extend(checked(
+                                property.schema(),
+                                value,
+                                property_key,
+                                scope.clone(),
+                                error.into(),
+                            ));
+                        }
+                        None if property.required() => steps.push(Step::Error(
+                            PropertyError {
+                                property,
+                                key: key.copy_of(),
+                                kind: PropertyErrorKind::Missing { required: true },
+                            }
+                            .into(),
+                        )),
+                        None => {}
+                    }
+                }
+                self.schedule(steps);
+            }
+            JsonSchema::Logic(applier) => self.logic(applier, input, key, scope),
+            JsonSchema::Array(
+                keyword @ (ArrayKeyword::PrefixItems(_)
+                | ArrayKeyword::Items { .. }
+                | ArrayKeyword::Contains { .. }),
+            ) => self.array(keyword, input, key, scope),
+            JsonSchema::Object(
+                keyword @ (ObjectKeyword::AdditionalProperties { .. }
+                | ObjectKeyword::PatternProperties(_)
+                | ObjectKeyword::DependentSchemas(_)),
+            ) => self.object(keyword, input, key, scope),
+            // The other keywords apply no subschemas to the input, or, like
+            // `propertyNames` and `contentSchema`, only to values that are
+            // not part of it, and produce at most one error for each
+            // property
+            _ => self.eager(schema, input, key, &scope),
+        }
+    }
+
+    fn logic(
+        &mut self,
+        applier: &'schema LogicApplier,
+        input: &'schema Json,
+        key: Key,
+        scope: Vec<&'schema str>,
+    ) {
+        let error = |kind| -> Annotation<'schema> {
+            LogicError {
+                key: key.copy_of(),
+                schema: applier,
+                kind,
+            }
+            .into()
+        };
+        let subschema = |schema| Step::Evaluate {
+            schema,
+            input,
+            key: key.copy_of(),
+            scope: scope.clone(),
+        };
+        let subschemas = |schemas: &'schema [JsonSchema]| schemas.iter().map(subschema);
+        let is_valid = |schema| self.is_valid(schema, input, &scope);
+
+        match applier {
+            LogicApplier::AllOf(schemas) => {
+                let mut steps = vec![Step::Mark];
+                steps.extend(subschemas(schemas));
+                steps.push(Step::ErrorIfFailed(error(LogicErrorKind::AllOfMissing(
+                    schemas,
+                ))));
+                self.schedule(steps);
+            }
+            LogicApplier::AnyOf(schemas) => {
+                if !schemas.iter().any(is_valid) {
+                    let mut steps: Vec<_> = subschemas(schemas).collect();
+                    steps.push(Step::Error(error(LogicErrorKind::AnyOfMissing(schemas))));
+                    self.schedule(steps);
+                }
+            }
+            LogicApplier::OneOf(schemas) => {
+                let valid = schemas
+                    .iter()
+                    .filter(|schema| is_valid(schema))
+                    .take(2)
+                    .count();
+
+                if valid == 0 {
+                    let mut steps: Vec<_> = subschemas(schemas).collect();
+                    steps.push(Step::Error(error(LogicErrorKind::OneOfMissing(schemas))));
+                    self.schedule(steps);
+                } else if valid > 1 {
+                    self.schedule([Step::Error(error(LogicErrorKind::OneOfMoreThanOne(
+                        schemas,
+                    )))]);
+                }
+            }
+            LogicApplier::Not(schema) => {
+                if is_valid(schema) {
+                    self.schedule([Step::Error(error(LogicErrorKind::NotIs(schema)))]);
+                }
+            }
+            LogicApplier::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let (branch, kind) = if is_valid(condition) {
+                    (then, then.as_deref().map(LogicErrorKind::ThenMissing))
+                } else {
+                    (
+                        otherwise,
+                        otherwise.as_deref().map(LogicErrorKind::ElseMissing),
+                    )
+                };
+
+                if let (Some(branch), Some(kind)) = (branch, kind) {
+                    let key = key.copy_of();
+                    self.schedule(checked(branch, input, key, scope.clone(), error(kind)));
+                }
+            }
+        }
+    }
+
+    /// Schedule the items of the array `input` for the keywords that apply
+    /// subschemas to them
+    fn array(
+        &mut self,
+        keyword: &'schema ArrayKeyword,
+        input: &'schema Json,
+        key: Key,
+        scope: Vec<&'schema str>,
+    ) {
+        let items = match input {
+            Json::Array(items) => items,
+            _ => return,
+        };
+        let item = move |schema, (index, value), key: &Key, scope| {
+            let mut item_key = key.copy_of();
+            item_key.push(KeyPart::Index(index));
+            let error = ArrayError {
+                key: item_key.copy_of(),
+                keyword,
+                kind: ArrayErrorKind::InvalidItem,
+            };
+            checked(schema, value, item_key, scope, error.into())
+        };
+
+        match keyword {
+            ArrayKeyword::PrefixItems(schemas) => {
+                let steps =
+                    schemas
+                        .iter()
+                        .zip(items)
+                        .enumerate()
+                        .map(move |(index, (schema, value))| {
+                            item(schema, (index, value), &key, scope.clone())
+                        });
+                self.schedule([Step::Each(Box::new(steps))]);
+            }
+            ArrayKeyword::Items { prefix, schema } => {
+                let steps = items
+                    .iter()
+                    .enumerate()
+                    .skip(*prefix)
+                    .map(move |value| item(schema, value, &key, scope.clone()));
+                self.schedule([Step::Each(Box::new(steps))]);
+            }
+            ArrayKeyword::Contains { schema, min, max } => {
+                // Only the number of matching items matters, and all of
+                // them only need to be counted if there is a maximum
+                let scope = &scope;
+                let matching = items
+                    .iter()
+                    .filter(|value| self.is_valid(schema, value, scope));
+                let count = match max {
+                    Some(_) => matching.count(),
+                    None => matching.take(*min).count(),
+                };
+
+                let kind = if count < *min {
+                    ArrayErrorKind::TooFewContained { count }
+                } else if max.is_some_and(|max| count > max) {
+                    ArrayErrorKind::TooManyContained { count }
+                } else {
+                    return;
+                };
+                let key = key.copy_of();
+                self.schedule([Step::Error(ArrayError { key, keyword, kind }.into())]);
+            }
+            _ => {}
+        }
+    }
+
+    /// Schedule the members of the object `input` for the keywords that
+    /// apply subschemas to them
+    fn object(
+        &mut self,
+        keyword: &'schema ObjectKeyword,
+        input: &'schema Json,
+        key: Key,
+        scope: Vec<&'schema str>,
+    ) {
+        let object = match input {
+            Json::Object(object) => object,
+            _ => return,
+        };
+        let member = move |schema, (name, value): (&String, &'schema Json), key: &Key, scope| {
+            let mut property_key = key.copy_of();
+            property_key.push(KeyPart::Identifier(name.clone()));
+            let error = ObjectError {
+                key: property_key.copy_of(),
+                keyword,
+                kind: ObjectErrorKind::InvalidProperty { name: name.clone() },
+            };
+            checked(schema, value, property_key, scope, error.into())
+        };
+
+        match keyword {
+            ObjectKeyword::AdditionalProperties {
+                properties,
+                patterns,
+                schema,
+            } => {
+                let steps = object
+                    .iter()
+                    .filter(|(name, _)| {
+                        !properties.contains(name) && !patterns.iter().any(|re| re.is_match(name))
+                    })
+                    .map(move |property| member(schema, property, &key, scope.clone()));
+                self.schedule([Step::Each(Box::new(steps))]);
+            }
+            ObjectKeyword::PatternProperties(patterns) => {
+                let steps = patterns.iter().flat_map(move |(pattern, schema)| {
+                    let (key, scope) = (key.copy_of(), scope.clone());
+                    object
+                        .iter()
+                        .filter(|(name, _)| pattern.is_match(name))
+                        .map(move |property| member(schema, property, &key, scope.clone()))
+                });
+                self.schedule([Step::Each(Box::new(steps))]);
+            }
+            ObjectKeyword::DependentSchemas(dependents) => {
+                let mut steps = Vec::new();
+                for (property, schema) in dependents {
+                    if object.contains_key(property) {
+                        let error = ObjectError {
+                            key: key.copy_of(),
+                            keyword,
+                            kind: ObjectErrorKind::InvalidDependent {
+                                property: property.clone(),
+                            },
+                        };
+                        let key = key.copy_of();
+                        steps.extend(checked(schema, input, key, scope.clone(), error.into()));
+                    }
+                }
+                self.schedule(steps);
+            }
+            _ => {}
+        }
+    }
+
+    /// Evaluate a schema that does not apply any subschemas to the input
+    /// all at once
+    fn eager(
+        &mut self,
+        schema: &'schema JsonSchema,
+        input: &'schema Json,
+        mut key: Key,
+        scope: &[&'schema str],
+    ) {
+        let mut context = ValidationContext::new().within(self.registry, scope);
+        schema.validate_json(&mut key, input, &mut context);
+
+        let errors: Vec<_> = context
+            .into_annotations()
+            .into_iter()
+            .filter(|annotation| annotation.is_error())
+            .map(Step::Error)
+            .collect();
+        self.schedule(errors);
+    }
+}
+
+impl<'schema> Iterator for ErrorIter<'schema> {
+    type Item = Annotation<'schema>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(step) = self.steps.pop() {
+            match step {
+                Step::Evaluate {
+                    schema,
+                    input,
+                    key,
+                    scope,
+                } => self.evaluate(schema, input, key, scope),
+                Step::Error(error) => {
+                    self.produced += 1;
+                    return Some(error);
+                }
+                Step::Mark => self.marks.push(self.produced),
+                Step::ErrorIfFailed(error) => {
+                    if self.marks.pop().is_some_and(|since| since < self.produced) {
+                        self.produced += 1;
+                        return Some(error);
+                    }
+                }
+                Step::Each(mut steps) => {
+                    if let Some(group) = steps.next() {
+                        self.steps.push(Step::Each(steps));
+                        self.schedule(group);
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        json::Json,
+        schema::{JsonSchema, Loader},
+    };
+
+    fn load(schema: &str) -> JsonSchema {
+        Loader::new()
+            .load(&Json::from_string(schema).unwrap())
+            .unwrap()
+    }
+
+    /// The errors of `iter_errors` and `validate`, in the order they give
+    /// them
+    fn both(schema: &JsonSchema, input: &Json) -> (Vec<String>, Vec<String>) {
+        let lazy = schema.iter_errors(input).map(|e| e.to_string()).collect();
+        let eager = schema
+            .validate(input)
+            .errors()
+            .map(|e| e.to_string())
+            .collect();
+        (lazy, eager)
+    }
+
+    #[test]
+    fn same_errors_as_validate() {
+        let schema = Json::from_string(
+            r#"{
+                "properties": {
+                    "a": {"type": "string"},
+                    "b": {"allOf": [{"type": "number"}, {"const": 1}]},
+                    "c": {"anyOf": [{"type": "string"}, {"type": "null"}]},
+                    "d": {"not": {"type": "array"}}
+                },
+                "required": ["e"]
+            }"#,
+        )
+        .unwrap();
+        let schema = Loader::new().load(&schema).unwrap();
+
+        let input = Json::from_string(r#"{"a": 1, "b": "x", "c": 2, "d": []}"#).unwrap();
+        let mut lazy: Vec<String> = schema.iter_errors(&input).map(|e| e.to_string()).collect();
+        let mut eager: Vec<String> = schema
+            .validate(&input)
+            .errors()
+            .map(|e| e.to_string())
+            .collect();
+        lazy.sort();
+        eager.sort();
+        assert_eq!(lazy, eager);
+
+        let input = Json::from_string(r#"{"c": "x", "e": 1}"#).unwrap();
+        assert_eq!(schema.iter_errors(&input).count(), 0);
+    }
+
+    #[test]
+    fn stops_early() {
+        let schema = Json::from_string(r#"{"properties": {"a": {"type": "string"}}}"#).unwrap();
+        let schema = Loader::new().load(&schema).unwrap();
+        let input = Json::from_string(r#"{"a": 1}"#).unwrap();
+
+        let mut errors = schema.iter_errors(&input);
+        assert_eq!(
+            errors.next().unwrap().to_string(),
+            r#"/a: expected string, found number 1"#
+        );
+        assert_eq!(
+            errors.next().unwrap().to_string(),
+            r#"/a: value of property "a" is invalid"#
+        );
+        assert!(errors.next().is_none());
+    }
+
+    #[test]
+    fn follows_references() {
+        let schema = Json::from_string(
+            r##"{
+                "$defs": {"name": {"type": "string"}},
+                "properties": {"a": {"$ref": "#/$defs/name"}},
+                "if": {"required": ["b"]},
+                "then": {"required": ["c"]}
+            }"##,
+        )
+        .unwrap();
+        let schema = Loader::new().load(&schema).unwrap();
+
+        let input = Json::from_string(r#"{"a": 1, "b": 2}"#).unwrap();
+        let mut lazy: Vec<String> = schema.iter_errors(&input).map(|e| e.to_string()).collect();
+        let mut eager: Vec<String> = schema
+            .validate(&input)
+            .errors()
+            .map(|e| e.to_string())
+            .collect();
+        lazy.sort();
+        eager.sort();
+        assert_eq!(lazy, eager);
+        assert_eq!(lazy.len(), 4);
+    }
+
+    #[test]
+    fn sibling_properties() {
+        let schema = load(r#"{"properties": {"a": {"type": "string"}, "b": {"type": "string"}}}"#);
+        let input = Json::from_string(r#"{"a": 1, "b": "x"}"#).unwrap();
+        let (lazy, eager) = both(&schema, &input);
+        assert_eq!(lazy, eager);
+        assert_eq!(lazy.len(), 2);
+    }
+
+    #[test]
+    fn items_and_members() {
+        let schema = load(
+            r#"{
+                "prefixItems": [{"type": "string"}],
+                "items": {"type": "number"},
+                "contains": {"const": 1},
+                "maxContains": 1
+            }"#,
+        );
+        let input = Json::from_string(r#"[1, "a", 1, 2]"#).unwrap();
+        let (mut lazy, mut eager) = both(&schema, &input);
+        lazy.sort();
+        eager.sort();
+        assert_eq!(lazy, eager);
+        assert_eq!(lazy.len(), 5);
+
+        let schema = load(
+            r#"{
+                "properties": {"a": true},
+                "patternProperties": {"^b": {"type": "string"}},
+                "additionalProperties": {"type": "number"},
+                "dependentSchemas": {"a": {"required": ["d"]}}
+            }"#,
+        );
+        let input = Json::from_string(r#"{"a": 1, "b1": 2, "b2": "x", "c": "y"}"#).unwrap();
+        let (mut lazy, mut eager) = both(&schema, &input);
+        lazy.sort();
+        eager.sort();
+        assert_eq!(lazy, eager);
+        assert_eq!(lazy.len(), 6);
+    }
+
+    #[test]
+    fn bounded() {
+        let schema = load(r#"{"items": {"type": "string"}}"#);
+        let input = Json::Array(vec![Json::Null; 100_000]);
+
+        // Only the item that failed is evaluated before the first error
+        let mut errors = schema.iter_errors(&input);
+        assert_eq!(
+            errors.next().unwrap().to_string(),
+            "/0: expected string, found null"
+        );
+        assert!(errors.steps.len() < 8);
+        assert_eq!(errors.count(), 199_999);
+    }
+}
@@ -0,0 +1,370 @@
+use std::{collections::HashSet, fmt::Display};
+
+use crate::{
+    json::{Json, Key, KeyPart},
+    schema::{
+        describe_location, Annotation, Describe, Evaluated, JsonSchema, JsonSchemaValidator,
+        LocationPart, ValidationContext,
+    },
+};
+
+#[derive(Debug, Clone)]
+pub enum ArrayErrorKind {
+    /// The item was not valid against `prefixItems`, `items` or
+    /// `unevaluatedItems`
+    InvalidItem,
+    /// `count` items were valid against `contains`
+    TooFewContained {
+        count: usize,
+    },
+    TooManyContained {
+        count: usize,
+    },
+    TooShort {
+        len: usize,
+    },
+    TooLong {
+        len: usize,
+    },
+    /// The items at `first` and `second` are equal
+    Duplicate {
+        first: usize,
+        second: usize,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct ArrayError<'schema> {
+    pub key: Key,
+    pub keyword: &'schema ArrayKeyword,
+    pub kind: ArrayErrorKind,
+}
+
+impl<'schema> From<ArrayError<'schema>> for Annotation<'schema> {
+    fn from(error: ArrayError<'schema>) -> Self {
+        Annotation::ArrayError(error)
+    }
+}
+
+impl Describe for ArrayError<'_> {
+    fn describe(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (min, max) = match self.keyword {
+            ArrayKeyword::Contains { min, max, .. } => (*min, max.unwrap_or_default()),
+            ArrayKeyword::MinItems(min) => (*min, 0),
+            ArrayKeyword::MaxItems(max) => (0, *max),
+            _ => (0, 0),
+        };
+
+        match self.kind {
+            ArrayErrorKind::InvalidItem => write!(f, "item is invalid"),
+            ArrayErrorKind::TooFewContained { count } => write!(
+                f,
+                "expected at least {} items valid against contains, found {}",
+                min, count
+            ),
+            ArrayErrorKind::TooManyContained { count } => write!(
+                f,
+                "expected at most {} items valid against contains, found {}",
+                max, count
+            ),
+            ArrayErrorKind::TooShort { len } => {
+                write!(f, "expected at least {} items, found {}", min, len)
+            }
+            ArrayErrorKind::TooLong { len } => {
+                write!(f, "expected at most {} items, found {}", max, len)
+            }
+            ArrayErrorKind::Duplicate { first, second } => {
+                write!(f, "items {} and {} are equal", first, second)
+            }
+        }
+    }
+}
+
+impl Display for ArrayError<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", describe_location(&self.key))?;
+        self.describe(f)
+    }
+}
+
+/// Keywords that only apply to arrays
+#[derive(Debug, Clone)]
+pub enum ArrayKeyword {
+    PrefixItems(Vec<JsonSchema>),
+    /// `items`, which applies to the items after the first `prefix`, to
+    /// which `prefixItems` applies
+    Items {
+        prefix: usize,
+        schema: Box<JsonSchema>,
+    },
+    /// `contains`, together with `minContains` and `maxContains`
+    Contains {
+        schema: Box<JsonSchema>,
+        min: usize,
+        max: Option<usize>,
+    },
+    MinItems(usize),
+    MaxItems(usize),
+    /// `uniqueItems` with a value of `true`
+    UniqueItems,
+    /// `unevaluatedItems`, which applies to the items that no other keyword
+    /// of the schema, or of the subschemas that the input is valid against,
+    /// evaluated. The items that `contains` matched only count as evaluated
+    /// from 2020-12 on.
+    UnevaluatedItems {
+        schema: Box<JsonSchema>,
+        contains: bool,
+    },
+}
+
+impl ArrayKeyword {
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            ArrayKeyword::PrefixItems(_) => "prefixItems",
+            ArrayKeyword::Items { .. } => "items",
+            ArrayKeyword::Contains { .. } => "contains",
+            ArrayKeyword::MinItems(_) => "minItems",
+            ArrayKeyword::MaxItems(_) => "maxItems",
+            ArrayKeyword::UniqueItems => "uniqueItems",
+            ArrayKeyword::UnevaluatedItems { .. } => "unevaluatedItems",
+        }
+    }
+
+    fn error<'schema>(
+        &'schema self,
+        key: &Key,
+        kind: ArrayErrorKind,
+        context: &mut ValidationContext<'schema>,
+    ) {
+        context.annotate(|| {
+            ArrayError {
+                key: key.copy_of(),
+                keyword: self,
+                kind,
+            }
+            .into()
+        });
+    }
+}
+
+impl From<ArrayKeyword> for JsonSchema {
+    fn from(keyword: ArrayKeyword) -> Self {
+        JsonSchema::Array(keyword)
+    }
+}
+
+/// Validate the item at `index` in the array at `key_to_input` against
+/// `schema`
+fn validate_item<'schema>(
+    keyword: &'schema ArrayKeyword,
+    schema: &'schema JsonSchema,
+    key_to_input: &Key,
+    (index, item): (usize, &'schema Json),
+    context: &mut ValidationContext<'schema>,
+) -> bool {
+    let item_key = &mut key_to_input.copy_of();
+    item_key.push(KeyPart::Index(index));
+
+    let valid = schema.validate_json(item_key, item, context);
+    if !valid {
+        keyword.error(item_key, ArrayErrorKind::InvalidItem, context);
+    }
+    valid
+}
+
+impl JsonSchemaValidator for ArrayKeyword {
+    fn validate_json<'schema>(
+        &'schema self,
+        key_to_input: &mut Key,
+        input: &'schema Json,
+        context: &mut ValidationContext<'schema>,
+    ) -> bool {
+        let items = match input {
+            Json::Array(items) => items,
+            _ => return true,
+        };
+
+        let mut success = true;
+        match self {
+            ArrayKeyword::PrefixItems(schemas) => {
+                for (index, (schema, item)) in schemas.iter().zip(items).enumerate() {
+                    context.push_location(LocationPart::Index(index));
+                    let valid = validate_item(self, schema, key_to_input, (index, item), context);
+                    context.pop_location();
+
+                    if !valid {
+                        success = false;
+                        if context.is_fail_fast() {
+                            break;
+                        }
+                    }
+                }
+                context.evaluate(input, Evaluated::Items(schemas.len()));
+            }
+            ArrayKeyword::Items { prefix, schema } => {
+                for item in items.iter().enumerate().skip(*prefix) {
+                    if !validate_item(self, schema, key_to_input, item, context) {
+                        success = false;
+                        if context.is_fail_fast() {
+                            break;
+                        }
+                    }
+                }
+                context.evaluate(input, Evaluated::AllItems);
+            }
+            ArrayKeyword::Contains { schema, min, max } => {
+                let mut count = 0;
+                for (index, item) in items.iter().enumerate() {
+                    let item_key = &mut key_to_input.copy_of();
+                    item_key.push(KeyPart::Index(index));
+
+                    // Items that do not match are not errors in themselves
+                    let checkpoint = context.checkpoint();
+                    if !schema.validate_json(item_key, item, context) {
+                        context.rollback(checkpoint);
+                        continue;
+                    }
+
+                    count += 1;
+                    context.evaluate(input, Evaluated::Contained(index));
+                    let decided = max.is_none() && count >= *min && !context.is_tracking();
+                    if context.is_fail_fast() && decided {
+                        break;
+                    }
+                }
+
+                if count < *min {
+                    success = false;
+                    self.error(
+                        key_to_input,
+                        ArrayErrorKind::TooFewContained { count },
+                        context,
+                    );
+                } else if max.is_some_and(|max| count > max) {
+                    success = false;
+                    self.error(
+                        key_to_input,
+                        ArrayErrorKind::TooManyContained { count },
+                        context,
+                    );
+                }
+            }
+            ArrayKeyword::MinItems(min) => {
+                if items.len() < *min {
+                    success = false;
+                    let len = items.len();
+                    self.error(key_to_input, ArrayErrorKind::TooShort { len }, context);
+                }
+            }
+            ArrayKeyword::MaxItems(max) => {
+                if items.len() > *max {
+                    success = false;
+                    let len = items.len();
+                    self.error(key_to_input, ArrayErrorKind::TooLong { len }, context);
+                }
+            }
+            ArrayKeyword::UniqueItems => {
+                let duplicate = items.iter().enumerate().find_map(|(second, item)| {
+                    items[..second]
+                        .iter()
+                        .position(|other| other == item)
+                        .map(|first| (first, second))
+                });
+
+                if let Some((first, second)) = duplicate {
+                    success = false;
+                    self.error(
+                        key_to_input,
+                        ArrayErrorKind::Duplicate { first, second },
+                        context,
+                    );
+                }
+            }
+            ArrayKeyword::UnevaluatedItems { schema, contains } => {
+                let mut prefix = 0;
+                let mut contained = HashSet::new();
+                for evaluated in context.evaluated(input) {
+                    match evaluated {
+                        Evaluated::Items(count) => prefix = prefix.max(count),
+                        Evaluated::AllItems => prefix = items.len(),
+                        Evaluated::Contained(index) if *contains => {
+                            contained.insert(index);
+                        }
+                        Evaluated::Contained(_) | Evaluated::Property(_) => {}
+                    }
+                }
+
+                for item in items.iter().enumerate().skip(prefix) {
+                    if contained.contains(&item.0) {
+                        continue;
+                    }
+                    if !validate_item(self, schema, key_to_input, item, context) {
+                        success = false;
+                        if context.is_fail_fast() {
+                            break;
+                        }
+                    }
+                }
+                context.evaluate(input, Evaluated::AllItems);
+            }
+        }
+        success
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{json::Json, schema::Loader};
+
+    fn errors(schema: &str, input: &str) -> Vec<String> {
+        let schema = Loader::new()
+            .load(&Json::from_string(schema).unwrap())
+            .unwrap();
+        let input = Json::from_string(input).unwrap();
+        let result = schema.validate(&input);
+        assert_eq!(result.success, result.errors().next().is_none());
+        result.errors().map(|error| error.to_string()).collect()
+    }
+
+    #[test]
+    fn items() {
+        let schema = r#"{"prefixItems": [{"type": "string"}], "items": {"type": "number"}}"#;
+        assert!(errors(schema, r#"["a", 1, 2]"#).is_empty());
+        assert!(errors(schema, "[]").is_empty());
+        assert!(errors(schema, "{}").is_empty());
+        assert!(errors(schema, r#"[1, 1]"#).contains(&"/0: item is invalid".to_string()));
+        assert!(errors(schema, r#"["a", "b"]"#).contains(&"/1: item is invalid".to_string()));
+    }
+
+    #[test]
+    fn contains() {
+        let schema = r#"{"contains": {"const": 1}, "minContains": 2, "maxContains": 3}"#;
+        assert!(errors(schema, "[1, 2, 1]").is_empty());
+        assert_eq!(
+            errors(schema, "[1, 2]"),
+            vec!["/: expected at least 2 items valid against contains, found 1"]
+        );
+        assert_eq!(
+            errors(schema, "[1, 1, 1, 1]"),
+            vec!["/: expected at most 3 items valid against contains, found 4"]
+        );
+    }
+
+    #[test]
+    fn sizes() {
+        let schema = r#"{"minItems": 1, "maxItems": 2, "uniqueItems": true}"#;
+        assert!(errors(schema, "[1, 2]").is_empty());
+        assert_eq!(
+            errors(schema, "[]"),
+            vec!["/: expected at least 1 items, found 0"]
+        );
+        assert_eq!(
+            errors(schema, "[1, 2, 3]"),
+            vec!["/: expected at most 2 items, found 3"]
+        );
+        assert_eq!(
+            errors(schema, "[1, 1.0]"),
+            vec!["/: items 0 and 1 are equal"]
+        );
+    }
+}
@@ -0,0 +1,433 @@
+use std::fmt::Display;
+
+use crate::{
+    json::{Json, Key},
+    schema::{
+        describe_location, describe_value, Annotation, AnnotationValue, Describe, JsonSchema,
+        JsonSchemaValidator, ValidationContext,
+    },
+};
+
+#[derive(Debug, Clone)]
+pub enum ContentErrorKind {
+    /// The string could not be decoded with `contentEncoding`
+    InvalidEncoding,
+    /// The decoded content is not a document of `contentMediaType`
+    InvalidMediaType,
+    /// The decoded document was not valid against `contentSchema`
+    InvalidContent,
+}
+
+#[derive(Debug, Clone)]
+pub struct ContentError<'schema> {
+    pub key: Key,
+    pub content: &'schema Content,
+    pub kind: ContentErrorKind,
+    pub value: &'schema Json,
+}
+
+impl<'schema> From<ContentError<'schema>> for Annotation<'schema> {
+    fn from(error: ContentError<'schema>) -> Self {
+        Annotation::ContentError(error)
+    }
+}
+
+impl AnnotationValue for ContentError<'_> {
+    fn is_error(&self) -> bool {
+        self.content.asserted()
+    }
+}
+
+impl Describe for ContentError<'_> {
+    fn describe(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            ContentErrorKind::InvalidEncoding => {
+                let encoding = self.content.encoding.as_ref().map(Encoding::name);
+                write!(
+                    f,
+                    "expected {}-encoded string, found {}",
+                    encoding.unwrap_or_default(),
+                    describe_value(self.value)
+                )
+            }
+            ContentErrorKind::InvalidMediaType => {
+                let media_type = self.content.media_type.as_ref().map(MediaType::name);
+                write!(f, "content is not valid {}", media_type.unwrap_or_default())
+            }
+            ContentErrorKind::InvalidContent => {
+                write!(f, "content is not valid against contentSchema")
+            }
+        }
+    }
+}
+
+impl Display for ContentError<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", describe_location(&self.key))?;
+        self.describe(f)
+    }
+}
+
+/// The encodings of `contentEncoding` that strings can be decoded from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Encoding {
+    Base16,
+    Base32,
+    Base64,
+    /// An encoding that is not known, which strings are not decoded from
+    Unknown(String),
+}
+
+impl Encoding {
+    /// Look up an encoding by the name used for it in `contentEncoding`
+    pub fn from_name(name: &str) -> Self {
+        match name.to_ascii_lowercase().as_str() {
+            "base16" => Self::Base16,
+            "base32" => Self::Base32,
+            "base64" => Self::Base64,
+            _ => Self::Unknown(name.to_string()),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Base16 => "base16",
+            Self::Base32 => "base32",
+            Self::Base64 => "base64",
+            Self::Unknown(name) => name,
+        }
+    }
+
+    /// Decode `value` as described by RFC 4648, or `None` if it is not
+    /// encoded with this encoding or the encoding is not known
+    pub fn decode(&self, value: &str) -> Option<Vec<u8>> {
+        match self {
+            Self::Base16 => decode(value, 4, 2, |c| (c as char).to_digit(16).map(|d| d as u8)),
+            Self::Base32 => decode(value, 5, 8, |c| match c {
+                b'A'..=b'Z' => Some(c - b'A'),
+                b'2'..=b'7' => Some(c - b'2' + 26),
+                _ => None,
+            }),
+            Self::Base64 => decode(value, 6, 4, |c| match c {
+                b'A'..=b'Z' => Some(c - b'A'),
+                b'a'..=b'z' => Some(c - b'a' + 26),
+                b'0'..=b'9' => Some(c - b'0' + 52),
+                b'+' => Some(62),
+                b'/' => Some(63),
+                _ => None,
+            }),
+            Self::Unknown(_) => None,
+        }
+    }
+}
+
+/// Decode `value`, in which every character stands for `bits` bits, and
+/// which is padded with `=` to a multiple of `block` characters
+fn decode(
+    value: &str,
+    bits: u32,
+    block: usize,
+    digit: impl Fn(u8) -> Option<u8>,
+) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(block) {
+        return None;
+    }
+
+    let data = value.trim_end_matches('=');
+    let padding = value.len() - data.len();
+    // Padding only ever fills the last block, and never makes up all of it
+    if padding >= block || (padding > 0 && bits == 4) {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(data.len() * bits as usize / 8);
+    let (mut buffer, mut buffered) = (0u32, 0);
+    for c in data.bytes() {
+        buffer = (buffer << bits) | digit(c)? as u32;
+        buffered += bits;
+        if buffered >= 8 {
+            buffered -= 8;
+            bytes.push((buffer >> buffered) as u8);
+            buffer &= (1 << buffered) - 1;
+        }
+    }
+
+    // What is left over must be the unused, zero bits of the last
+    // character, rather than a whole character too many
+    if buffered >= bits || buffer != 0 {
+        return None;
+    }
+    Some(bytes)
+}
+
+/// The media types of `contentMediaType` that decoded content can be
+/// parsed as
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MediaType {
+    /// `application/json`, or any media type with the `+json` suffix
+    Json(String),
+    /// A media type that is not known, which content is not parsed as
+    Unknown(String),
+}
+
+impl MediaType {
+    /// Look up a media type by the name used for it in
+    /// `contentMediaType`, which may include parameters like `charset`
+    pub fn from_name(name: &str) -> Self {
+        let essence = name.split(';').next().unwrap_or_default().trim();
+        let essence = essence.to_ascii_lowercase();
+        if essence == "application/json" || essence.ends_with("+json") {
+            Self::Json(name.to_string())
+        } else {
+            Self::Unknown(name.to_string())
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Json(name) | Self::Unknown(name) => name,
+        }
+    }
+
+    /// Parse `content` as a document of this media type, or `None` if it
+    /// is not one or the media type is not known
+    pub fn parse(&self, content: &[u8]) -> Option<Json> {
+        match self {
+            Self::Json(_) => Json::from_string(std::str::from_utf8(content).ok()?),
+            Self::Unknown(_) => None,
+        }
+    }
+}
+
+/// `contentEncoding`, `contentMediaType` and `contentSchema`, which
+/// describe a document that is embedded in a string
+///
+/// Content only applies to strings. Unless content is asserted, a string
+/// that cannot be decoded, parsed or validated produces an annotation
+/// rather than an error, as described by the content vocabulary.
+#[derive(Debug, Clone)]
+pub struct Content {
+    encoding: Option<Encoding>,
+    media_type: Option<MediaType>,
+    /// Applies to the document that the string is parsed as
+    schema: Option<Box<JsonSchema>>,
+    assert: bool,
+}
+
+impl Content {
+    pub fn new(
+        encoding: Option<Encoding>,
+        media_type: Option<MediaType>,
+        schema: Option<JsonSchema>,
+        assert: bool,
+    ) -> Self {
+        Self {
+            encoding,
+            media_type,
+            schema: schema.map(Box::new),
+            assert,
+        }
+    }
+
+    pub fn keyword(&self) -> &'static str {
+        if self.media_type.is_some() {
+            "contentMediaType"
+        } else {
+            "contentEncoding"
+        }
+    }
+
+    pub fn encoding(&self) -> Option<&Encoding> {
+        self.encoding.as_ref()
+    }
+
+    pub fn media_type(&self) -> Option<&MediaType> {
+        self.media_type.as_ref()
+    }
+
+    /// Whether strings whose content is not valid fail validation
+    pub fn asserted(&self) -> bool {
+        self.assert
+    }
+
+    fn error<'schema>(
+        &'schema self,
+        key: &Key,
+        kind: ContentErrorKind,
+        value: &'schema Json,
+        context: &mut ValidationContext<'schema>,
+    ) -> bool {
+        context.annotate(|| {
+            ContentError {
+                key: key.copy_of(),
+                content: self,
+                kind,
+                value,
+            }
+            .into()
+        });
+        !self.assert
+    }
+}
+
+impl From<Content> for JsonSchema {
+    fn from(content: Content) -> Self {
+        JsonSchema::Content(content)
+    }
+}
+
+impl JsonSchemaValidator for Content {
+    fn validate_json<'schema>(
+        &'schema self,
+        key_to_input: &mut Key,
+        input: &'schema Json,
+        context: &mut ValidationContext<'schema>,
+    ) -> bool {
+        let value = match input {
+            Json::String(value) => value,
+            _ => return true,
+        };
+
+        let decoded;
+        let content = match &self.encoding {
+            Some(Encoding::Unknown(_)) => return true,
+            Some(encoding) => match encoding.decode(value) {
+                Some(bytes) => {
+                    decoded = bytes;
+                    &decoded
+                }
+                None => {
+                    let kind = ContentErrorKind::InvalidEncoding;
+                    return self.error(key_to_input, kind, input, context);
+                }
+            },
+            None => value.as_bytes(),
+        };
+
+        let document = match &self.media_type {
+            None | Some(MediaType::Unknown(_)) => return true,
+            Some(media_type) => match media_type.parse(content) {
+                Some(document) => document,
+                None => {
+                    let kind = ContentErrorKind::InvalidMediaType;
+                    return self.error(key_to_input, kind, input, context);
+                }
+            },
+        };
+
+        // The document is not part of the input, so it is validated
+        // separately, and only the outcome is recorded
+        if let Some(schema) = &self.schema {
+            let mut content_context = context.detached();
+            if !schema.validate_json(&mut Key::default(), &document, &mut content_context) {
+                let kind = ContentErrorKind::InvalidContent;
+                return self.error(key_to_input, kind, input, context);
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Encoding;
+    use crate::{json::Json, schema::Loader};
+
+    #[test]
+    fn encodings() {
+        let decode = |name, value| Encoding::from_name(name).decode(value);
+
+        assert_eq!(
+            decode("base64", "eyJhIjogMX0="),
+            Some(br#"{"a": 1}"#.to_vec())
+        );
+        assert_eq!(decode("base64", ""), Some(vec![]));
+        assert_eq!(decode("base64", "Zm9vYg=="), Some(b"foob".to_vec()));
+        assert_eq!(decode("base64", "Zm9vYg="), None);
+        assert_eq!(decode("base64", "Zm9vYh=="), None);
+        assert_eq!(decode("base64", "Zm9v===="), None);
+        assert_eq!(decode("base64", "Zm9*"), None);
+
+        assert_eq!(decode("base32", "MZXW6YQ="), Some(b"foob".to_vec()));
+        assert_eq!(
+            decode("base32", "MZXW6YTBOI======"),
+            Some(b"foobar".to_vec())
+        );
+        assert_eq!(decode("base32", "MZXW6YQ"), None);
+        assert_eq!(decode("base32", "mzxw6yq="), None);
+
+        assert_eq!(decode("base16", "666F6f"), Some(b"foo".to_vec()));
+        assert_eq!(decode("base16", "666"), None);
+        assert_eq!(decode("base16", "6G"), None);
+
+        assert_eq!(decode("quoted-printable", "foo"), None);
+    }
+
+    fn errors(loader: Loader, schema: &str, input: &str) -> Vec<String> {
+        let schema = loader.load(&Json::from_string(schema).unwrap()).unwrap();
+        let input = Json::from_string(input).unwrap();
+        let result = schema.validate(&input);
+        assert_eq!(result.success, result.errors().next().is_none());
+        assert_eq!(result.success, schema.is_valid(&input));
+        result.errors().map(|error| error.to_string()).collect()
+    }
+
+    #[test]
+    fn content() {
+        let schema = r#"{
+            "contentEncoding": "base64",
+            "contentMediaType": "application/json",
+            "contentSchema": {"required": ["a"]}
+        }"#;
+        let asserting = || Loader::new().assert_content(true);
+
+        // {"a": 1}, {"b": 1} and {"a":
+        assert!(errors(asserting(), schema, r#""eyJhIjogMX0=""#).is_empty());
+        assert!(errors(asserting(), schema, "1").is_empty());
+        assert_eq!(
+            errors(asserting(), schema, r#""eyJhIjog""#),
+            vec!["/: content is not valid application/json"]
+        );
+        assert_eq!(
+            errors(asserting(), schema, r#""eyJiIjogMX0=""#),
+            vec!["/: content is not valid against contentSchema"]
+        );
+        assert_eq!(
+            errors(asserting(), schema, r#""e30""#),
+            vec![r#"/: expected base64-encoded string, found string "e30""#]
+        );
+
+        // Content is only annotated by default
+        let schema = Loader::new()
+            .load(&Json::from_string(schema).unwrap())
+            .unwrap();
+        let input = Json::from_string(r#""eyJiIjogMX0=""#).unwrap();
+        let result = schema.validate(&input);
+        assert!(result.success);
+        assert_eq!(result.annotations.len(), 1);
+    }
+
+    #[test]
+    fn media_types() {
+        let schema = r#"{
+            "contentEncoding": "base64",
+            "contentMediaType": "application/schema+json; charset=utf-8"
+        }"#;
+        let loader = || Loader::new().assert_content(true);
+        // {"type": "string"} and {
+        assert!(errors(loader(), schema, r#""eyJ0eXBlIjogInN0cmluZyJ9""#).is_empty());
+        assert_eq!(
+            errors(loader(), schema, r#""ew==""#),
+            vec!["/: content is not valid application/schema+json; charset=utf-8"]
+        );
+
+        // Content of media types that are not known is not checked
+        let schema = r#"{"contentEncoding": "base64", "contentMediaType": "image/png"}"#;
+        assert!(errors(loader(), schema, r#""iVBORw0KGgo=""#).is_empty());
+        assert_eq!(errors(loader(), schema, r#""iVBORw0KGgo""#).len(), 1);
+
+        // `contentSchema` does nothing without `contentMediaType`
+        let schema = r#"{"contentEncoding": "base16", "contentSchema": false}"#;
+        assert!(errors(loader(), schema, r#""7B7D""#).is_empty());
+    }
+}
@@ -0,0 +1,228 @@
+use std::{fmt::Display, sync::Arc};
+
+use crate::{
+    json::{Json, Key},
+    schema::{
+        describe_location, Annotation, AnnotationValue, Describe, JsonSchemaValidator,
+        ValidationContext,
+    },
+};
+
+/// A keyword that is not defined by the specification, such as
+/// `x-max-bytes`
+pub trait CustomKeyword: Send + Sync {
+    /// Check `input` against this keyword, describing the problem if it is
+    /// not valid
+    fn validate(&self, input: &Json) -> Result<(), String>;
+
+    /// The annotation that this keyword produces for valid `input`, if any
+    fn annotation(&self, _input: &Json) -> Option<Json> {
+        None
+    }
+}
+
+/// Compiles the value of a custom keyword in a schema into a
+/// [`CustomKeyword`]
+///
+/// Returns `None` if the value is not valid for the keyword. This is
+/// implemented for every `Fn(&Json) -> Option<Box<dyn CustomKeyword>>`.
+pub trait KeywordFactory: Send + Sync {
+    fn compile(&self, value: &Json) -> Option<Box<dyn CustomKeyword>>;
+}
+
+impl<F> KeywordFactory for F
+where
+    F: Fn(&Json) -> Option<Box<dyn CustomKeyword>> + Send + Sync,
+{
+    fn compile(&self, value: &Json) -> Option<Box<dyn CustomKeyword>> {
+        self(value)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum CustomAnnotationKind {
+    /// The input was not valid against the keyword
+    Error(String),
+    /// The keyword annotated the input with a value
+    Value(Json),
+}
+
+#[derive(Debug, Clone)]
+pub struct CustomAnnotation<'schema> {
+    pub key: Key,
+    pub keyword: &'schema Custom,
+    pub kind: CustomAnnotationKind,
+}
+
+impl<'schema> From<CustomAnnotation<'schema>> for Annotation<'schema> {
+    fn from(annotation: CustomAnnotation<'schema>) -> Self {
+        Annotation::Custom(annotation)
+    }
+}
+
+impl AnnotationValue for CustomAnnotation<'_> {
+    fn is_error(&self) -> bool {
+        matches!(self.kind, CustomAnnotationKind::Error(_))
+    }
+}
+
+impl Describe for CustomAnnotation<'_> {
+    fn describe(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            CustomAnnotationKind::Error(message) => write!(f, "{}", message),
+            CustomAnnotationKind::Value(value) => write!(f, "{} is {}", self.keyword.name(), value),
+        }
+    }
+}
+
+impl Display for CustomAnnotation<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", describe_location(&self.key))?;
+        self.describe(f)
+    }
+}
+
+/// A compiled custom keyword, together with its name
+#[derive(Clone)]
+pub struct Custom {
+    name: String,
+    keyword: Arc<dyn CustomKeyword>,
+}
+
+impl std::fmt::Debug for Custom {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Custom").field("name", &self.name).finish()
+    }
+}
+
+impl Custom {
+    pub fn new(name: &str, keyword: Box<dyn CustomKeyword>) -> Self {
+        Self {
+            name: name.to_string(),
+            keyword: keyword.into(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl JsonSchemaValidator for Custom {
+    fn validate_json<'schema>(
+        &'schema self,
+        key_to_input: &mut Key,
+        input: &'schema Json,
+        context: &mut ValidationContext<'schema>,
+    ) -> bool {
+        match self.keyword.validate(input) {
+            Ok(()) => {
+                if !context.is_fail_fast() {
+                    if let Some(value) = self.keyword.annotation(input) {
+                        context.annotate(|| {
+                            CustomAnnotation {
+                                key: key_to_input.copy_of(),
+                                keyword: self,
+                                kind: CustomAnnotationKind::Value(value),
+                            }
+                            .into()
+                        });
+                    }
+                }
+                true
+            }
+            Err(message) => {
+                context.annotate(|| {
+                    CustomAnnotation {
+                        key: key_to_input.copy_of(),
+                        keyword: self,
+                        kind: CustomAnnotationKind::Error(message),
+                    }
+                    .into()
+                });
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CustomKeyword;
+    use crate::{
+        json::Json,
+        schema::{LoadError, Loader, OutputFormat},
+    };
+
+    struct MaxBytes(usize);
+
+    impl CustomKeyword for MaxBytes {
+        fn validate(&self, input: &Json) -> Result<(), String> {
+            match input {
+                Json::String(value) if value.len() > self.0 => Err(format!(
+                    "expected at most {} bytes, found {}",
+                    self.0,
+                    value.len()
+                )),
+                _ => Ok(()),
+            }
+        }
+
+        fn annotation(&self, input: &Json) -> Option<Json> {
+            match input {
+                Json::String(value) => Some(Json::String(format!("{} bytes", value.len()))),
+                _ => None,
+            }
+        }
+    }
+
+    fn loader() -> Loader {
+        Loader::new().with_keyword("x-max-bytes", |value: &Json| match value {
+            Json::Number(number) => {
+                let max = number.parse().ok()?;
+                Some(Box::new(MaxBytes(max)) as Box<dyn CustomKeyword>)
+            }
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn validates() {
+        let schema = Json::from_string(r#"{"properties": {"a": {"x-max-bytes": 3}}}"#).unwrap();
+        let schema = loader().load(&schema).unwrap();
+
+        let input = Json::from_string(r#"{"a": "abc"}"#).unwrap();
+        assert!(schema.is_valid(&input));
+
+        let input = Json::from_string(r#"{"a": "abcd"}"#).unwrap();
+        let result = schema.validate(&input);
+        assert!(!result.success);
+        assert!(result
+            .errors()
+            .any(|error| error.to_string() == "/a: expected at most 3 bytes, found 4"));
+
+        let output = schema.output(&input, OutputFormat::Basic);
+        assert!(output
+            .to_string()
+            .contains(r#""keywordLocation":"/properties/a/x-max-bytes""#));
+    }
+
+    #[test]
+    fn annotates() {
+        let schema = Json::from_string(r#"{"x-max-bytes": 10}"#).unwrap();
+        let schema = loader().load(&schema).unwrap();
+
+        let input = Json::from_string(r#""abc""#).unwrap();
+        let output = schema.output(&input, OutputFormat::Basic);
+        assert!(output.to_string().contains(r#""annotation":"3 bytes""#));
+    }
+
+    #[test]
+    fn invalid_value() {
+        let schema = Json::from_string(r#"{"x-max-bytes": "three"}"#).unwrap();
+        assert!(matches!(
+            loader().load(&schema),
+            Err(LoadError::InvalidKeywordValue(_))
+        ));
+    }
+}
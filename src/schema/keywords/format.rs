@@ -0,0 +1,929 @@
+use std::{fmt::Display, sync::Arc};
+
+use crate::{
+    json::{Json, Key},
+    schema::{
+        describe_location, describe_value, regex::Regex, Annotation, AnnotationValue, Describe,
+        JsonSchemaValidator, ValidationContext,
+    },
+};
+
+#[derive(Debug, Clone)]
+pub struct FormatError<'schema> {
+    pub key: Key,
+    pub format: &'schema Format,
+    pub value: &'schema Json,
+}
+
+impl<'schema> From<FormatError<'schema>> for Annotation<'schema> {
+    fn from(error: FormatError<'schema>) -> Self {
+        Annotation::FormatError(error)
+    }
+}
+
+impl AnnotationValue for FormatError<'_> {
+    fn is_error(&self) -> bool {
+        self.format.asserted()
+    }
+}
+
+impl Describe for FormatError<'_> {
+    fn describe(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "expected {}, found {}",
+            self.format.name(),
+            describe_value(self.value)
+        )
+    }
+}
+
+impl Display for FormatError<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", describe_location(&self.key))?;
+        self.describe(f)
+    }
+}
+
+/// The formats defined by the JSON Schema specification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatKind {
+    DateTime,
+    Date,
+    Time,
+    Duration,
+    Email,
+    IdnEmail,
+    Hostname,
+    IdnHostname,
+    Ipv4,
+    Ipv6,
+    Uri,
+    UriReference,
+    Iri,
+    IriReference,
+    UriTemplate,
+    Uuid,
+    JsonPointer,
+    RelativeJsonPointer,
+    Regex,
+    /// A format that is not known, which every string is valid against
+    Unknown,
+}
+
+impl FormatKind {
+    /// Look up a format by the name used for it in the `format` keyword
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "date-time" => Self::DateTime,
+            "date" => Self::Date,
+            "time" => Self::Time,
+            "duration" => Self::Duration,
+            "email" => Self::Email,
+            "idn-email" => Self::IdnEmail,
+            "hostname" => Self::Hostname,
+            "idn-hostname" => Self::IdnHostname,
+            "ipv4" => Self::Ipv4,
+            "ipv6" => Self::Ipv6,
+            "uri" => Self::Uri,
+            "uri-reference" => Self::UriReference,
+            "iri" => Self::Iri,
+            "iri-reference" => Self::IriReference,
+            "uri-template" => Self::UriTemplate,
+            "uuid" => Self::Uuid,
+            "json-pointer" => Self::JsonPointer,
+            "relative-json-pointer" => Self::RelativeJsonPointer,
+            "regex" => Self::Regex,
+            _ => Self::Unknown,
+        }
+    }
+
+    /// Check whether `value` is valid against this format
+    pub fn check(&self, value: &str) -> bool {
+        match self {
+            Self::DateTime => date_time(value),
+            Self::Date => date(value),
+            Self::Time => time(value),
+            Self::Duration => duration(value),
+            Self::Email => email(value, false),
+            Self::IdnEmail => email(value, true),
+            Self::Hostname => hostname(value, false),
+            Self::IdnHostname => hostname(value, true),
+            Self::Ipv4 => ipv4(value),
+            Self::Ipv6 => ipv6(value),
+            Self::Uri => uri(value, true, false),
+            Self::UriReference => uri(value, false, false),
+            Self::Iri => uri(value, true, true),
+            Self::IriReference => uri(value, false, true),
+            Self::UriTemplate => uri_template(value),
+            Self::Uuid => uuid(value),
+            Self::JsonPointer => json_pointer(value),
+            Self::RelativeJsonPointer => relative_json_pointer(value),
+            Self::Regex => regex(value),
+            Self::Unknown => true,
+        }
+    }
+}
+
+/// Checks strings against a format that is not defined by the
+/// specification
+///
+/// This is implemented for every `Fn(&str) -> bool`.
+pub trait FormatChecker: Send + Sync {
+    fn check(&self, value: &str) -> bool;
+}
+
+impl<F> FormatChecker for F
+where
+    F: Fn(&str) -> bool + Send + Sync,
+{
+    fn check(&self, value: &str) -> bool {
+        self(value)
+    }
+}
+
+/// The `format` keyword
+///
+/// Formats only apply to strings. Unless the format is asserted, a string
+/// that is not valid against it produces an annotation rather than an
+/// error, as described by the format-annotation vocabulary.
+#[derive(Clone)]
+pub struct Format {
+    name: String,
+    kind: FormatKind,
+    /// Used instead of `kind` for custom formats
+    checker: Option<Arc<dyn FormatChecker>>,
+    assert: bool,
+}
+
+impl std::fmt::Debug for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Format")
+            .field("name", &self.name)
+            .field("kind", &self.kind)
+            .field("custom", &self.checker.is_some())
+            .field("assert", &self.assert)
+            .finish()
+    }
+}
+
+impl Format {
+    pub fn new(name: &str, assert: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            kind: FormatKind::from_name(name),
+            checker: None,
+            assert,
+        }
+    }
+
+    /// A format that is checked by `checker`
+    pub fn custom(name: &str, checker: Arc<dyn FormatChecker>, assert: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            kind: FormatKind::Unknown,
+            checker: Some(checker),
+            assert,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> FormatKind {
+        self.kind
+    }
+
+    /// Whether strings are checked against this format at all
+    pub fn is_known(&self) -> bool {
+        self.checker.is_some() || self.kind != FormatKind::Unknown
+    }
+
+    /// Check whether `value` is valid against this format
+    pub fn check(&self, value: &str) -> bool {
+        match &self.checker {
+            Some(checker) => checker.check(value),
+            None => self.kind.check(value),
+        }
+    }
+
+    /// Whether strings that are not valid against this format fail
+    /// validation
+    pub fn asserted(&self) -> bool {
+        self.assert
+    }
+}
+
+impl JsonSchemaValidator for Format {
+    fn validate_json<'schema>(
+        &'schema self,
+        key_to_input: &mut Key,
+        input: &'schema Json,
+        context: &mut ValidationContext<'schema>,
+    ) -> bool {
+        let value = match input {
+            Json::String(value) => value,
+            _ => return true,
+        };
+
+        if self.check(value) {
+            return true;
+        }
+
+        context.annotate(|| {
+            FormatError {
+                key: key_to_input.copy_of(),
+                format: self,
+                value: input,
+            }
+            .into()
+        });
+        !self.assert
+    }
+}
+
+/// Parse `digits` as a decimal number, if it consists of exactly `len`
+/// ASCII digits
+fn number(digits: &str, len: usize) -> Option<u32> {
+    if digits.len() == len && digits.bytes().all(|b| b.is_ascii_digit()) {
+        digits.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// RFC 3339 `full-date`
+fn date(value: &str) -> bool {
+    let mut parts = value.split('-');
+    let (year, month, day) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(year), Some(month), Some(day), None) => (year, month, day),
+        _ => return false,
+    };
+
+    let (year, month, day) = match (number(year, 4), number(month, 2), number(day, 2)) {
+        (Some(year), Some(month), Some(day)) => (year, month, day),
+        _ => return false,
+    };
+
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+
+    (1..=days).contains(&day)
+}
+
+/// RFC 3339 `full-time`, which allows a leap second at 23:59:60 UTC
+fn time(value: &str) -> bool {
+    let offset_start = match value.find(['Z', 'z', '+', '-']) {
+        Some(idx) => idx,
+        None => return false,
+    };
+    let (partial, offset) = value.split_at(offset_start);
+
+    let (partial, fraction) = match partial.split_once('.') {
+        Some((partial, fraction)) => (partial, Some(fraction)),
+        None => (partial, None),
+    };
+    if let Some(fraction) = fraction {
+        if fraction.is_empty() || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+            return false;
+        }
+    }
+
+    let clock = |value: &str, seconds: bool| -> Option<Vec<u32>> {
+        let parts: Vec<&str> = value.split(':').collect();
+        if parts.len() != if seconds { 3 } else { 2 } {
+            return None;
+        }
+        parts.iter().map(|part| number(part, 2)).collect()
+    };
+
+    let (hour, minute, second) = match clock(partial, true).as_deref() {
+        Some(&[hour, minute, second]) if hour <= 23 && minute <= 59 && second <= 60 => {
+            (hour, minute, second)
+        }
+        _ => return false,
+    };
+
+    let offset_minutes = match offset {
+        "Z" | "z" => 0,
+        _ => {
+            let (negative, offset) = match offset.split_at(1) {
+                ("+", offset) => (false, offset),
+                ("-", offset) => (true, offset),
+                _ => return false,
+            };
+            match clock(offset, false).as_deref() {
+                Some(&[hours, minutes]) if hours <= 23 && minutes <= 59 => {
+                    let minutes = (hours * 60 + minutes) as i64;
+                    if negative {
+                        -minutes
+                    } else {
+                        minutes
+                    }
+                }
+                _ => return false,
+            }
+        }
+    };
+
+    if second == 60 {
+        let utc = (hour * 60 + minute) as i64 - offset_minutes;
+        return utc.rem_euclid(24 * 60) == 23 * 60 + 59;
+    }
+
+    true
+}
+
+/// RFC 3339 `date-time`
+fn date_time(value: &str) -> bool {
+    match value.as_bytes().get(10) {
+        Some(b'T' | b't') => date(&value[..10]) && time(&value[11..]),
+        _ => false,
+    }
+}
+
+/// The ISO 8601 durations described in appendix A of RFC 3339
+fn duration(value: &str) -> bool {
+    /// Check that `value` is a sequence of numbers followed by one of
+    /// `units`, in order, and return how many there are
+    fn components(mut value: &str, units: &[u8]) -> Option<usize> {
+        let mut count = 0;
+        let mut next_unit = 0;
+        while !value.is_empty() {
+            let digits = value.bytes().take_while(|b| b.is_ascii_digit()).count();
+            let unit = *value.as_bytes().get(digits)?;
+            let position = units[next_unit..].iter().position(|u| *u == unit)?;
+
+            if digits == 0 {
+                return None;
+            }
+
+            next_unit += position + 1;
+            count += 1;
+            value = &value[digits + 1..];
+        }
+        Some(count)
+    }
+
+    let value = match value.strip_prefix('P') {
+        Some(value) => value,
+        None => return false,
+    };
+
+    if let Some(weeks) = value.strip_suffix('W') {
+        return !weeks.is_empty() && weeks.bytes().all(|b| b.is_ascii_digit());
+    }
+
+    let (date, time) = match value.split_once('T') {
+        Some((date, time)) => (date, Some(time)),
+        None => (value, None),
+    };
+
+    let date = match components(date, b"YMD") {
+        Some(count) => count,
+        None => return false,
+    };
+
+    match time.map(|time| components(time, b"HMS")) {
+        Some(Some(count)) => count > 0,
+        Some(None) => false,
+        None => date > 0,
+    }
+}
+
+/// RFC 5321 `Mailbox`, optionally with the internationalized extensions
+/// from RFC 6531
+fn email(value: &str, idn: bool) -> bool {
+    let (local, domain) = match value.rfind('@') {
+        Some(idx) => (&value[..idx], &value[idx + 1..]),
+        None => return false,
+    };
+
+    let local_valid = if local.len() >= 2 && local.starts_with('"') && local.ends_with('"') {
+        quoted_string(&local[1..local.len() - 1], idn)
+    } else {
+        !local.is_empty()
+            && !local.starts_with('.')
+            && !local.ends_with('.')
+            && !local.contains("..")
+            && local.chars().all(|c| {
+                c.is_ascii_alphanumeric()
+                    || "!#$%&'*+-/=?^_`{|}~.".contains(c)
+                    || idn && !c.is_ascii() && !c.is_whitespace() && !c.is_control()
+            })
+    };
+
+    let domain_valid = if let Some(literal) = domain
+        .strip_prefix('[')
+        .and_then(|domain| domain.strip_suffix(']'))
+    {
+        match literal.strip_prefix("IPv6:") {
+            Some(address) => ipv6(address),
+            None => ipv4(literal),
+        }
+    } else {
+        hostname(domain, idn)
+    };
+
+    local_valid && domain_valid
+}
+
+/// The contents of an RFC 5321 `Quoted-string`
+fn quoted_string(value: &str, idn: bool) -> bool {
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' => match chars.next() {
+                Some(c) => c,
+                None => return false,
+            },
+            '"' => return false,
+            c => c,
+        };
+
+        if !(c == ' ' || c.is_ascii_graphic() || idn && !c.is_ascii()) {
+            return false;
+        }
+    }
+    true
+}
+
+/// RFC 1123 host names, optionally with internationalized labels
+fn hostname(value: &str, idn: bool) -> bool {
+    let is_separator =
+        |c: char| c == '.' || idn && matches!(c, '\u{3002}' | '\u{ff0e}' | '\u{ff61}');
+
+    let value = value.strip_suffix(is_separator).unwrap_or(value);
+    if value.is_empty() || value.chars().count() > 253 {
+        return false;
+    }
+
+    value.split(is_separator).all(|label| {
+        let length = label.chars().count();
+        if !(1..=63).contains(&length) || label.starts_with('-') || label.ends_with('-') {
+            return false;
+        }
+
+        // Labels with hyphens in the third and fourth position are
+        // reserved for encodings such as punycode
+        let bytes = label.as_bytes();
+        if bytes.len() >= 4 && &bytes[2..4] == b"--" && !label.starts_with("xn--") {
+            return false;
+        }
+
+        label.chars().all(|c| {
+            c.is_ascii_alphanumeric()
+                || c == '-'
+                || idn && !c.is_ascii() && !c.is_whitespace() && !c.is_control()
+        })
+    })
+}
+
+/// The dotted-quad notation from RFC 2673
+fn ipv4(value: &str) -> bool {
+    let parts: Vec<&str> = value.split('.').collect();
+    parts.len() == 4
+        && parts.iter().all(|part| {
+            (1..=3).contains(&part.len())
+                && part.bytes().all(|b| b.is_ascii_digit())
+                && (part.len() == 1 || !part.starts_with('0'))
+                && part.parse::<u8>().is_ok()
+        })
+}
+
+/// The text representations of IPv6 addresses from RFC 4291
+fn ipv6(value: &str) -> bool {
+    /// Count the 16-bit groups in `groups`, which may end in an IPv4
+    /// address if `last` is set
+    fn count(groups: &str, last: bool) -> Option<usize> {
+        if groups.is_empty() {
+            return Some(0);
+        }
+
+        let groups: Vec<&str> = groups.split(':').collect();
+        let mut count = 0;
+        for (idx, group) in groups.iter().enumerate() {
+            if last && idx == groups.len() - 1 && group.contains('.') {
+                if !ipv4(group) {
+                    return None;
+                }
+                count += 2;
+            } else if (1..=4).contains(&group.len()) && group.bytes().all(|b| b.is_ascii_hexdigit())
+            {
+                count += 1;
+            } else {
+                return None;
+            }
+        }
+        Some(count)
+    }
+
+    match value.split_once("::") {
+        Some((head, tail)) => {
+            if tail.contains("::") {
+                return false;
+            }
+            match (count(head, false), count(tail, true)) {
+                (Some(head), Some(tail)) => head + tail <= 7,
+                _ => false,
+            }
+        }
+        None => count(value, true) == Some(8),
+    }
+}
+
+/// Whether `value` consists of characters that match `allowed` and
+/// percent-encoded octets
+fn percent_encoded(value: &str, iri: bool, allowed: impl Fn(char) -> bool) -> bool {
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        let valid = if c == '%' {
+            matches!(
+                (chars.next(), chars.next()),
+                (Some(a), Some(b)) if a.is_ascii_hexdigit() && b.is_ascii_hexdigit()
+            )
+        } else {
+            allowed(c) || iri && !c.is_ascii() && !c.is_whitespace() && !c.is_control()
+        };
+
+        if !valid {
+            return false;
+        }
+    }
+    true
+}
+
+fn unreserved(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "-._~".contains(c)
+}
+
+fn sub_delim(c: char) -> bool {
+    "!$&'()*+,;=".contains(c)
+}
+
+fn pchar(c: char) -> bool {
+    unreserved(c) || sub_delim(c) || c == ':' || c == '@'
+}
+
+/// RFC 3986 `URI` (if `absolute` is set) or `URI-reference`, or their
+/// RFC 3987 equivalents if `iri` is set
+fn uri(value: &str, absolute: bool, iri: bool) -> bool {
+    let (value, fragment) = match value.split_once('#') {
+        Some((value, fragment)) => (value, Some(fragment)),
+        None => (value, None),
+    };
+    let (value, query) = match value.split_once('?') {
+        Some((value, query)) => (value, Some(query)),
+        None => (value, None),
+    };
+
+    let query_char = |c| pchar(c) || c == '/' || c == '?';
+    if !fragment.is_none_or(|fragment| percent_encoded(fragment, iri, query_char))
+        || !query.is_none_or(|query| percent_encoded(query, iri, query_char))
+    {
+        return false;
+    }
+
+    let (scheme, rest) = match value.find([':', '/']) {
+        Some(idx) if value[idx..].starts_with(':') => (Some(&value[..idx]), &value[idx + 1..]),
+        _ => (None, value),
+    };
+
+    match scheme {
+        Some(scheme) => {
+            let mut chars = scheme.chars();
+            let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+                && chars.all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+            if !valid {
+                return false;
+            }
+        }
+        None if absolute => return false,
+        None => {}
+    }
+
+    let path = match rest.strip_prefix("//") {
+        Some(rest) => {
+            let end = rest.find('/').unwrap_or(rest.len());
+            if !authority(&rest[..end], iri) {
+                return false;
+            }
+            &rest[end..]
+        }
+        None => rest,
+    };
+
+    percent_encoded(path, iri, |c| pchar(c) || c == '/')
+}
+
+/// RFC 3986 `authority`
+fn authority(value: &str, iri: bool) -> bool {
+    let (userinfo, host) = match value.rsplit_once('@') {
+        Some((userinfo, host)) => (Some(userinfo), host),
+        None => (None, value),
+    };
+
+    if let Some(userinfo) = userinfo {
+        if !percent_encoded(userinfo, iri, |c| unreserved(c) || sub_delim(c) || c == ':') {
+            return false;
+        }
+    }
+
+    let (host, port) = if let Some(rest) = host.strip_prefix('[') {
+        match rest.split_once(']') {
+            Some((literal, port)) => {
+                let valid = ipv6(literal)
+                    || literal.starts_with(['v', 'V'])
+                        && literal.contains('.')
+                        && literal
+                            .chars()
+                            .all(|c| unreserved(c) || sub_delim(c) || c == ':');
+                if !valid {
+                    return false;
+                }
+                ("", port)
+            }
+            None => return false,
+        }
+    } else {
+        match host.rfind(':') {
+            Some(idx) => (&host[..idx], &host[idx..]),
+            None => (host, ""),
+        }
+    };
+
+    let port_valid =
+        port.is_empty() || port.starts_with(':') && port[1..].bytes().all(|b| b.is_ascii_digit());
+
+    port_valid && percent_encoded(host, iri, |c| unreserved(c) || sub_delim(c))
+}
+
+/// RFC 6570 URI templates
+fn uri_template(value: &str) -> bool {
+    let mut in_expression = false;
+    let mut literal = String::new();
+    for c in value.chars() {
+        match c {
+            '{' if !in_expression => in_expression = true,
+            '}' if in_expression => in_expression = false,
+            '{' | '}' => return false,
+            c if !in_expression => literal.push(c),
+            c => {
+                if !(c.is_ascii_alphanumeric() || "_%.,:*+#./;?&=!@|$()'-".contains(c)) {
+                    return false;
+                }
+            }
+        }
+    }
+
+    let reserved = |c: char| pchar(c) || "/?#[]".contains(c);
+    !in_expression && percent_encoded(&literal, true, reserved)
+}
+
+/// RFC 4122 UUIDs, in their string representation
+fn uuid(value: &str) -> bool {
+    value.len() == 36
+        && value.bytes().enumerate().all(|(idx, b)| match idx {
+            8 | 13 | 18 | 23 => b == b'-',
+            _ => b.is_ascii_hexdigit(),
+        })
+}
+
+/// RFC 6901 JSON Pointers
+fn json_pointer(value: &str) -> bool {
+    if !value.is_empty() && !value.starts_with('/') {
+        return false;
+    }
+
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '~' && !matches!(chars.next(), Some('0' | '1')) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Relative JSON Pointers, as described in the draft of the same name
+fn relative_json_pointer(value: &str) -> bool {
+    let digits = value.bytes().take_while(|b| b.is_ascii_digit()).count();
+    if digits == 0 || digits > 1 && value.starts_with('0') {
+        return false;
+    }
+
+    let rest = &value[digits..];
+    rest == "#" || json_pointer(rest)
+}
+
+/// Regular expressions in the dialect of ECMA-262, with the `u` flag
+fn regex(value: &str) -> bool {
+    Regex::new(value).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FormatKind;
+
+    fn check(format: &str, valid: &[&str], invalid: &[&str]) {
+        let kind = FormatKind::from_name(format);
+        for value in valid {
+            assert!(kind.check(value), "{} should be a valid {}", value, format);
+        }
+        for value in invalid {
+            assert!(
+                !kind.check(value),
+                "{} should not be a valid {}",
+                value,
+                format
+            );
+        }
+    }
+
+    #[test]
+    fn dates_and_times() {
+        check(
+            "date-time",
+            &[
+                "1963-06-19T08:30:06.283185Z",
+                "1998-12-31T23:59:60Z",
+                "1998-12-31T15:59:60.123-08:00",
+                "1963-06-19t08:30:06z",
+            ],
+            &[
+                "1998-12-31T22:59:60Z",
+                "1990-02-31T15:59:59.123-08:00",
+                "1990-12-31T15:59:59-24:00",
+                "06/19/1963 08:30:06 PST",
+                "2013-350T01:01:01",
+                "1963-06-1৪T00:00:00Z",
+                "2020-01-01T08:30:06Z01:00",
+            ],
+        );
+        check(
+            "date",
+            &["2020-02-29", "2000-02-29", "2021-12-31"],
+            &[
+                "2021-02-29",
+                "1900-02-29",
+                "2020-13-01",
+                "2020-1-01",
+                "20200101",
+            ],
+        );
+        check(
+            "time",
+            &["08:30:06Z", "23:59:60Z", "08:30:06.5+01:00"],
+            &[
+                "08:30:06",
+                "24:00:00Z",
+                "08:30:06.Z",
+                "22:59:60Z",
+                "8:30:06Z",
+                "08:30:06Z01:00",
+                "08:30:06z+01:00",
+            ],
+        );
+        check(
+            "duration",
+            &["P4DT12H30M5S", "P4Y", "PT0S", "P1M", "PT1M", "P2W"],
+            &[
+                "P",
+                "PT",
+                "P1D2H",
+                "P2D1Y",
+                "P1",
+                "PT1D",
+                "P1Y2W",
+                "4DT12H30M5S",
+            ],
+        );
+    }
+
+    #[test]
+    fn addresses() {
+        check(
+            "email",
+            &[
+                "joe.bloggs@example.com",
+                "\"joe bloggs\"@example.com",
+                "te~st@example.com",
+                "joe@[127.0.0.1]",
+                "joe@[IPv6:::1]",
+            ],
+            &[
+                "2962",
+                ".test@example.com",
+                "test.@example.com",
+                "te..st@example.com",
+                "joe@[127.0.0.300]",
+            ],
+        );
+        check("idn-email", &["실례@실례.테스트"], &["2962"]);
+        check(
+            "hostname",
+            &["www.example.com", "xn--4gbwdl.xn--wgbh1c", "hostname."],
+            &[
+                "-a-host-name-that-starts-with--",
+                "not_a_valid_host_name",
+                "ab--cd.com",
+                ".",
+                "a.b..c",
+            ],
+        );
+        check("idn-hostname", &["실례.테스트"], &["〮실례.-테스트"]);
+        check(
+            "ipv4",
+            &["192.168.0.1", "0.0.0.0"],
+            &["127.0.0.0.1", "256.256.256.256", "087.10.0.1", "1৪.0.0.1"],
+        );
+        check(
+            "ipv6",
+            &["::1", "::", "1:2:3:4:5:6:7:8", "::ffff:192.168.0.1", "1::8"],
+            &[
+                "12345::",
+                "1:1:1:1:1:1:1:1:1",
+                "::laptop",
+                "1::2::3",
+                ":1:2",
+            ],
+        );
+    }
+
+    #[test]
+    fn identifiers() {
+        check(
+            "uri",
+            &[
+                "http://foo.bar/?baz=qux#quux",
+                "http://[2001:db8::7]/c=GB?objectClass?one",
+                "mailto:John.Doe@example.com",
+                "urn:oasis:names:specification:docbook:dtd:xml:4.1.2",
+            ],
+            &[
+                "//foo.bar/?baz=qux#quux",
+                "\\\\WINDOWS\\fileshare",
+                "abc",
+                "http:// shouldfail.com",
+                ":// should fail",
+                "http://-.~_!$&'()*+,;=:%40:80%2f::::::@example.com/%zz",
+            ],
+        );
+        check(
+            "uri-reference",
+            &[
+                "http://foo.bar/?baz=qux#quux",
+                "//foo.bar/",
+                "/abc",
+                "#frag",
+                "abc",
+            ],
+            &["\\\\WINDOWS\\fileshare", "#frag\\ment"],
+        );
+        check("iri", &["http://ƒøø.ßår/?∂éœ=πîx#πîüx"], &["/abc"]);
+        check("iri-reference", &["//ƒøø.ßår/"], &["\\\\WINDOWS\\filëßåré"]);
+        check(
+            "uri-template",
+            &[
+                "http://example.com/dictionary/{term:1}/{term}",
+                "dictionary/{term}",
+            ],
+            &["http://example.com/dictionary/{term:1}/{term"],
+        );
+        check(
+            "uuid",
+            &["2EB8AA08-AA98-11EA-B4AA-73B441D16380"],
+            &[
+                "2eb8aa08-aa98-11ea-b4aa-73b441d1638",
+                "2eb8aa08aa9811eab4aa73b441d16380",
+            ],
+        );
+        check(
+            "json-pointer",
+            &["", "/foo/bar~0/baz~1/%a", "/"],
+            &["/foo/bar~", "#/foo", "a"],
+        );
+        check(
+            "relative-json-pointer",
+            &["1", "0/foo/bar", "2/0/baz/1/zip", "0#"],
+            &["/foo/bar", "-1/foo/bar", "01/a", "0##", ""],
+        );
+        check(
+            "regex",
+            &[
+                "([abc])+\\s+$",
+                "^(?:a|b){2,3}?$",
+                "[\\b\\-]",
+                "(?<name>x)\\k<name>",
+            ],
+            &["^(abc]", "\\a", "a**", "{", "(?<=x", "a{3,1}"],
+        );
+        check("unknown-format", &["anything"], &[]);
+    }
+}
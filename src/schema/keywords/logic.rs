@@ -1,18 +1,23 @@
+use std::fmt::Display;
+
 use crate::{
     json::{Json, Key},
-    schema::{Annotation, AnnotationValue, JsonSchema, JsonSchemaValidator},
+    schema::{
+        describe_location, Annotation, AnnotationValue, Describe, JsonSchema, JsonSchemaValidator,
+        LocationPart, ValidationContext,
+    },
 };
 
 #[derive(Debug, Clone)]
 pub struct LogicError<'schema> {
     pub key: Key,
-    pub schema: JsonSchema<'schema>,
+    pub schema: &'schema LogicApplier,
     pub kind: LogicErrorKind<'schema>,
 }
 
-impl<'schema> Into<Annotation<'schema>> for LogicError<'schema> {
-    fn into(self) -> Annotation<'schema> {
-        Annotation::LogicError(self)
+impl<'schema> From<LogicError<'schema>> for Annotation<'schema> {
+    fn from(error: LogicError<'schema>) -> Self {
+        Annotation::LogicError(error)
     }
 }
 
@@ -22,129 +27,196 @@ impl<'schema> AnnotationValue for LogicError<'schema> {
     }
 }
 
+impl Describe for LogicError<'_> {
+    fn describe(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl Display for LogicError<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", describe_location(&self.key))?;
+        self.describe(f)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum LogicErrorKind<'schema> {
-    AllOfMissing(&'schema Vec<&'schema JsonSchema<'schema>>),
-    AnyOfMissing(&'schema Vec<&'schema JsonSchema<'schema>>),
-    OneOfMissing(&'schema Vec<&'schema JsonSchema<'schema>>),
-    OneOfMoreThanOne(&'schema Vec<&'schema JsonSchema<'schema>>),
-    NotIs(&'schema JsonSchema<'schema>),
+    AllOfMissing(&'schema [JsonSchema]),
+    AnyOfMissing(&'schema [JsonSchema]),
+    OneOfMissing(&'schema [JsonSchema]),
+    OneOfMoreThanOne(&'schema [JsonSchema]),
+    NotIs(&'schema JsonSchema),
+    /// Valid against `if`, but not against `then`
+    ThenMissing(&'schema JsonSchema),
+    /// Not valid against `if`, nor against `else`
+    ElseMissing(&'schema JsonSchema),
+}
+
+impl Display for LogicErrorKind<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let subschemas = |count: usize| {
+            if count == 1 {
+                "the subschema".to_string()
+            } else {
+                format!("the {} subschemas", count)
+            }
+        };
+
+        match self {
+            LogicErrorKind::AllOfMissing(schemas) => {
+                write!(
+                    f,
+                    "not valid against all of {} in allOf",
+                    subschemas(schemas.len())
+                )
+            }
+            LogicErrorKind::AnyOfMissing(schemas) => {
+                write!(
+                    f,
+                    "not valid against any of {} in anyOf",
+                    subschemas(schemas.len())
+                )
+            }
+            LogicErrorKind::OneOfMissing(schemas) => {
+                write!(
+                    f,
+                    "not valid against any of {} in oneOf",
+                    subschemas(schemas.len())
+                )
+            }
+            LogicErrorKind::OneOfMoreThanOne(schemas) => write!(
+                f,
+                "valid against more than one of {} in oneOf",
+                subschemas(schemas.len())
+            ),
+            LogicErrorKind::NotIs(_) => write!(f, "must not be valid against the schema in not"),
+            LogicErrorKind::ThenMissing(_) => write!(
+                f,
+                "valid against the schema in if, but not against the schema in then"
+            ),
+            LogicErrorKind::ElseMissing(_) => write!(
+                f,
+                "not valid against the schema in if, nor against the schema in else"
+            ),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
-pub enum LogicApplier<'schema> {
-    AllOf(Vec<&'schema JsonSchema<'schema>>),
-    AnyOf(Vec<&'schema JsonSchema<'schema>>),
-    OneOf(Vec<&'schema JsonSchema<'schema>>),
-    Not(&'schema JsonSchema<'schema>),
+pub enum LogicApplier {
+    AllOf(Vec<JsonSchema>),
+    AnyOf(Vec<JsonSchema>),
+    OneOf(Vec<JsonSchema>),
+    Not(Box<JsonSchema>),
+    /// `if`, together with `then` and `else`
+    If {
+        condition: Box<JsonSchema>,
+        then: Option<Box<JsonSchema>>,
+        otherwise: Option<Box<JsonSchema>>,
+    },
 }
 
-impl<'schema> Into<JsonSchema<'schema>> for LogicApplier<'schema> {
-    fn into(self) -> JsonSchema<'schema> {
-        JsonSchema::Logic(self)
+impl From<LogicApplier> for JsonSchema {
+    fn from(applier: LogicApplier) -> Self {
+        JsonSchema::Logic(applier)
     }
 }
 
-impl<'me> JsonSchemaValidator for LogicApplier<'me> {
+impl JsonSchemaValidator for LogicApplier {
     fn validate_json<'schema>(
         &'schema self,
         key_to_input: &mut Key,
-        input: &Json,
-        annotations: &mut Vec<Annotation<'schema>>,
+        input: &'schema Json,
+        context: &mut ValidationContext<'schema>,
     ) -> bool {
         let mut success = true;
-        let key = Key::default();
         let schemas = match self {
             LogicApplier::AllOf(schemas)
             | LogicApplier::AnyOf(schemas)
             | LogicApplier::OneOf(schemas) => schemas,
             LogicApplier::Not(schema) => {
-                if schema.validate_json(key_to_input, input, annotations) {
-                    annotations.push(
+                // `not` evaluates nothing, whatever its subschema does
+                let mark = context.evaluated_mark();
+                let valid = schema.validate_json(key_to_input, input, context);
+                context.forget_evaluated(mark);
+                if valid {
+                    context.annotate(|| {
                         LogicError {
-                            schema: self.clone().into(),
-                            key: key.clone(),
+                            schema: self,
+                            key: key_to_input.copy_of(),
                             kind: LogicErrorKind::NotIs(schema),
                         }
-                        .into(),
-                    );
+                        .into()
+                    });
                     success = false;
                 };
                 return success;
             }
+            LogicApplier::If {
+                condition,
+                then,
+                otherwise,
+            } => return self.validate_if(condition, then, otherwise, key_to_input, input, context),
         };
 
-        let total_size = schemas.iter().count();
+        let total_size = schemas.len();
 
         let mut valid = 0;
-        for schema in schemas {
-            if schema.validate_json(key_to_input, input, annotations) {
+        for (index, schema) in schemas.iter().enumerate() {
+            context.push_location(LocationPart::Index(index));
+            let schema_valid = schema.validate_json(key_to_input, input, context);
+            context.pop_location();
+
+            if schema_valid {
                 valid += 1;
             }
-        }
 
-        match self {
-            LogicApplier::AllOf(vec) => {
-                if valid != total_size {
-                    annotations.push(
-                        LogicError {
-                            schema: self.clone().into(),
-                            key: key.copy_of(),
-                            kind: LogicErrorKind::AllOfMissing(vec),
-                        }
-                        .into(),
-                    );
-                    success = false;
-                }
-            }
-            LogicApplier::AnyOf(vec) => {
-                if valid == 0 {
-                    annotations.push(
-                        LogicError {
-                            schema: self.clone().into(),
-                            key: key.copy_of(),
-                            kind: LogicErrorKind::AnyOfMissing(vec),
-                        }
-                        .into(),
-                    );
-                    success = false;
-                }
-            }
-            LogicApplier::OneOf(vec) => {
-                if valid == 0 {
-                    annotations.push(
-                        LogicError {
-                            schema: self.clone().into(),
-                            key: key.copy_of(),
-                            kind: LogicErrorKind::OneOfMissing(vec),
-                        }
-                        .into(),
-                    );
-                    success = false;
-                } else if valid != 1 {
-                    annotations.push(
-                        LogicError {
-                            schema: self.clone().into(),
-                            key: key.copy_of(),
-                            kind: LogicErrorKind::OneOfMoreThanOne(vec),
-                        }
-                        .into(),
-                    );
-                    success = false;
+            // When annotations are not needed, stop as soon as the outcome
+            // can no longer change, unless the subschemas that are valid
+            // are needed for what they evaluate
+            if context.is_fail_fast() {
+                let decided = match self {
+                    LogicApplier::AllOf(_) => !schema_valid,
+                    LogicApplier::AnyOf(_) => schema_valid && !context.is_tracking(),
+                    LogicApplier::OneOf(_) => valid > 1,
+                    LogicApplier::Not(_) | LogicApplier::If { .. } => unreachable!(),
+                };
+
+                if decided {
+                    break;
                 }
             }
-            LogicApplier::Not(_) => unreachable!(),
         }
+
+        let kind = match self {
+            LogicApplier::AllOf(vec) if valid != total_size => LogicErrorKind::AllOfMissing(vec),
+            LogicApplier::AnyOf(vec) if valid == 0 => LogicErrorKind::AnyOfMissing(vec),
+            LogicApplier::OneOf(vec) if valid == 0 => LogicErrorKind::OneOfMissing(vec),
+            LogicApplier::OneOf(vec) if valid != 1 => LogicErrorKind::OneOfMoreThanOne(vec),
+            _ => return success,
+        };
+
+        context.annotate(|| {
+            LogicError {
+                schema: self,
+                key: key_to_input.copy_of(),
+                kind,
+            }
+            .into()
+        });
+        success = false;
+
         success
     }
 }
 
 #[derive(Debug, Clone)]
-pub enum LogicValidationError<'schema> {
-    SchemaArrayEmpty(LogicApplier<'schema>),
+pub enum LogicValidationError {
+    SchemaArrayEmpty(LogicApplier),
 }
 
-impl<'schema> LogicApplier<'schema> {
+impl LogicApplier {
     /// Check if this applier itself is valid
     pub fn is_valid(&self) -> Result<(), LogicValidationError> {
         match self {
@@ -153,24 +225,71 @@ impl<'schema> LogicApplier<'schema> {
                     return Err(LogicValidationError::SchemaArrayEmpty(self.clone()));
                 }
             }
-            LogicApplier::Not(_) => {}
+            LogicApplier::Not(_) | LogicApplier::If { .. } => {}
         }
         Ok(())
     }
+
+    fn validate_if<'schema>(
+        &'schema self,
+        condition: &'schema JsonSchema,
+        then: &'schema Option<Box<JsonSchema>>,
+        otherwise: &'schema Option<Box<JsonSchema>>,
+        key_to_input: &mut Key,
+        input: &'schema Json,
+        context: &mut ValidationContext<'schema>,
+    ) -> bool {
+        // Failing the condition is not an error in itself
+        let checkpoint = context.checkpoint();
+        let matched = condition.validate_json(key_to_input, input, context);
+        if !matched {
+            context.rollback(checkpoint);
+        }
+
+        let (keyword, branch) = match (matched, then, otherwise) {
+            (true, Some(then), _) => ("then", then),
+            (false, _, Some(otherwise)) => ("else", otherwise),
+            _ => return true,
+        };
+
+        // The branches are siblings of `if` in the schema
+        context.pop_location();
+        context.push_location(LocationPart::Keyword(keyword));
+        let valid = branch.validate_json(key_to_input, input, context);
+        context.pop_location();
+        context.push_location(LocationPart::Keyword("if"));
+
+        if !valid {
+            context.annotate(|| {
+                let kind = if matched {
+                    LogicErrorKind::ThenMissing(branch)
+                } else {
+                    LogicErrorKind::ElseMissing(branch)
+                };
+                LogicError {
+                    schema: self,
+                    key: key_to_input.copy_of(),
+                    kind,
+                }
+                .into()
+            });
+        }
+        valid
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::LogicApplier;
     use crate::json::{Json, Key};
-    use crate::schema::JsonSchemaValidator;
+    use crate::schema::{JsonSchemaValidator, Loader, ValidationContext};
 
     macro_rules! assert_pretty_print {
         ($applier: expr, $test: expr, $input: expr) => {
-            let errors = &mut Vec::new();
+            let context = &mut ValidationContext::new();
             let key = &mut Key::default();
             assert!(
-                $applier.validate_json(key, &$input, errors) == $test,
+                $applier.validate_json(key, &$input, context) == $test,
                 "Failed: {:?} = {:?} not {}",
                 $input,
                 $applier,
@@ -185,19 +304,19 @@ mod tests {
             fn $name() {
                 let input: Json = "Test".into();
 
-                let me = &input.clone().into();
-                let not_me = &"Not present".into();
+                let me = || input.clone().into();
+                let not_me = || "Not present".into();
 
-                let applier = $applier(vec![me]);
+                let applier = $applier(vec![me()]);
                 assert_pretty_print!(applier, $self_only, input);
 
-                let applier = $applier(vec![me, not_me]);
+                let applier = $applier(vec![me(), not_me()]);
                 assert_pretty_print!(applier, $self_and_other, input);
 
-                let applier = $applier(vec![me, me]);
+                let applier = $applier(vec![me(), me()]);
                 assert_pretty_print!(applier, $self_twice, input);
 
-                let applier = $applier(vec![not_me]);
+                let applier = $applier(vec![not_me()]);
                 assert_pretty_print!(applier, $only_other, input);
             }
         };
@@ -211,8 +330,8 @@ mod tests {
     fn not() {
         let input: Json = "Test".into();
 
-        let me = &input.clone().into();
-        let not_me = &"Not present".into();
+        let me = Box::new(input.clone().into());
+        let not_me = Box::new("Not present".into());
 
         let applier = LogicApplier::Not(me);
         assert_pretty_print!(applier, false, input);
@@ -220,4 +339,34 @@ mod tests {
         let applier = LogicApplier::Not(not_me);
         assert_pretty_print!(applier, true, input);
     }
+
+    #[test]
+    fn if_then_else() {
+        let schema = Json::from_string(
+            r#"{"if": {"type": "string"}, "then": {"minLength": 2}, "else": {"type": "number"}}"#,
+        )
+        .unwrap();
+        let schema = Loader::new().load(&schema).unwrap();
+
+        for (input, valid) in [
+            (r#""ab""#, true),
+            (r#""a""#, false),
+            ("1", true),
+            ("null", false),
+        ] {
+            let input = Json::from_string(input).unwrap();
+            assert_eq!(schema.validate(&input).success, valid, "{}", input);
+        }
+
+        // Failing the condition is not reported
+        let result = schema.validate(&Json::Null);
+        let errors: Vec<String> = result.errors().map(|error| error.to_string()).collect();
+        assert_eq!(
+            errors,
+            vec![
+                "/: expected number, found null",
+                "/: not valid against the schema in if, nor against the schema in else"
+            ]
+        );
+    }
 }
@@ -0,0 +1,124 @@
+use crate::{
+    json::{Json, Key},
+    schema::{Annotation, JsonSchemaValidator, ValidationContext},
+};
+
+/// Keywords that never fail validation, but annotate the input they are
+/// applied to
+#[derive(Debug, Clone, PartialEq)]
+pub enum Metadata {
+    Title(String),
+    Description(String),
+    Default(Json),
+    Examples(Vec<Json>),
+    Deprecated(bool),
+    ReadOnly(bool),
+    WriteOnly(bool),
+}
+
+impl From<Metadata> for Json {
+    fn from(metadata: Metadata) -> Self {
+        match metadata {
+            Metadata::Title(value) | Metadata::Description(value) => Json::String(value),
+            Metadata::Default(value) => value,
+            Metadata::Examples(values) => Json::Array(values),
+            Metadata::Deprecated(value)
+            | Metadata::ReadOnly(value)
+            | Metadata::WriteOnly(value) => Json::Boolean(value),
+        }
+    }
+}
+
+impl Metadata {
+    /// The keyword that this metadata was read from
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            Metadata::Title(_) => "title",
+            Metadata::Description(_) => "description",
+            Metadata::Default(_) => "default",
+            Metadata::Examples(_) => "examples",
+            Metadata::Deprecated(_) => "deprecated",
+            Metadata::ReadOnly(_) => "readOnly",
+            Metadata::WriteOnly(_) => "writeOnly",
+        }
+    }
+
+    /// Read the metadata keyword `keyword` with value `value`
+    ///
+    /// Returns `None` if `keyword` is not a metadata keyword, and
+    /// `Some(Err(()))` if `value` has the wrong type for `keyword`
+    pub(crate) fn from_keyword(keyword: &str, value: &Json) -> Option<Result<Self, ()>> {
+        let metadata = match (keyword, value) {
+            ("title", Json::String(title)) => Metadata::Title(title.clone()),
+            ("description", Json::String(desc)) => Metadata::Description(desc.clone()),
+            ("default", value) => Metadata::Default(value.clone()),
+            ("examples", Json::Array(examples)) => Metadata::Examples(examples.clone()),
+            ("deprecated", Json::Boolean(value)) => Metadata::Deprecated(*value),
+            ("readOnly", Json::Boolean(value)) => Metadata::ReadOnly(*value),
+            ("writeOnly", Json::Boolean(value)) => Metadata::WriteOnly(*value),
+            ("title" | "description" | "examples" | "deprecated" | "readOnly" | "writeOnly", _) => {
+                return Some(Err(()))
+            }
+            _ => return None,
+        };
+        Some(Ok(metadata))
+    }
+}
+
+impl JsonSchemaValidator for Metadata {
+    fn validate_json<'schema>(
+        &'schema self,
+        key_to_input: &mut Key,
+        _input: &'schema Json,
+        context: &mut ValidationContext<'schema>,
+    ) -> bool {
+        context.annotate(|| Annotation::Metadata {
+            metadata: self,
+            key: key_to_input.copy_of(),
+        });
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Metadata;
+    use crate::{json::Json, schema::Loader};
+
+    #[test]
+    fn collected_by_location() {
+        let schema = Json::from_string(
+            r#"{
+                "title": "root",
+                "properties": {
+                    "a": {
+                        "default": 12,
+                        "deprecated": true,
+                        "anyOf": [
+                            {"type": "string", "description": "dropped"},
+                            {"type": "number", "description": "kept"}
+                        ]
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let schema = Loader::new().load(&schema).unwrap();
+
+        let input = Json::from_string(r#"{"a": 5}"#).unwrap();
+        let result = schema.validate(&input);
+        assert!(result.success);
+
+        let metadata = result.metadata();
+        assert_eq!(metadata[""], vec![&Metadata::Title("root".to_string())]);
+
+        let a = &metadata["/a"];
+        assert_eq!(a.len(), 3);
+        assert!(a.contains(&&Metadata::Description("kept".to_string())));
+        assert!(a.contains(&&Metadata::Deprecated(true)));
+        assert!(!a.contains(&&Metadata::Description("dropped".to_string())));
+
+        let input = Json::from_string(r#"{"a": null}"#).unwrap();
+        assert!(schema.validate(&input).metadata().is_empty());
+    }
+}
@@ -1,14 +1,49 @@
+mod array;
+pub use array::ArrayKeyword;
+
+mod content;
+pub use content::{Content, Encoding, MediaType};
+
+mod custom;
+pub use custom::{Custom, CustomKeyword, KeywordFactory};
+
+mod format;
+pub use format::{Format, FormatChecker, FormatKind};
+
 mod logic;
 pub use logic::{LogicApplier, LogicValidationError};
 
+mod metadata;
+pub use metadata::Metadata;
+
+mod number;
+pub use number::NumberKeyword;
+
+mod object;
+pub use object::ObjectKeyword;
+
 mod property;
 pub use property::Property;
 
+mod reference;
+pub use reference::Reference;
+
+mod string;
+pub use string::StringKeyword;
+
 mod ty;
 pub use ty::Type;
 
 pub mod annotations {
+    pub use super::array::{ArrayError, ArrayErrorKind};
+    pub use super::content::{ContentError, ContentErrorKind};
+    pub use super::custom::{CustomAnnotation, CustomAnnotationKind};
+    pub use super::format::FormatError;
     pub use super::logic::{LogicError, LogicErrorKind};
+    pub use super::number::NumberError;
+    pub use super::object::{ObjectError, ObjectErrorKind};
     pub use super::property::{PropertyError, PropertyErrorKind};
+    pub use super::reference::ReferenceError;
+    pub use super::string::StringError;
     pub use super::ty::{TypeError, TypeErrorKind};
 }
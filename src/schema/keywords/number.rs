@@ -0,0 +1,207 @@
+use std::fmt::Display;
+
+use crate::{
+    json::{Decimal, Json, Key},
+    schema::{
+        describe_location, describe_value, Annotation, Describe, JsonSchema, JsonSchemaValidator,
+        ValidationContext,
+    },
+};
+
+#[derive(Debug, Clone)]
+pub struct NumberError<'schema> {
+    pub key: Key,
+    pub keyword: &'schema NumberKeyword,
+    pub value: &'schema Json,
+}
+
+impl<'schema> From<NumberError<'schema>> for Annotation<'schema> {
+    fn from(error: NumberError<'schema>) -> Self {
+        Annotation::NumberError(error)
+    }
+}
+
+impl Describe for NumberError<'_> {
+    fn describe(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let expected = match self.keyword {
+            NumberKeyword::Minimum(limit) => format!("at least {}", limit),
+            NumberKeyword::Maximum(limit) => format!("at most {}", limit),
+            NumberKeyword::ExclusiveMinimum(limit) => format!("more than {}", limit),
+            NumberKeyword::ExclusiveMaximum(limit) => format!("less than {}", limit),
+            NumberKeyword::MultipleOf(divisor) => format!("a multiple of {}", divisor),
+        };
+        write!(
+            f,
+            "expected {}, found {}",
+            expected,
+            describe_value(self.value)
+        )
+    }
+}
+
+impl Display for NumberError<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", describe_location(&self.key))?;
+        self.describe(f)
+    }
+}
+
+/// Keywords that only apply to numbers
+///
+/// Each holds its number as it is written in the schema, in decimal, and
+/// compares it with the input exactly.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NumberKeyword {
+    Minimum(String),
+    Maximum(String),
+    ExclusiveMinimum(String),
+    ExclusiveMaximum(String),
+    MultipleOf(String),
+}
+
+impl NumberKeyword {
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            NumberKeyword::Minimum(_) => "minimum",
+            NumberKeyword::Maximum(_) => "maximum",
+            NumberKeyword::ExclusiveMinimum(_) => "exclusiveMinimum",
+            NumberKeyword::ExclusiveMaximum(_) => "exclusiveMaximum",
+            NumberKeyword::MultipleOf(_) => "multipleOf",
+        }
+    }
+
+    fn number(&self) -> &str {
+        match self {
+            NumberKeyword::Minimum(number)
+            | NumberKeyword::Maximum(number)
+            | NumberKeyword::ExclusiveMinimum(number)
+            | NumberKeyword::ExclusiveMaximum(number)
+            | NumberKeyword::MultipleOf(number) => number,
+        }
+    }
+
+    fn accepts(&self, value: Decimal) -> bool {
+        let number = match Decimal::parse(self.number()) {
+            Some(number) => number,
+            None => return true,
+        };
+        match self {
+            NumberKeyword::Minimum(_) => value >= number,
+            NumberKeyword::Maximum(_) => value <= number,
+            NumberKeyword::ExclusiveMinimum(_) => value > number,
+            NumberKeyword::ExclusiveMaximum(_) => value < number,
+            NumberKeyword::MultipleOf(_) => value.is_multiple_of(&number),
+        }
+    }
+
+    /// Whether this accepts `Infinity`, `-Infinity` or `NaN`, which have no
+    /// decimal value. They are compared as floats, and are not multiples
+    /// of anything.
+    fn accepts_float(&self, value: f64) -> bool {
+        let number = match self.number().parse::<f64>() {
+            Ok(number) => number,
+            Err(_) => return true,
+        };
+        match self {
+            NumberKeyword::Minimum(_) => value >= number,
+            NumberKeyword::Maximum(_) => value <= number,
+            NumberKeyword::ExclusiveMinimum(_) => value > number,
+            NumberKeyword::ExclusiveMaximum(_) => value < number,
+            NumberKeyword::MultipleOf(_) => false,
+        }
+    }
+}
+
+impl From<NumberKeyword> for JsonSchema {
+    fn from(keyword: NumberKeyword) -> Self {
+        JsonSchema::Number(keyword)
+    }
+}
+
+impl JsonSchemaValidator for NumberKeyword {
+    fn validate_json<'schema>(
+        &'schema self,
+        key_to_input: &mut Key,
+        input: &'schema Json,
+        context: &mut ValidationContext<'schema>,
+    ) -> bool {
+        let accepted = match (input.as_decimal(), input.as_f64()) {
+            (Some(value), _) => self.accepts(value),
+            (None, Some(value)) => self.accepts_float(value),
+            (None, None) => true,
+        };
+        if accepted {
+            return true;
+        }
+
+        context.annotate(|| {
+            NumberError {
+                key: key_to_input.copy_of(),
+                keyword: self,
+                value: input,
+            }
+            .into()
+        });
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NumberKeyword;
+    use crate::json::Decimal;
+
+    fn accepts(keyword: NumberKeyword, value: &str) -> bool {
+        keyword.accepts(Decimal::parse(value).unwrap())
+    }
+
+    #[test]
+    fn limits() {
+        assert!(accepts(NumberKeyword::Minimum("1".into()), "1.0"));
+        assert!(!accepts(NumberKeyword::ExclusiveMinimum("1".into()), "1"));
+        assert!(accepts(NumberKeyword::Maximum("1".into()), "-1.5"));
+        assert!(!accepts(NumberKeyword::ExclusiveMaximum("1".into()), "1e0"));
+
+        // Integers past the precision of a float are compared exactly
+        let limit = || "9007199254740992".to_string();
+        assert!(!accepts(
+            NumberKeyword::Maximum(limit()),
+            "9007199254740993"
+        ));
+        assert!(accepts(NumberKeyword::Maximum(limit()), "9007199254740992"));
+        assert!(!accepts(
+            NumberKeyword::ExclusiveMaximum(limit()),
+            "9007199254740992.0"
+        ));
+        assert!(accepts(NumberKeyword::Minimum(limit()), "9007199254740993"));
+        assert!(!accepts(
+            NumberKeyword::Minimum(limit()),
+            "9007199254740991"
+        ));
+        assert!(accepts(
+            NumberKeyword::ExclusiveMinimum(limit()),
+            "9007199254740993"
+        ));
+    }
+
+    #[test]
+    fn multiple_of() {
+        let multiple_of =
+            |divisor: &str, value| accepts(NumberKeyword::MultipleOf(divisor.into()), value);
+        assert!(multiple_of("2", "10"));
+        assert!(!multiple_of("2", "7"));
+        assert!(multiple_of("0.0001", "0.0075"));
+        assert!(!multiple_of("0.0001", "0.00751"));
+        assert!(multiple_of("0.01", "19.99"));
+        assert!(!multiple_of("0.123456789", "1e308"));
+
+        // Past 2^53, and where the quotient is too large for a float to
+        // tell whether it is an integer
+        assert!(!multiple_of("2", "9007199254740993"));
+        assert!(multiple_of("2", "9007199254740994"));
+        assert!(!multiple_of("3", "36028797018963971"));
+        assert!(!multiple_of("0.3", "1e300"));
+        assert!(multiple_of("7", "123456789012345678901234567890"));
+        assert!(!multiple_of("7", "123456789012345678901234567891"));
+    }
+}
@@ -0,0 +1,423 @@
+use std::{collections::HashSet, fmt::Display};
+
+use crate::{
+    json::{Json, Key, KeyPart},
+    schema::{
+        describe_location, regex::Regex, Annotation, Describe, Evaluated, JsonSchema,
+        JsonSchemaValidator, LocationPart, ValidationContext,
+    },
+};
+
+#[derive(Debug, Clone)]
+pub enum ObjectErrorKind {
+    /// The value of the property was not valid against
+    /// `additionalProperties`, `patternProperties` or
+    /// `unevaluatedProperties`
+    InvalidProperty {
+        name: String,
+    },
+    /// The name of the property was not valid against `propertyNames`
+    InvalidName {
+        name: String,
+    },
+    TooFew {
+        count: usize,
+    },
+    TooMany {
+        count: usize,
+    },
+    /// `dependency` is required because `property` is present
+    MissingDependency {
+        property: String,
+        dependency: String,
+    },
+    /// The object was not valid against the schema that applies because
+    /// `property` is present
+    InvalidDependent {
+        property: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct ObjectError<'schema> {
+    pub key: Key,
+    pub keyword: &'schema ObjectKeyword,
+    pub kind: ObjectErrorKind,
+}
+
+impl<'schema> From<ObjectError<'schema>> for Annotation<'schema> {
+    fn from(error: ObjectError<'schema>) -> Self {
+        Annotation::ObjectError(error)
+    }
+}
+
+impl Describe for ObjectError<'_> {
+    fn describe(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            ObjectErrorKind::InvalidProperty { name } => {
+                write!(f, "value of property \"{}\" is invalid", name)
+            }
+            ObjectErrorKind::InvalidName { name } => {
+                write!(f, "property name \"{}\" is invalid", name)
+            }
+            ObjectErrorKind::TooFew { count } => match self.keyword {
+                ObjectKeyword::MinProperties(min) => {
+                    write!(f, "expected at least {} properties, found {}", min, count)
+                }
+                _ => write!(f, "too few properties"),
+            },
+            ObjectErrorKind::TooMany { count } => match self.keyword {
+                ObjectKeyword::MaxProperties(max) => {
+                    write!(f, "expected at most {} properties, found {}", max, count)
+                }
+                _ => write!(f, "too many properties"),
+            },
+            ObjectErrorKind::MissingDependency {
+                property,
+                dependency,
+            } => write!(
+                f,
+                "property \"{}\" is required when \"{}\" is present",
+                dependency, property
+            ),
+            ObjectErrorKind::InvalidDependent { property } => write!(
+                f,
+                "not valid against the schema that applies when \"{}\" is present",
+                property
+            ),
+        }
+    }
+}
+
+impl Display for ObjectError<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", describe_location(&self.key))?;
+        self.describe(f)
+    }
+}
+
+/// Keywords that only apply to objects, except for `properties` and
+/// `required`
+#[derive(Debug, Clone)]
+pub enum ObjectKeyword {
+    /// `additionalProperties`, which applies to the properties that are
+    /// neither named in `properties` nor matched by `patternProperties`
+    AdditionalProperties {
+        properties: Vec<String>,
+        patterns: Vec<Regex>,
+        schema: Box<JsonSchema>,
+    },
+    PatternProperties(Vec<(Regex, JsonSchema)>),
+    PropertyNames(Box<JsonSchema>),
+    MinProperties(usize),
+    MaxProperties(usize),
+    DependentRequired(Vec<(String, Vec<String>)>),
+    DependentSchemas(Vec<(String, JsonSchema)>),
+    /// `unevaluatedProperties`, which applies to the properties that no
+    /// other keyword of the schema, or of the subschemas that the input is
+    /// valid against, evaluated
+    UnevaluatedProperties(Box<JsonSchema>),
+}
+
+impl ObjectKeyword {
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            ObjectKeyword::AdditionalProperties { .. } => "additionalProperties",
+            ObjectKeyword::PatternProperties(_) => "patternProperties",
+            ObjectKeyword::PropertyNames(_) => "propertyNames",
+            ObjectKeyword::MinProperties(_) => "minProperties",
+            ObjectKeyword::MaxProperties(_) => "maxProperties",
+            ObjectKeyword::DependentRequired(_) => "dependentRequired",
+            ObjectKeyword::DependentSchemas(_) => "dependentSchemas",
+            ObjectKeyword::UnevaluatedProperties(_) => "unevaluatedProperties",
+        }
+    }
+
+    fn error<'schema>(
+        &'schema self,
+        key: &Key,
+        kind: ObjectErrorKind,
+        context: &mut ValidationContext<'schema>,
+    ) {
+        context.annotate(|| {
+            ObjectError {
+                key: key.copy_of(),
+                keyword: self,
+                kind,
+            }
+            .into()
+        });
+    }
+
+    /// Validate the properties `names` of `object` against `schema`, noting
+    /// that they were evaluated
+    fn validate_properties<'schema>(
+        &'schema self,
+        schema: &'schema JsonSchema,
+        key_to_input: &Key,
+        (input, names): (&'schema Json, Vec<&'schema String>),
+        context: &mut ValidationContext<'schema>,
+    ) -> bool {
+        let object = match input {
+            Json::Object(object) => object,
+            _ => return true,
+        };
+
+        let mut success = true;
+        for name in names {
+            let property = (name, &object[name]);
+            if !self.validate_property(schema, key_to_input, property, context) {
+                success = false;
+                if context.is_fail_fast() {
+                    break;
+                }
+            }
+            context.evaluate(input, Evaluated::Property(name));
+        }
+        success
+    }
+
+    /// Validate the value of the property `name` against `schema`
+    fn validate_property<'schema>(
+        &'schema self,
+        schema: &'schema JsonSchema,
+        key_to_input: &Key,
+        (name, value): (&String, &'schema Json),
+        context: &mut ValidationContext<'schema>,
+    ) -> bool {
+        let property_key = &mut key_to_input.copy_of();
+        property_key.push(KeyPart::Identifier(name.clone()));
+
+        let valid = schema.validate_json(property_key, value, context);
+        if !valid {
+            let name = name.clone();
+            self.error(
+                property_key,
+                ObjectErrorKind::InvalidProperty { name },
+                context,
+            );
+        }
+        valid
+    }
+}
+
+impl From<ObjectKeyword> for JsonSchema {
+    fn from(keyword: ObjectKeyword) -> Self {
+        JsonSchema::Object(keyword)
+    }
+}
+
+impl JsonSchemaValidator for ObjectKeyword {
+    fn validate_json<'schema>(
+        &'schema self,
+        key_to_input: &mut Key,
+        input: &'schema Json,
+        context: &mut ValidationContext<'schema>,
+    ) -> bool {
+        let object = match input {
+            Json::Object(object) => object,
+            _ => return true,
+        };
+
+        // Properties are evaluated in a fixed order so that errors are too
+        let mut names: Vec<&String> = object.keys().collect();
+        names.sort();
+
+        let mut success = true;
+        match self {
+            ObjectKeyword::AdditionalProperties {
+                properties,
+                patterns,
+                schema,
+            } => {
+                let additional = names.into_iter().filter(|name| {
+                    !properties.contains(name) && !patterns.iter().any(|re| re.is_match(name))
+                });
+                let properties = (input, additional.collect());
+                success = self.validate_properties(schema, key_to_input, properties, context);
+            }
+            ObjectKeyword::PatternProperties(patterns) => {
+                for (pattern, schema) in patterns {
+                    let matching = names.iter().filter(|name| pattern.is_match(name));
+                    let properties = (input, matching.copied().collect());
+
+                    context.push_location(LocationPart::Keyword(pattern.as_str()));
+                    let valid = self.validate_properties(schema, key_to_input, properties, context);
+                    context.pop_location();
+
+                    if !valid {
+                        success = false;
+                        if context.is_fail_fast() {
+                            break;
+                        }
+                    }
+                }
+            }
+            ObjectKeyword::PropertyNames(schema) => {
+                // Names are not part of the input, so they are validated
+                // separately, and only the outcome is recorded
+                for name in names {
+                    let name_json = Json::String(name.clone());
+                    let mut names_context = context.detached();
+                    if !schema.validate_json(&mut Key::default(), &name_json, &mut names_context) {
+                        success = false;
+                        let name = name.clone();
+                        self.error(key_to_input, ObjectErrorKind::InvalidName { name }, context);
+                        if context.is_fail_fast() {
+                            break;
+                        }
+                    }
+                }
+            }
+            ObjectKeyword::MinProperties(min) => {
+                if object.name_count() < *min {
+                    success = false;
+                    let count = object.name_count();
+                    self.error(key_to_input, ObjectErrorKind::TooFew { count }, context);
+                }
+            }
+            ObjectKeyword::MaxProperties(max) => {
+                if object.name_count() > *max {
+                    success = false;
+                    let count = object.name_count();
+                    self.error(key_to_input, ObjectErrorKind::TooMany { count }, context);
+                }
+            }
+            ObjectKeyword::DependentRequired(dependencies) => {
+                for (property, required) in dependencies {
+                    if !object.contains_key(property) {
+                        continue;
+                    }
+
+                    for dependency in required {
+                        if !object.contains_key(dependency) {
+                            success = false;
+                            let kind = ObjectErrorKind::MissingDependency {
+                                property: property.clone(),
+                                dependency: dependency.clone(),
+                            };
+                            self.error(key_to_input, kind, context);
+                        }
+                    }
+                }
+            }
+            ObjectKeyword::DependentSchemas(dependents) => {
+                for (property, schema) in dependents {
+                    if !object.contains_key(property) {
+                        continue;
+                    }
+
+                    context.push_location(LocationPart::Keyword(property));
+                    let valid = schema.validate_json(key_to_input, input, context);
+                    context.pop_location();
+
+                    if !valid {
+                        success = false;
+                        let property = property.clone();
+                        let kind = ObjectErrorKind::InvalidDependent { property };
+                        self.error(key_to_input, kind, context);
+                        if context.is_fail_fast() {
+                            break;
+                        }
+                    }
+                }
+            }
+            ObjectKeyword::UnevaluatedProperties(schema) => {
+                let evaluated: HashSet<&str> = context
+                    .evaluated(input)
+                    .filter_map(|evaluated| match evaluated {
+                        Evaluated::Property(name) => Some(name),
+                        _ => None,
+                    })
+                    .collect();
+                let unevaluated = names
+                    .into_iter()
+                    .filter(|name| !evaluated.contains(name.as_str()));
+                let properties = (input, unevaluated.collect());
+                success = self.validate_properties(schema, key_to_input, properties, context);
+            }
+        }
+        success
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        json::{DuplicateKeys, Json, ParserOptions},
+        schema::Loader,
+    };
+
+    fn errors(schema: &str, input: &str) -> Vec<String> {
+        let schema = Loader::new()
+            .load(&Json::from_string(schema).unwrap())
+            .unwrap();
+        let input = Json::from_string(input).unwrap();
+        let result = schema.validate(&input);
+        assert_eq!(result.success, result.errors().next().is_none());
+        result.errors().map(|error| error.to_string()).collect()
+    }
+
+    #[test]
+    fn additional_properties() {
+        let schema = r#"{
+            "properties": {"a": true},
+            "patternProperties": {"^x-": {"type": "string"}},
+            "additionalProperties": false
+        }"#;
+        assert!(errors(schema, r#"{"a": 1, "x-b": "c"}"#).is_empty());
+
+        let errors = errors(schema, r#"{"b": 1, "x-c": 2}"#);
+        assert!(errors.contains(&r#"/b: value of property "b" is invalid"#.to_string()));
+        assert!(errors.contains(&r#"/x-c: value of property "x-c" is invalid"#.to_string()));
+    }
+
+    #[test]
+    fn property_names() {
+        let schema = r#"{"propertyNames": {"maxLength": 3}, "minProperties": 1}"#;
+        assert!(errors(schema, r#"{"abc": 1}"#).is_empty());
+        assert_eq!(
+            errors(schema, r#"{"abcd": 1}"#),
+            vec![r#"/: property name "abcd" is invalid"#]
+        );
+        assert_eq!(
+            errors(schema, "{}"),
+            vec!["/: expected at least 1 properties, found 0"]
+        );
+    }
+
+    #[test]
+    fn duplicate_names() {
+        let schema = Loader::new()
+            .load(&Json::from_string(r#"{"minProperties": 2, "maxProperties": 1}"#).unwrap())
+            .unwrap();
+        let options = ParserOptions {
+            duplicate_keys: DuplicateKeys::KeepAll,
+            ..Default::default()
+        };
+        let input = Json::from_string_with_options(r#"{"a": 1, "a": 2}"#, options).unwrap();
+
+        // A name counts once, however many members have it
+        let errors: Vec<String> = schema
+            .validate(&input)
+            .errors()
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(errors, vec!["/: expected at least 2 properties, found 1"]);
+    }
+
+    #[test]
+    fn dependencies() {
+        let schema = r#"{
+            "dependentRequired": {"a": ["b"]},
+            "dependentSchemas": {"c": {"required": ["d"]}}
+        }"#;
+        assert!(errors(schema, r#"{"a": 1, "b": 2, "c": 3, "d": 4}"#).is_empty());
+        assert!(errors(schema, r#"{"b": 2}"#).is_empty());
+        assert!(errors(schema, r#"{"a": 1}"#)
+            .contains(&r#"/: property "b" is required when "a" is present"#.to_string()));
+        assert!(errors(schema, r#"{"c": 1}"#).contains(
+            &r#"/: not valid against the schema that applies when "c" is present"#.to_string()
+        ));
+    }
+}
@@ -1,6 +1,11 @@
+use std::fmt::Display;
+
 use crate::{
     json::{Json, Key, KeyPart},
-    schema::{Annotation, AnnotationValue, JsonSchema, JsonSchemaValidator},
+    schema::{
+        describe_location, Annotation, AnnotationValue, Describe, Evaluated, JsonSchema,
+        JsonSchemaValidator, LocationPart, ValidationContext,
+    },
 };
 
 #[derive(Debug, Clone)]
@@ -10,9 +15,24 @@ pub enum PropertyErrorKind {
     Invalid,
 }
 
+impl Display for PropertyErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PropertyErrorKind::IncorrectType => write!(f, "expected object"),
+            PropertyErrorKind::Missing { required: true } => {
+                write!(f, "required property is missing")
+            }
+            PropertyErrorKind::Missing { required: false } => {
+                write!(f, "optional property is missing")
+            }
+            PropertyErrorKind::Invalid => write!(f, "property is invalid"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PropertyError<'schema> {
-    pub schema: JsonSchema<'schema>,
+    pub property: &'schema Property,
     pub key: Key,
     pub kind: PropertyErrorKind,
 }
@@ -26,93 +46,145 @@ impl<'schema> AnnotationValue for PropertyError<'schema> {
     }
 }
 
-impl<'schema> Into<Annotation<'schema>> for PropertyError<'schema> {
-    fn into(self) -> Annotation<'schema> {
-        Annotation::PropertyError(self)
+impl Describe for PropertyError<'_> {
+    fn describe(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = self.property.name();
+        match self.kind {
+            PropertyErrorKind::IncorrectType => write!(f, "{}", self.kind),
+            PropertyErrorKind::Missing { required: true } => {
+                write!(f, "required property \"{}\" is missing", name)
+            }
+            PropertyErrorKind::Missing { required: false } => {
+                write!(f, "optional property \"{}\" is missing", name)
+            }
+            PropertyErrorKind::Invalid => write!(f, "value of property \"{}\" is invalid", name),
+        }
+    }
+}
+
+impl Display for PropertyError<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", describe_location(&self.key))?;
+        self.describe(f)
+    }
+}
+
+impl<'schema> From<PropertyError<'schema>> for Annotation<'schema> {
+    fn from(error: PropertyError<'schema>) -> Self {
+        Annotation::PropertyError(error)
     }
 }
 
 #[derive(Debug, Clone)]
-pub struct Property<'schema> {
+pub struct Property {
     required: bool,
     name: String,
-    schema: &'schema JsonSchema<'schema>,
+    schema: Box<JsonSchema>,
 }
 
-impl<'schema> Into<JsonSchema<'schema>> for Property<'schema> {
-    fn into(self) -> JsonSchema<'schema> {
-        JsonSchema::Properties(vec![self])
+impl From<Property> for JsonSchema {
+    fn from(property: Property) -> Self {
+        JsonSchema::Properties(vec![property])
     }
 }
 
-impl<'me> JsonSchemaValidator for Property<'me> {
+impl JsonSchemaValidator for Property {
     fn validate_json<'schema>(
         &'schema self,
         key_to_input: &mut Key,
-        input: &Json,
-        annotations: &mut Vec<Annotation<'schema>>,
+        input: &'schema Json,
+        context: &mut ValidationContext<'schema>,
     ) -> bool {
         let object = match input {
             Json::Object(obj) => obj,
             _ => {
-                annotations.push(
+                context.annotate(|| {
                     PropertyError {
-                        schema: self.clone().into(),
+                        property: self,
                         key: key_to_input.copy_of(),
                         kind: PropertyErrorKind::IncorrectType,
                     }
-                    .into(),
-                );
+                    .into()
+                });
                 return false;
             }
         };
 
-        if let Some((object_key, object_value)) = object.iter().find(|(key, _)| key == &&self.name)
-        {
+        if let Some((object_key, object_value)) = object.get_key_value(self.name.as_str()) {
+            context.evaluate(input, Evaluated::Property(object_key));
+            context.push_location(LocationPart::Keyword(&self.name));
+
+            // The path to the input is only needed for annotations
+            if context.is_fail_fast() {
+                let success = self
+                    .schema
+                    .validate_json(key_to_input, object_value, context);
+                context.pop_location();
+                return success;
+            }
+
             let input_key = &mut key_to_input.copy_of();
             input_key.push(KeyPart::Identifier(object_key.clone()));
 
-            let success = self
-                .schema
-                .validate_json(input_key, object_value, annotations);
+            let success = self.schema.validate_json(input_key, object_value, context);
+            context.pop_location();
+
             if !success {
-                annotations.push(
+                context.annotate(|| {
                     PropertyError {
-                        schema: self.schema.clone(),
+                        property: self,
                         key: input_key.clone(),
                         kind: PropertyErrorKind::Invalid,
                     }
-                    .into(),
-                );
+                    .into()
+                });
                 false
             } else {
                 true
             }
         } else {
-            annotations.push(
+            let error = || {
                 PropertyError {
-                    schema: self.clone().into(),
+                    property: self,
                     key: key_to_input.copy_of(),
                     kind: PropertyErrorKind::Missing {
                         required: self.required,
                     },
                 }
-                .into(),
-            );
+                .into()
+            };
+
+            if self.required {
+                context.annotate_for_sibling("required", key_to_input, error);
+            } else {
+                context.annotate(error);
+            }
             !self.required
         }
     }
 }
 
-impl<'schema> Property<'schema> {
-    pub fn new(name: &str, schema: &'schema JsonSchema<'schema>, required: bool) -> Self {
+impl Property {
+    pub fn new(name: &str, schema: JsonSchema, required: bool) -> Self {
         Self {
             name: name.to_string(),
-            schema,
+            schema: Box::new(schema),
             required,
         }
     }
 
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn schema(&self) -> &JsonSchema {
+        &self.schema
+    }
+
+    pub fn required(&self) -> bool {
+        self.required
+    }
+
     pub fn set_required(&mut self, required: bool) {
         self.required = required;
     }
@@ -122,7 +194,7 @@ impl<'schema> Property<'schema> {
 mod tests {
     use crate::{
         json::{Json, Key},
-        schema::{keywords::Type, JsonSchema, JsonSchemaValidator},
+        schema::{keywords::Type, JsonSchemaValidator, ValidationContext},
     };
 
     use super::Property;
@@ -131,23 +203,17 @@ mod tests {
     fn required() {
         let input = &Json::from_string(r#"{"x": "value"}"#).unwrap();
 
-        let ty = JsonSchema::Type(Type::String);
-
-        let mut schema = Property {
-            required: false,
-            name: "x".to_string(),
-            schema: &ty,
-        };
+        let mut schema = Property::new("x", Type::String.into(), false);
 
         macro_rules! test {
             ($name: expr, $required: expr, $success: expr, $empty: expr) => {
-                let annotations = &mut Vec::new();
+                let mut context = ValidationContext::new();
                 schema.name = $name.to_string();
                 schema.required = $required;
                 let key = &mut Key::default();
-                let result = schema.validate_json(key, &input, annotations);
+                let result = schema.validate_json(key, input, &mut context);
                 assert_eq!(result, $success);
-                assert_eq!(annotations.is_empty(), $empty);
+                assert_eq!(context.into_annotations().is_empty(), $empty);
             };
         }
 
@@ -161,19 +227,13 @@ mod tests {
     fn incorrect_type() {
         let input = &Json::from_string(r#"["x", "value"]"#).unwrap();
 
-        let ty = JsonSchema::Type(Type::String);
-
-        let schema = Property {
-            required: false,
-            name: "x".to_string(),
-            schema: &ty,
-        };
+        let schema = Property::new("x", Type::String.into(), false);
 
-        let annotations = &mut Vec::new();
+        let mut context = ValidationContext::new();
         let key = &mut Key::default();
-        let result = schema.validate_json(key, input, annotations);
+        let result = schema.validate_json(key, input, &mut context);
 
         assert!(!result);
-        assert!(!annotations.is_empty());
+        assert!(!context.into_annotations().is_empty());
     }
 }
@@ -0,0 +1,203 @@
+use std::fmt::Display;
+
+use crate::{
+    json::{Json, Key},
+    schema::{
+        describe_location, Annotation, Describe, JsonSchema, JsonSchemaValidator, ValidationContext,
+    },
+};
+
+#[derive(Debug, Clone)]
+pub struct ReferenceError<'schema> {
+    pub key: Key,
+    pub reference: &'schema Reference,
+}
+
+impl<'schema> From<ReferenceError<'schema>> for Annotation<'schema> {
+    fn from(error: ReferenceError<'schema>) -> Self {
+        Annotation::ReferenceError(error)
+    }
+}
+
+impl Describe for ReferenceError<'_> {
+    fn describe(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "reference to {} could not be resolved",
+            self.reference.uri
+        )
+    }
+}
+
+impl Display for ReferenceError<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", describe_location(&self.key))?;
+        self.describe(f)
+    }
+}
+
+/// `$ref` or `$dynamicRef`, which apply the schema at an absolute URI
+///
+/// References are resolved when they are evaluated, against the schemas
+/// that were compiled along with the schema they are part of.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    uri: String,
+    dynamic: bool,
+}
+
+impl Reference {
+    pub fn new(uri: &str, dynamic: bool) -> Self {
+        Self {
+            uri: uri.to_string(),
+            dynamic,
+        }
+    }
+
+    /// The absolute URI that this reference resolves to, before taking
+    /// the dynamic scope into account
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    pub fn is_dynamic(&self) -> bool {
+        self.dynamic
+    }
+
+    pub fn keyword(&self) -> &'static str {
+        if self.dynamic {
+            "$dynamicRef"
+        } else {
+            "$ref"
+        }
+    }
+}
+
+impl From<Reference> for JsonSchema {
+    fn from(reference: Reference) -> Self {
+        JsonSchema::Reference(reference)
+    }
+}
+
+impl JsonSchemaValidator for Reference {
+    fn validate_json<'schema>(
+        &'schema self,
+        key_to_input: &mut Key,
+        input: &'schema Json,
+        context: &mut ValidationContext<'schema>,
+    ) -> bool {
+        let target = match context.resolve(&self.uri, self.dynamic) {
+            Some(target) => target,
+            None => {
+                context.annotate(|| {
+                    ReferenceError {
+                        key: key_to_input.copy_of(),
+                        reference: self,
+                    }
+                    .into()
+                });
+                return false;
+            }
+        };
+
+        context.enter_resource(&target.base_uri, &target.pointer);
+        let success = target.schema.validate_json(key_to_input, input, context);
+        context.exit_resource();
+        success
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        json::Json,
+        schema::{Loader, OutputFormat},
+    };
+
+    fn load(schema: &str) -> crate::schema::JsonSchema {
+        Loader::new()
+            .load(&Json::from_string(schema).unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn local() {
+        let schema = load(
+            r##"{
+                "$defs": {"positive": {"type": "integer", "exclusiveMinimum": 0}},
+                "properties": {"a": {"$ref": "#/$defs/positive"}}
+            }"##,
+        );
+        assert!(schema.is_valid(&Json::from_string(r#"{"a": 1}"#).unwrap()));
+        assert!(!schema.is_valid(&Json::from_string(r#"{"a": 0}"#).unwrap()));
+    }
+
+    #[test]
+    fn recursive() {
+        let schema = load(
+            r##"{
+                "$id": "https://example.com/tree",
+                "properties": {
+                    "value": {"type": "number"},
+                    "children": {"items": {"$ref": "#"}}
+                }
+            }"##,
+        );
+        let input = r#"{"value": 1, "children": [{"value": 2, "children": [{"value": "x"}]}]}"#;
+        let input = Json::from_string(input).unwrap();
+        assert!(!schema.is_valid(&input));
+
+        let output = schema.output(&input, OutputFormat::Basic).to_string();
+        assert!(output.contains(
+            r#""absoluteKeywordLocation":"https://example.com/tree#/properties/value/type""#
+        ));
+        assert!(output.contains(r#""instanceLocation":"/children/0/children/0/value""#));
+    }
+
+    #[test]
+    fn anchors() {
+        let schema = load(
+            r##"{
+                "$id": "https://example.com/root",
+                "$ref": "item#name",
+                "$defs": {
+                    "item": {"$id": "item", "$defs": {"name": {"$anchor": "name", "type": "string"}}}
+                }
+            }"##,
+        );
+        assert!(schema.is_valid(&"x".into()));
+        assert!(!schema.is_valid(&Json::Null));
+    }
+
+    #[test]
+    fn dynamic() {
+        let schema = load(
+            r##"{
+                "$id": "https://example.com/strict-tree",
+                "$dynamicAnchor": "node",
+                "$ref": "tree",
+                "$defs": {
+                    "tree": {
+                        "$id": "tree",
+                        "$dynamicAnchor": "node",
+                        "type": "object",
+                        "properties": {
+                            "children": {"type": "array", "items": {"$dynamicRef": "#node"}}
+                        }
+                    }
+                },
+                "properties": {"children": true},
+                "propertyNames": {"enum": ["children", "data"]}
+            }"##,
+        );
+        assert!(schema.is_valid(&Json::from_string(r#"{"children": [{"data": 1}]}"#).unwrap()));
+        assert!(!schema.is_valid(&Json::from_string(r#"{"children": [{"daat": 1}]}"#).unwrap()));
+    }
+
+    #[test]
+    fn bundled_metaschema() {
+        let schema = load(r#"{"$ref": "https://json-schema.org/draft/2020-12/schema"}"#);
+        assert!(schema.is_valid(&Json::from_string(r#"{"type": "string"}"#).unwrap()));
+        assert!(!schema.is_valid(&Json::from_string(r#"{"type": "strnig"}"#).unwrap()));
+    }
+}
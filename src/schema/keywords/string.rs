@@ -0,0 +1,128 @@
+use std::fmt::Display;
+
+use crate::{
+    json::{Json, Key},
+    schema::{
+        describe_location, describe_value, regex::Regex, Annotation, Describe, JsonSchema,
+        JsonSchemaValidator, ValidationContext,
+    },
+};
+
+#[derive(Debug, Clone)]
+pub struct StringError<'schema> {
+    pub key: Key,
+    pub keyword: &'schema StringKeyword,
+    pub value: &'schema Json,
+}
+
+impl<'schema> From<StringError<'schema>> for Annotation<'schema> {
+    fn from(error: StringError<'schema>) -> Self {
+        Annotation::StringError(error)
+    }
+}
+
+impl Describe for StringError<'_> {
+    fn describe(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = describe_value(self.value);
+        match self.keyword {
+            StringKeyword::MinLength(min) => {
+                write!(f, "expected at least {} characters, found {}", min, value)
+            }
+            StringKeyword::MaxLength(max) => {
+                write!(f, "expected at most {} characters, found {}", max, value)
+            }
+            StringKeyword::Pattern(pattern) => {
+                write!(f, "expected to match {}, found {}", pattern, value)
+            }
+        }
+    }
+}
+
+impl Display for StringError<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", describe_location(&self.key))?;
+        self.describe(f)
+    }
+}
+
+/// Keywords that only apply to strings, except for `format`
+///
+/// Lengths are counted in Unicode code points.
+#[derive(Debug, Clone)]
+pub enum StringKeyword {
+    MinLength(usize),
+    MaxLength(usize),
+    Pattern(Regex),
+}
+
+impl StringKeyword {
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            StringKeyword::MinLength(_) => "minLength",
+            StringKeyword::MaxLength(_) => "maxLength",
+            StringKeyword::Pattern(_) => "pattern",
+        }
+    }
+
+    fn accepts(&self, value: &str) -> bool {
+        match self {
+            StringKeyword::MinLength(min) => value.chars().count() >= *min,
+            StringKeyword::MaxLength(max) => value.chars().count() <= *max,
+            StringKeyword::Pattern(pattern) => pattern.is_match(value),
+        }
+    }
+}
+
+impl From<StringKeyword> for JsonSchema {
+    fn from(keyword: StringKeyword) -> Self {
+        JsonSchema::String(keyword)
+    }
+}
+
+impl JsonSchemaValidator for StringKeyword {
+    fn validate_json<'schema>(
+        &'schema self,
+        key_to_input: &mut Key,
+        input: &'schema Json,
+        context: &mut ValidationContext<'schema>,
+    ) -> bool {
+        match input {
+            Json::String(value) if !self.accepts(value) => {
+                context.annotate(|| {
+                    StringError {
+                        key: key_to_input.copy_of(),
+                        keyword: self,
+                        value: input,
+                    }
+                    .into()
+                });
+                false
+            }
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StringKeyword;
+    use crate::schema::regex::Regex;
+
+    #[test]
+    fn lengths() {
+        assert!(StringKeyword::MinLength(2).accepts("ab"));
+        assert!(!StringKeyword::MinLength(2).accepts("\u{1F600}"));
+        assert!(StringKeyword::MaxLength(1).accepts("\u{1F600}"));
+        assert!(!StringKeyword::MaxLength(1).accepts("ab"));
+    }
+
+    #[test]
+    fn pattern() {
+        let pattern = StringKeyword::Pattern(Regex::new("^a+$").unwrap());
+        assert!(pattern.accepts("aaa"));
+        assert!(!pattern.accepts("aab"));
+
+        let pattern = StringKeyword::Pattern(Regex::new("b").unwrap());
+        assert!(pattern.accepts("abc"));
+    }
+}
@@ -1,28 +1,69 @@
+use std::fmt::Display;
+
 use crate::{
     json::{Json, Key},
-    schema::{Annotation, JsonSchemaValidator},
+    schema::{
+        describe_location, describe_value, Annotation, Describe, JsonSchema, JsonSchemaValidator,
+        ValidationContext,
+    },
 };
 
 #[derive(Debug, Clone)]
 pub enum TypeErrorKind {
-    TypeMismatch { expected: Type },
+    /// The input is none of the types that `type` allows
+    TypeMismatch { expected: Vec<Type> },
+    /// The input is a number, but `type` only allows integers
     NotInteger,
 }
 
+impl Display for TypeErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TypeErrorKind::TypeMismatch { expected } => {
+                write!(f, "expected ")?;
+                for (index, ty) in expected.iter().enumerate() {
+                    match index {
+                        0 => {}
+                        _ if index == expected.len() - 1 => write!(f, " or ")?,
+                        _ => write!(f, ", ")?,
+                    }
+                    write!(f, "{}", ty)?;
+                }
+                Ok(())
+            }
+            TypeErrorKind::NotInteger => write!(f, "expected integer"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
-pub struct TypeError {
+pub struct TypeError<'schema> {
     pub key: Key,
     pub error: TypeErrorKind,
     pub actual: Type,
+    pub value: &'schema Json,
+}
+
+impl<'schema> From<TypeError<'schema>> for Annotation<'schema> {
+    fn from(error: TypeError<'schema>) -> Self {
+        Annotation::TypeError(error)
+    }
+}
+
+impl Describe for TypeError<'_> {
+    fn describe(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}, found {}", self.error, describe_value(self.value))
+    }
 }
 
-impl<'schema> Into<Annotation<'schema>> for TypeError {
-    fn into(self) -> Annotation<'schema> {
-        Annotation::TypeError(self)
+impl Display for TypeError<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", describe_location(&self.key))?;
+        self.describe(f)
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Type {
     String,
     Number,
@@ -33,12 +74,61 @@ pub enum Type {
     Null,
 }
 
+impl Type {
+    /// The name used for this type in the `type` keyword
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::String => "string",
+            Self::Number => "number",
+            Self::Integer => "integer",
+            Self::Object => "object",
+            Self::Array => "array",
+            Self::Boolean => "boolean",
+            Self::Null => "null",
+        }
+    }
+
+    /// Look up a type by the name used for it in the `type` keyword
+    pub fn from_name(name: &str) -> Option<Self> {
+        let ty = match name {
+            "string" => Self::String,
+            "number" => Self::Number,
+            "integer" => Self::Integer,
+            "object" => Self::Object,
+            "array" => Self::Array,
+            "boolean" => Self::Boolean,
+            "null" => Self::Null,
+            _ => return None,
+        };
+        Some(ty)
+    }
+
+    /// Whether `input` is of this type
+    ///
+    /// Every integer is a number, and numbers with a fractional part of
+    /// zero, like `1.0`, are integers.
+    pub fn matches(&self, input: &Json) -> bool {
+        match (self, input) {
+            (Self::Integer, Json::Number(_)) => {
+                input.as_f64().is_some_and(|value| value.fract() == 0.0)
+            }
+            _ => *self == Type::from(input),
+        }
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
 impl From<&Json> for Type {
     fn from(input: &Json) -> Self {
         match input {
             Json::Object(_) => Self::Object,
             Json::Array(_) => Self::Array,
-            Json::Number { .. } => Self::Number,
+            Json::Number(_) => Self::Number,
             Json::String(_) => Self::String,
             Json::Boolean(_) => Self::Boolean,
             Json::Null => Self::Null,
@@ -46,39 +136,94 @@ impl From<&Json> for Type {
     }
 }
 
-impl JsonSchemaValidator for Type {
+impl From<Type> for JsonSchema {
+    fn from(ty: Type) -> Self {
+        JsonSchema::Type(vec![ty])
+    }
+}
+
+/// The `type` keyword, which allows any of the types in the set
+impl JsonSchemaValidator for Vec<Type> {
     fn validate_json<'schema>(
         &'schema self,
         key_to_input: &mut Key,
-        input: &Json,
-        annotations: &mut Vec<Annotation<'schema>>,
+        input: &'schema Json,
+        context: &mut ValidationContext<'schema>,
     ) -> bool {
-        let error_kind = if let (Type::Integer, Json::Number { fraction, .. }) = (self, input) {
-            if fraction.1 == 0 {
-                None
-            } else {
-                Some(TypeErrorKind::NotInteger)
-            }
-        } else if self != &input.into() {
-            Some(TypeErrorKind::TypeMismatch {
-                expected: self.clone(),
-            })
+        if self.iter().any(|ty| ty.matches(input)) {
+            return true;
+        }
+
+        let error = if matches!(input, Json::Number(_)) && self.contains(&Type::Integer) {
+            TypeErrorKind::NotInteger
         } else {
-            None
+            TypeErrorKind::TypeMismatch {
+                expected: self.clone(),
+            }
         };
 
-        if let Some(type_error) = error_kind {
-            annotations.push(
-                TypeError {
-                    key: key_to_input.copy_of(),
-                    error: type_error,
-                    actual: input.into(),
-                }
-                .into(),
-            );
-            false
-        } else {
-            true
-        }
+        context.annotate(|| {
+            TypeError {
+                key: key_to_input.copy_of(),
+                error,
+                actual: input.into(),
+                value: input,
+            }
+            .into()
+        });
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{json::Json, schema::Loader};
+
+    fn errors(schema: &str, input: &str) -> Vec<String> {
+        let schema = Loader::new()
+            .load(&Json::from_string(schema).unwrap())
+            .unwrap();
+        let input = Json::from_string(input).unwrap();
+        let result = schema.validate(&input);
+        assert_eq!(result.success, result.errors().next().is_none());
+        result.errors().map(|error| error.to_string()).collect()
+    }
+
+    #[test]
+    fn types() {
+        assert!(errors(r#"{"type": "number"}"#, "3").is_empty());
+        assert!(errors(r#"{"type": ["number"]}"#, "3").is_empty());
+        assert!(errors(r#"{"type": "integer"}"#, "3.0").is_empty());
+        assert!(errors(r#"{"type": "integer"}"#, "1.5e1").is_empty());
+        assert_eq!(
+            errors(r#"{"type": "integer"}"#, "1.5"),
+            vec!["/: expected integer, found number 1.5"]
+        );
+        assert_eq!(
+            errors(r#"{"type": "string"}"#, "null"),
+            vec!["/: expected string, found null"]
+        );
+    }
+
+    #[test]
+    fn type_sets() {
+        let schema = r#"{"type": ["string", "null"]}"#;
+        assert!(errors(schema, r#""a""#).is_empty());
+        assert!(errors(schema, "null").is_empty());
+        assert_eq!(
+            errors(schema, "1"),
+            vec!["/: expected string or null, found number 1"]
+        );
+
+        let schema = r#"{"type": ["array", "object", "integer"]}"#;
+        assert!(errors(schema, "2").is_empty());
+        assert_eq!(
+            errors(schema, "2.5"),
+            vec!["/: expected integer, found number 2.5"]
+        );
+        assert_eq!(
+            errors(schema, "true"),
+            vec!["/: expected array, object or integer, found boolean true"]
+        );
     }
 }
@@ -0,0 +1,1927 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    sync::{Arc, OnceLock},
+};
+
+use crate::json::{Json, Key, KeyPart, Map};
+
+use super::{
+    describe_location,
+    keywords::{
+        annotations::{
+            ArrayError, ArrayErrorKind, LogicError, LogicErrorKind, ObjectError, ObjectErrorKind,
+            PropertyError, PropertyErrorKind,
+        },
+        ArrayKeyword, Content, Custom, Encoding, Format, FormatChecker, KeywordFactory,
+        LogicApplier, MediaType, Metadata, NumberKeyword, ObjectKeyword, Property, Reference,
+        StringKeyword, Type,
+    },
+    registry::{Registry, Target},
+    uri, Annotation, Draft, JsonSchema, Regex, Vocabulary,
+};
+
+/// The metaschema documents that are bundled with the crate, by `$id`
+const BUNDLED: &[(&str, &str)] = &[
+    (
+        "http://json-schema.org/draft-04/schema",
+        include_str!("metaschemas/draft4/schema.json"),
+    ),
+    (
+        "http://json-schema.org/draft-06/schema",
+        include_str!("metaschemas/draft6/schema.json"),
+    ),
+    (
+        "http://json-schema.org/draft-07/schema",
+        include_str!("metaschemas/draft7/schema.json"),
+    ),
+    (
+        "https://json-schema.org/draft/2019-09/schema",
+        include_str!("metaschemas/draft2019-09/schema.json"),
+    ),
+    (
+        "https://json-schema.org/draft/2019-09/meta/core",
+        include_str!("metaschemas/draft2019-09/meta/core.json"),
+    ),
+    (
+        "https://json-schema.org/draft/2019-09/meta/applicator",
+        include_str!("metaschemas/draft2019-09/meta/applicator.json"),
+    ),
+    (
+        "https://json-schema.org/draft/2019-09/meta/validation",
+        include_str!("metaschemas/draft2019-09/meta/validation.json"),
+    ),
+    (
+        "https://json-schema.org/draft/2019-09/meta/meta-data",
+        include_str!("metaschemas/draft2019-09/meta/meta-data.json"),
+    ),
+    (
+        "https://json-schema.org/draft/2019-09/meta/format",
+        include_str!("metaschemas/draft2019-09/meta/format.json"),
+    ),
+    (
+        "https://json-schema.org/draft/2019-09/meta/content",
+        include_str!("metaschemas/draft2019-09/meta/content.json"),
+    ),
+    (
+        "https://json-schema.org/draft/2020-12/schema",
+        include_str!("metaschemas/draft2020-12/schema.json"),
+    ),
+    (
+        "https://json-schema.org/draft/2020-12/meta/core",
+        include_str!("metaschemas/draft2020-12/meta/core.json"),
+    ),
+    (
+        "https://json-schema.org/draft/2020-12/meta/applicator",
+        include_str!("metaschemas/draft2020-12/meta/applicator.json"),
+    ),
+    (
+        "https://json-schema.org/draft/2020-12/meta/unevaluated",
+        include_str!("metaschemas/draft2020-12/meta/unevaluated.json"),
+    ),
+    (
+        "https://json-schema.org/draft/2020-12/meta/validation",
+        include_str!("metaschemas/draft2020-12/meta/validation.json"),
+    ),
+    (
+        "https://json-schema.org/draft/2020-12/meta/meta-data",
+        include_str!("metaschemas/draft2020-12/meta/meta-data.json"),
+    ),
+    (
+        "https://json-schema.org/draft/2020-12/meta/format-annotation",
+        include_str!("metaschemas/draft2020-12/meta/format-annotation.json"),
+    ),
+    (
+        "https://json-schema.org/draft/2020-12/meta/format-assertion",
+        include_str!("metaschemas/draft2020-12/meta/format-assertion.json"),
+    ),
+    (
+        "https://json-schema.org/draft/2020-12/meta/content",
+        include_str!("metaschemas/draft2020-12/meta/content.json"),
+    ),
+];
+
+/// The bundled document with the `$id` `uri`
+fn bundled(uri: &str) -> Option<&'static Json> {
+    static DOCUMENTS: OnceLock<HashMap<&'static str, Json>> = OnceLock::new();
+
+    DOCUMENTS
+        .get_or_init(|| {
+            BUNDLED
+                .iter()
+                .map(|(uri, source)| {
+                    let document =
+                        Json::from_string(source).expect("Bundled metaschemas are valid JSON");
+                    (*uri, document)
+                })
+                .collect()
+        })
+        .get(uri)
+}
+
+/// The compiled form of the bundled metaschema with the `$id` `uri`
+fn metaschema(uri: &str) -> Option<&'static JsonSchema> {
+    static COMPILED: OnceLock<HashMap<&'static str, JsonSchema>> = OnceLock::new();
+
+    COMPILED
+        .get_or_init(|| {
+            let loader = Loader::new().validate_schemas(false);
+            BUNDLED
+                .iter()
+                .map(|(uri, _)| {
+                    let document = bundled(uri).expect("Every bundled metaschema is parsed");
+                    let schema = loader
+                        .load(document)
+                        .expect("Bundled metaschemas can be compiled");
+                    (*uri, schema)
+                })
+                .collect()
+        })
+        .get(uri)
+}
+
+/// A problem with a schema document, found by validating it against its
+/// metaschema
+#[derive(Debug, Clone)]
+pub struct SchemaError {
+    /// The location of the problem in the schema document
+    pub key: Key,
+    pub message: String,
+}
+
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", describe_location(&self.key), self.message)
+    }
+}
+
+/// Whether `error` only reports that a subschema failed, which the errors
+/// of that subschema describe better
+fn summarizes(error: &Annotation) -> bool {
+    matches!(
+        error,
+        Annotation::LogicError(LogicError {
+            kind: LogicErrorKind::AllOfMissing(_),
+            ..
+        }) | Annotation::PropertyError(PropertyError {
+            kind: PropertyErrorKind::Invalid,
+            ..
+        }) | Annotation::ArrayError(ArrayError {
+            kind: ArrayErrorKind::InvalidItem,
+            ..
+        }) | Annotation::ObjectError(ObjectError {
+            kind: ObjectErrorKind::InvalidProperty { .. },
+            ..
+        })
+    )
+}
+
+/// The draft and vocabularies that a schema document is written against
+#[derive(Debug, Clone)]
+struct Dialect {
+    draft: Draft,
+    vocabularies: Vec<Vocabulary>,
+}
+
+impl Dialect {
+    /// The dialect of the metaschema of `draft`
+    fn of(draft: Draft) -> Self {
+        Self {
+            draft,
+            vocabularies: Vocabulary::defaults(),
+        }
+    }
+
+    /// Whether `keyword` should be compiled
+    fn enables(&self, keyword: &str) -> bool {
+        if !self.draft.supports(keyword) {
+            return false;
+        }
+
+        match Vocabulary::of(keyword) {
+            // There were no vocabularies before 2019-09
+            _ if self.draft < Draft::Draft201909 => true,
+            Some(Vocabulary::Core) | None => true,
+            Some(Vocabulary::FormatAnnotation) => {
+                self.vocabularies.contains(&Vocabulary::FormatAnnotation) || self.asserts_formats()
+            }
+            Some(vocabulary) => self.vocabularies.contains(&vocabulary),
+        }
+    }
+
+    fn asserts_formats(&self) -> bool {
+        self.vocabularies.contains(&Vocabulary::FormatAssertion)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum LoadError {
+    /// A schema was neither an object nor a boolean
+    InvalidSchema(Key),
+    /// A keyword had a value of the wrong shape
+    InvalidKeywordValue(Key),
+    /// `type` named a type that does not exist
+    UnknownType(Key),
+    /// `allOf`, `anyOf` or `oneOf` contained no subschemas
+    EmptySchemaArray(Key),
+    /// `format` named a format that is neither defined by the
+    /// specification nor registered with the loader
+    UnknownFormat(Key),
+    /// `pattern` or `patternProperties` contained a regular expression
+    /// that could not be parsed
+    InvalidRegex(Key),
+    /// `$ref` or `$dynamicRef` referred to a schema that could not be found
+    UnresolvedReference(Key),
+    /// `$schema` named a metaschema that the loader does not know
+    UnknownMetaschema(Key),
+    /// The metaschema required a vocabulary that the loader does not
+    /// know, at this location in the metaschema
+    UnknownVocabulary(Key),
+    /// The schema was not valid against its metaschema
+    Metaschema(Vec<SchemaError>),
+}
+
+impl LoadError {
+    /// The location of the problem, which is that of the first problem the
+    /// metaschema found, if it found any
+    pub fn key(&self) -> Option<&Key> {
+        match self {
+            Self::InvalidSchema(key)
+            | Self::InvalidKeywordValue(key)
+            | Self::UnknownType(key)
+            | Self::EmptySchemaArray(key)
+            | Self::UnknownFormat(key)
+            | Self::InvalidRegex(key)
+            | Self::UnresolvedReference(key)
+            | Self::UnknownMetaschema(key)
+            | Self::UnknownVocabulary(key) => Some(key),
+            Self::Metaschema(errors) => errors.first().map(|error| &error.key),
+        }
+    }
+}
+
+/// Writes the location of the problem as a JSON Pointer, followed by what
+/// the problem is. Each problem the metaschema found is on its own line.
+impl Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            Self::InvalidSchema(_) => "expected a schema, which is an object or a boolean",
+            Self::InvalidKeywordValue(_) => "invalid value for this keyword",
+            Self::UnknownType(_) => "unknown type",
+            Self::EmptySchemaArray(_) => "expected at least one subschema",
+            Self::UnknownFormat(_) => "unknown format",
+            Self::InvalidRegex(_) => "invalid regular expression",
+            Self::UnresolvedReference(_) => "reference to a schema that could not be found",
+            Self::UnknownMetaschema(_) => "unknown metaschema",
+            Self::UnknownVocabulary(_) => "unknown required vocabulary",
+            Self::Metaschema(errors) if errors.is_empty() => {
+                return write!(f, "schema is not valid against its metaschema")
+            }
+            Self::Metaschema(errors) => {
+                for (index, error) in errors.iter().enumerate() {
+                    if index > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{}", error)?;
+                }
+                return Ok(());
+            }
+        };
+        let key = self.key().expect("every other error has a location");
+        write!(f, "{}: {}", describe_location(key), message)
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+/// Compiles schema documents into [`JsonSchema`]s
+///
+/// Keywords that the loader does not know about are ignored.
+#[derive(Clone)]
+pub struct Loader {
+    default_draft: Draft,
+    validate_schemas: bool,
+    assert_formats: bool,
+    reject_unknown_formats: bool,
+    assert_content: bool,
+    formats: HashMap<String, Arc<dyn FormatChecker>>,
+    keywords: HashMap<String, Arc<dyn KeywordFactory>>,
+    /// Documents that references and `$schema` can refer to, by URI
+    documents: HashMap<String, Json>,
+}
+
+impl Default for Loader {
+    fn default() -> Self {
+        Self {
+            default_draft: Draft::default(),
+            validate_schemas: true,
+            assert_formats: false,
+            reject_unknown_formats: false,
+            assert_content: false,
+            formats: HashMap::new(),
+            keywords: HashMap::new(),
+            documents: HashMap::new(),
+        }
+    }
+}
+
+impl std::fmt::Debug for Loader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut formats: Vec<&String> = self.formats.keys().collect();
+        formats.sort();
+        let mut keywords: Vec<&String> = self.keywords.keys().collect();
+        keywords.sort();
+        let mut documents: Vec<&String> = self.documents.keys().collect();
+        documents.sort();
+
+        f.debug_struct("Loader")
+            .field("default_draft", &self.default_draft)
+            .field("validate_schemas", &self.validate_schemas)
+            .field("assert_formats", &self.assert_formats)
+            .field("reject_unknown_formats", &self.reject_unknown_formats)
+            .field("assert_content", &self.assert_content)
+            .field("formats", &formats)
+            .field("keywords", &keywords)
+            .field("documents", &documents)
+            .finish()
+    }
+}
+
+impl Loader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The draft of schema documents that do not name one in `$schema`,
+    /// which is 2020-12 by default
+    pub fn default_draft(mut self, draft: Draft) -> Self {
+        self.default_draft = draft;
+        self
+    }
+
+    /// Whether schema documents should be validated against their
+    /// metaschema before they are compiled, failing with
+    /// [`LoadError::Metaschema`] if they are not valid
+    ///
+    /// The metaschema is the one that `$schema` names, or the one of the
+    /// default draft if there is no `$schema`. This is on by default.
+    pub fn validate_schemas(mut self, validate: bool) -> Self {
+        self.validate_schemas = validate;
+        self
+    }
+
+    /// Whether `format` should fail validation of strings that are not
+    /// valid against the format, as described by the format-assertion
+    /// vocabulary
+    ///
+    /// By default formats are only annotations.
+    pub fn assert_formats(mut self, assert: bool) -> Self {
+        self.assert_formats = assert;
+        self
+    }
+
+    /// Check strings against `checker` wherever `format` is `name`
+    ///
+    /// This replaces the validator for `name` if it is one of the formats
+    /// defined by the specification.
+    pub fn with_format<C>(mut self, name: &str, checker: C) -> Self
+    where
+        C: FormatChecker + 'static,
+    {
+        self.formats.insert(name.to_string(), Arc::new(checker));
+        self
+    }
+
+    /// Whether loading a schema that uses a format that is not known
+    /// should fail with [`LoadError::UnknownFormat`]
+    pub fn reject_unknown_formats(mut self, reject: bool) -> Self {
+        self.reject_unknown_formats = reject;
+        self
+    }
+
+    /// Whether strings that `contentEncoding`, `contentMediaType` and
+    /// `contentSchema` apply to should fail validation if they cannot be
+    /// decoded, parsed, or are not valid against `contentSchema`
+    ///
+    /// By default content is only annotated.
+    pub fn assert_content(mut self, assert: bool) -> Self {
+        self.assert_content = assert;
+        self
+    }
+
+    /// Compile the keyword `name` with `factory` wherever it appears in a
+    /// schema
+    ///
+    /// Keywords registered this way take precedence over the keywords
+    /// defined by the specification.
+    pub fn with_keyword<F>(mut self, name: &str, factory: F) -> Self
+    where
+        F: KeywordFactory + 'static,
+    {
+        self.keywords.insert(name.to_string(), Arc::new(factory));
+        self
+    }
+
+    /// Make `document` available to `$ref` and `$schema` under `uri`
+    ///
+    /// This is how custom metaschemas are used: a schema whose `$schema` is
+    /// `uri` is validated against `document`, and only uses the
+    /// vocabularies that `document` declares in `$vocabulary`.
+    pub fn with_document(mut self, uri: &str, document: Json) -> Self {
+        self.documents.insert(normalize(uri).to_string(), document);
+        self
+    }
+
+    pub fn load(&self, schema: &Json) -> Result<JsonSchema, LoadError> {
+        if self.validate_schemas {
+            self.validate_schema(schema)?;
+        }
+        Compiler::new(self).compile(schema)
+    }
+
+    /// The URI of the metaschema of `document`
+    fn metaschema_uri<'a>(&self, document: &'a Json) -> &'a str {
+        // Anything but a string is reported by the metaschema
+        match document {
+            Json::Object(object) => match object.get("$schema") {
+                Some(Json::String(uri)) => normalize(uri),
+                _ => self.default_draft.uri(),
+            },
+            _ => self.default_draft.uri(),
+        }
+    }
+
+    /// The dialect that `document` is written against, going by its
+    /// `$schema`
+    ///
+    /// A custom metaschema decides the vocabularies, and the draft of the
+    /// metaschema itself decides the draft.
+    fn dialect_of(&self, document: &Json) -> Result<Dialect, LoadError> {
+        let uri = self.metaschema_uri(document);
+        if let Some(draft) = Draft::from_uri(uri) {
+            return Ok(Dialect::of(draft));
+        }
+
+        let metaschema = match self.documents.get(uri) {
+            Some(Json::Object(metaschema)) => metaschema,
+            _ => return Ok(Dialect::of(self.default_draft)),
+        };
+
+        let draft = match metaschema.get("$schema") {
+            Some(Json::String(uri)) => Draft::from_uri(uri).unwrap_or(self.default_draft),
+            _ => self.default_draft,
+        };
+
+        let declared = match metaschema.get("$vocabulary") {
+            Some(Json::Object(declared)) => declared,
+            _ => return Ok(Dialect::of(draft)),
+        };
+
+        let mut uris: Vec<&String> = declared.keys().collect();
+        uris.sort();
+
+        let mut vocabularies = Vec::new();
+        for uri in uris {
+            match (Vocabulary::from_uri(uri), &declared[uri]) {
+                (Some(vocabulary), _) => vocabularies.push(vocabulary),
+                // Optional vocabularies that are not known are ignored
+                (None, Json::Boolean(false)) => {}
+                (None, _) => {
+                    return Err(LoadError::UnknownVocabulary(Key::new(vec![
+                        KeyPart::Identifier("$vocabulary".to_string()),
+                        KeyPart::Identifier(uri.clone()),
+                    ])))
+                }
+            }
+        }
+        Ok(Dialect {
+            draft,
+            vocabularies,
+        })
+    }
+
+    /// Check `schema` against the metaschema that it names
+    fn validate_schema(&self, schema: &Json) -> Result<(), LoadError> {
+        let uri = self.metaschema_uri(schema);
+
+        let custom;
+        let metaschema = match (metaschema(uri), self.documents.get(uri)) {
+            (Some(metaschema), _) => metaschema,
+            (None, Some(document)) => {
+                custom = self.clone().validate_schemas(false).load(document)?;
+                &custom
+            }
+            (None, None) => {
+                return Err(LoadError::UnknownMetaschema(Key::new(vec![
+                    KeyPart::Identifier("$schema".to_string()),
+                ])))
+            }
+        };
+
+        let result = metaschema.validate(schema);
+        if result.success {
+            return Ok(());
+        }
+
+        let error = |error: &Annotation| SchemaError {
+            key: error.key().copy_of(),
+            message: error.message(),
+        };
+        let mut errors: Vec<SchemaError> = result
+            .errors()
+            .filter(|annotation| !summarizes(annotation))
+            .map(error)
+            .collect();
+        if errors.is_empty() {
+            errors = result.errors().map(error).collect();
+        }
+
+        // Every vocabulary metaschema checks the type of the schema, so the
+        // same error can be found several times
+        errors.sort_by_cached_key(|error| (error.key.to_string(), error.message.clone()));
+        errors.dedup_by(|a, b| a.to_string() == b.to_string());
+        Err(LoadError::Metaschema(errors))
+    }
+}
+
+/// Resolve `reference` against `base_uri`, which is empty if there is no
+/// base URI
+fn join(base_uri: &str, reference: &str) -> String {
+    if base_uri.is_empty() {
+        reference.to_string()
+    } else {
+        uri::resolve(base_uri, reference)
+    }
+}
+
+/// `uri` without an empty fragment, which makes no difference to what it
+/// refers to
+fn normalize(uri: &str) -> &str {
+    uri.strip_suffix('#').unwrap_or(uri)
+}
+
+/// The base URI that the `$id` of `schema` sets, if it has one
+fn id_of(schema: &Json, base_uri: &str, draft: Draft) -> Option<String> {
+    let object = match schema {
+        Json::Object(object) => object,
+        _ => return None,
+    };
+
+    if draft.ref_overrides_siblings() && object.contains_key("$ref") {
+        return None;
+    }
+
+    match object.get(draft.id_keyword()) {
+        Some(Json::String(id)) if !id.starts_with('#') => {
+            Some(normalize(&join(base_uri, id)).to_string())
+        }
+        _ => None,
+    }
+}
+
+/// The anchors that `schema` declares, along with whether they are dynamic
+///
+/// `$recursiveAnchor` declares a dynamic anchor with an empty name.
+fn anchors_of(schema: &Map, draft: Draft) -> Vec<(&str, bool)> {
+    let mut anchors = Vec::new();
+    if draft < Draft::Draft201909 {
+        if let Some(Json::String(id)) = schema.get(draft.id_keyword()) {
+            if let Some(name) = id.strip_prefix('#') {
+                anchors.push((name, false));
+            }
+        }
+        return anchors;
+    }
+
+    if let Some(Json::String(name)) = schema.get("$anchor") {
+        anchors.push((name.as_str(), false));
+    }
+    match draft {
+        Draft::Draft201909 => {
+            if let Some(Json::Boolean(true)) = schema.get("$recursiveAnchor") {
+                anchors.push(("", true));
+            }
+        }
+        _ => {
+            if let Some(Json::String(name)) = schema.get("$dynamicAnchor") {
+                anchors.push((name.as_str(), true));
+            }
+        }
+    }
+    anchors
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let escaped = value
+            .get(idx + 1..idx + 3)
+            .filter(|_| bytes[idx] == b'%')
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                idx += 3;
+            }
+            None => {
+                decoded.push(bytes[idx]);
+                idx += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// The reference tokens of the JSON Pointer in the fragment of a URI
+fn pointer_tokens(fragment: &str) -> Vec<String> {
+    percent_decode(fragment)
+        .split('/')
+        .skip(1)
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect()
+}
+
+fn key_of(tokens: &[String]) -> Key {
+    Key::new(
+        tokens
+            .iter()
+            .map(|token| KeyPart::Identifier(token.clone()))
+            .collect(),
+    )
+}
+
+/// The location of the keyword `name` next to the one at `key`
+fn sibling_key(key: &Key, name: &str) -> Key {
+    let mut sibling = key.copy_of();
+    sibling.pop();
+    sibling.push(KeyPart::Identifier(name.to_string()));
+    sibling
+}
+
+fn load_count(key: &Key, value: &Json) -> Result<usize, LoadError> {
+    match value.as_f64() {
+        Some(count) if count >= 0.0 && count.fract() == 0.0 => Ok(count as usize),
+        _ => Err(LoadError::InvalidKeywordValue(key.copy_of())),
+    }
+}
+
+/// The text of a number that is written in decimal, so that it can be
+/// compared exactly
+fn load_number(key: &Key, value: &Json) -> Result<String, LoadError> {
+    match value {
+        Json::Number(number) if value.as_decimal().is_some() => Ok(number.clone()),
+        _ => Err(LoadError::InvalidKeywordValue(key.copy_of())),
+    }
+}
+
+fn load_string<'a>(key: &Key, value: &'a Json) -> Result<&'a str, LoadError> {
+    match value {
+        Json::String(value) => Ok(value),
+        _ => Err(LoadError::InvalidKeywordValue(key.copy_of())),
+    }
+}
+
+fn load_strings(key: &Key, value: &Json) -> Result<Vec<String>, LoadError> {
+    match value {
+        Json::Array(values) => values
+            .iter()
+            .map(|value| load_string(key, value).map(str::to_string))
+            .collect(),
+        _ => Err(LoadError::InvalidKeywordValue(key.copy_of())),
+    }
+}
+
+fn load_regex(key: &Key, pattern: &str) -> Result<Regex, LoadError> {
+    Regex::new(pattern).map_err(|_| LoadError::InvalidRegex(key.copy_of()))
+}
+
+/// Where a schema resource or anchor is, in the documents that are being
+/// compiled
+#[derive(Debug, Clone)]
+struct Location {
+    document: String,
+    pointer: Vec<String>,
+}
+
+/// The state of compiling a single schema document, along with the
+/// documents that it references
+struct Compiler<'a> {
+    loader: &'a Loader,
+    documents: HashMap<String, (&'a Json, Dialect)>,
+    /// The dialect of the document that is being compiled
+    dialect: Dialect,
+    /// Every schema resource and anchor that has been found, by URI
+    locations: HashMap<String, Location>,
+    /// The references whose targets still have to be compiled, along
+    /// with where they were found
+    pending: Vec<(String, Key)>,
+    registry: Registry,
+}
+
+impl<'a> Compiler<'a> {
+    fn new(loader: &'a Loader) -> Self {
+        Self {
+            loader,
+            documents: HashMap::new(),
+            dialect: Dialect::of(loader.default_draft),
+            locations: HashMap::new(),
+            pending: Vec::new(),
+            registry: Registry::default(),
+        }
+    }
+
+    fn compile(mut self, schema: &'a Json) -> Result<JsonSchema, LoadError> {
+        self.dialect = self.loader.dialect_of(schema)?;
+        let base_uri = id_of(schema, "", self.dialect.draft).unwrap_or_default();
+        self.index(&base_uri, schema)?;
+
+        let root = self.load_schema(&mut Key::default(), "", schema)?;
+        while let Some((uri, key)) = self.pending.pop() {
+            if !self.registry.contains(&uri) {
+                let target = self.load_target(&uri, &key)?;
+                self.registry.insert(uri, target);
+            }
+        }
+
+        if self.registry.is_empty() {
+            Ok(root)
+        } else {
+            Ok(JsonSchema::Root {
+                schema: Box::new(root),
+                registry: Box::new(self.registry),
+            })
+        }
+    }
+
+    /// Record where the schema resources and anchors in the document at
+    /// `uri` are
+    fn index(&mut self, uri: &str, document: &'a Json) -> Result<(), LoadError> {
+        let dialect = self.loader.dialect_of(document)?;
+        let draft = dialect.draft;
+        self.documents.insert(uri.to_string(), (document, dialect));
+        self.locations.insert(
+            uri.to_string(),
+            Location {
+                document: uri.to_string(),
+                pointer: Vec::new(),
+            },
+        );
+        self.index_schema((uri, draft), uri, &mut Vec::new(), document);
+        Ok(())
+    }
+
+    fn index_schema(
+        &mut self,
+        (document, draft): (&str, Draft),
+        base_uri: &str,
+        pointer: &mut Vec<String>,
+        schema: &'a Json,
+    ) {
+        let object = match schema {
+            Json::Object(object) => object,
+            _ => return,
+        };
+
+        let location = |pointer: &Vec<String>| Location {
+            document: document.to_string(),
+            pointer: pointer.clone(),
+        };
+
+        let base_uri = match id_of(schema, base_uri, draft) {
+            Some(id) => {
+                self.locations.insert(id.clone(), location(pointer));
+                id
+            }
+            None => base_uri.to_string(),
+        };
+
+        for (name, dynamic) in anchors_of(object, draft) {
+            let uri = format!("{}#{}", base_uri, name);
+            self.locations.insert(uri.clone(), location(pointer));
+
+            // A dynamic reference may resolve to any dynamic anchor, so
+            // they are all compiled
+            if dynamic {
+                self.registry.add_dynamic_anchor(uri.clone());
+                self.pending.push((uri, key_of(pointer)));
+            }
+        }
+
+        for (name, value) in object {
+            pointer.push(name.clone());
+            match (name.as_str(), value) {
+                (
+                    "properties" | "patternProperties" | "dependentSchemas" | "$defs"
+                    | "definitions" | "dependencies",
+                    Json::Object(subschemas),
+                ) => {
+                    for (name, subschema) in subschemas {
+                        pointer.push(name.clone());
+                        self.index_schema((document, draft), &base_uri, pointer, subschema);
+                        pointer.pop();
+                    }
+                }
+                (
+                    "prefixItems" | "items" | "allOf" | "anyOf" | "oneOf",
+                    Json::Array(subschemas),
+                ) => {
+                    for (index, subschema) in subschemas.iter().enumerate() {
+                        pointer.push(index.to_string());
+                        self.index_schema((document, draft), &base_uri, pointer, subschema);
+                        pointer.pop();
+                    }
+                }
+                (
+                    "items"
+                    | "additionalItems"
+                    | "contains"
+                    | "additionalProperties"
+                    | "propertyNames"
+                    | "not"
+                    | "if"
+                    | "then"
+                    | "else"
+                    | "unevaluatedItems"
+                    | "unevaluatedProperties"
+                    | "contentSchema",
+                    subschema,
+                ) => self.index_schema((document, draft), &base_uri, pointer, subschema),
+                _ => {}
+            }
+            pointer.pop();
+        }
+    }
+
+    /// Compile the schema that a reference to `uri`, found at `key`,
+    /// resolves to
+    fn load_target(&mut self, uri: &str, key: &Key) -> Result<Target, LoadError> {
+        let unresolved = || LoadError::UnresolvedReference(key.copy_of());
+
+        let (resource, fragment) = uri.split_once('#').unwrap_or((uri, ""));
+        if !self.locations.contains_key(resource) {
+            let loader = self.loader;
+            if let Some(document) = loader.documents.get(resource).or_else(|| bundled(resource)) {
+                self.index(resource, document)?;
+            }
+        }
+
+        let location = if fragment.is_empty() || fragment.starts_with('/') {
+            let mut location = self
+                .locations
+                .get(resource)
+                .cloned()
+                .ok_or_else(unresolved)?;
+            location.pointer.extend(pointer_tokens(fragment));
+            location
+        } else {
+            self.locations.get(uri).cloned().ok_or_else(unresolved)?
+        };
+
+        // Find the schema, along with the resource that it is part of
+        let (mut schema, dialect) = self
+            .documents
+            .get(&location.document)
+            .cloned()
+            .ok_or_else(unresolved)?;
+        let mut base_uri = location.document.clone();
+        let mut pointer = String::new();
+        for token in &location.pointer {
+            if let Some(id) = id_of(schema, &base_uri, dialect.draft) {
+                base_uri = id;
+                pointer.clear();
+            }
+
+            schema = match schema {
+                Json::Object(object) => object.get(token),
+                Json::Array(array) => token.parse().ok().and_then(|idx: usize| array.get(idx)),
+                _ => None,
+            }
+            .ok_or_else(unresolved)?;
+
+            pointer.push('/');
+            pointer.push_str(&token.replace('~', "~0").replace('/', "~1"));
+        }
+
+        // The target may be in a document of another dialect
+        let dialect = std::mem::replace(&mut self.dialect, dialect);
+        let schema = self.load_schema(&mut key_of(&location.pointer), &base_uri, schema);
+        self.dialect = dialect;
+
+        let schema = schema?;
+        Ok(Target {
+            base_uri,
+            pointer,
+            schema,
+        })
+    }
+
+    /// The absolute URI of `reference`, whose target is compiled later
+    fn reference(&mut self, key: &Key, base_uri: &str, reference: &str) -> String {
+        let uri = normalize(&join(base_uri, reference)).to_string();
+        self.pending.push((uri.clone(), key.copy_of()));
+        uri
+    }
+
+    fn load_schema(
+        &mut self,
+        key: &mut Key,
+        base_uri: &str,
+        schema: &Json,
+    ) -> Result<JsonSchema, LoadError> {
+        let object = match schema {
+            Json::Boolean(accept) => return Ok(JsonSchema::Boolean(*accept)),
+            Json::Object(object) => object,
+            _ => return Err(LoadError::InvalidSchema(key.copy_of())),
+        };
+
+        let id_keyword = self.dialect.draft.id_keyword();
+        let id = match object.get(id_keyword) {
+            Some(Json::String(_)) | None => id_of(schema, base_uri, self.dialect.draft),
+            Some(_) => {
+                key.push(KeyPart::Identifier(id_keyword.to_string()));
+                return Err(LoadError::InvalidKeywordValue(key.copy_of()));
+            }
+        };
+        let base_uri = id.as_deref().unwrap_or(base_uri);
+
+        let mut names: Vec<&String> = match object.get_key_value("$ref") {
+            Some((name, _)) if self.dialect.draft.ref_overrides_siblings() => vec![name],
+            _ => object.keys().collect(),
+        };
+        names.sort();
+
+        let mut keywords = Vec::new();
+        for name in names {
+            key.push(KeyPart::Identifier(name.clone()));
+            let keyword = self.load_keyword(key, base_uri, object, name)?;
+            key.pop();
+
+            keywords.extend(keyword);
+        }
+        // `unevaluatedProperties` and `unevaluatedItems` go last, since they
+        // depend on what the other keywords evaluate
+        keywords.sort_by_key(JsonSchema::has_unevaluated);
+
+        let schema = JsonSchema::Keywords(keywords);
+        if let Some(id) = id {
+            Ok(JsonSchema::Resource {
+                id,
+                schema: Box::new(schema),
+            })
+        } else {
+            Ok(schema)
+        }
+    }
+
+    /// Compile the keyword `name` of the schema `object`, if it is a
+    /// keyword that is known
+    fn load_keyword(
+        &mut self,
+        key: &mut Key,
+        base_uri: &str,
+        object: &Map,
+        name: &str,
+    ) -> Result<Option<JsonSchema>, LoadError> {
+        let value = &object[name];
+        let invalid = |key: &Key| LoadError::InvalidKeywordValue(key.copy_of());
+
+        if let Some(factory) = self.loader.keywords.get(name) {
+            let keyword = factory.compile(value).ok_or_else(|| invalid(key))?;
+            return Ok(Some(JsonSchema::Custom(Custom::new(name, keyword))));
+        }
+
+        if !self.dialect.enables(name) {
+            return Ok(None);
+        }
+
+        if let Some(metadata) = Metadata::from_keyword(name, value) {
+            let metadata = metadata.map_err(|_| invalid(key))?;
+            return Ok(Some(JsonSchema::Metadata(metadata)));
+        }
+
+        let keyword = match name {
+            "$ref" | "$dynamicRef" => {
+                let uri = self.reference(key, base_uri, load_string(key, value)?);
+                Reference::new(&uri, name == "$dynamicRef").into()
+            }
+            // `$recursiveRef` always refers to the root of a resource, and
+            // is resolved like a `$dynamicRef` to a `$recursiveAnchor`
+            "$recursiveRef" if self.dialect.draft == Draft::Draft201909 => {
+                let resource = join(base_uri, load_string(key, value)?);
+                let uri = format!("{}#", normalize(&resource));
+                self.pending.push((uri.clone(), key.copy_of()));
+                Reference::new(&uri, true).into()
+            }
+            "const" => JsonSchema::Primitive(value.clone()),
+            "enum" => match value {
+                Json::Array(values) => JsonSchema::Enum(values.clone()),
+                _ => return Err(invalid(key)),
+            },
+            "type" => Self::load_type(key, value)?,
+            "format" => JsonSchema::Format(self.load_format(key, value)?),
+            // `contentEncoding` and `contentSchema` are folded into
+            // `contentMediaType`, and `contentSchema` only applies along
+            // with it
+            "contentMediaType" => self.load_content(key, base_uri, object)?.into(),
+            "contentEncoding"
+                if !(object.contains_key("contentMediaType")
+                    && self.dialect.enables("contentMediaType")) =>
+            {
+                self.load_content(key, base_uri, object)?.into()
+            }
+            "allOf" | "anyOf" | "oneOf" => {
+                let schemas = self.load_schema_array(key, base_uri, value)?;
+                let applier = match name {
+                    "allOf" => LogicApplier::AllOf(schemas),
+                    "anyOf" => LogicApplier::AnyOf(schemas),
+                    _ => LogicApplier::OneOf(schemas),
+                };
+
+                if applier.is_valid().is_err() {
+                    return Err(LoadError::EmptySchemaArray(key.copy_of()));
+                }
+
+                applier.into()
+            }
+            "not" => {
+                let schema = self.load_schema(key, base_uri, value)?;
+                LogicApplier::Not(Box::new(schema)).into()
+            }
+            // `then` and `else` are folded into `if`, without which they
+            // do nothing
+            "if" => {
+                let condition = Box::new(self.load_schema(key, base_uri, value)?);
+                let then = self.load_sibling(key, base_uri, object, "then")?;
+                let otherwise = self.load_sibling(key, base_uri, object, "else")?;
+                LogicApplier::If {
+                    condition,
+                    then,
+                    otherwise,
+                }
+                .into()
+            }
+            // `required` is folded into the properties keyword
+            "properties" => {
+                let required = object
+                    .get("required")
+                    .filter(|_| self.dialect.enables("required"));
+                self.load_properties(key, base_uri, value, required)?
+            }
+            "required"
+                if !(object.contains_key("properties") && self.dialect.enables("properties")) =>
+            {
+                let properties = &Json::Object(Map::new());
+                self.load_properties(key, base_uri, properties, Some(value))?
+            }
+            "prefixItems" => {
+                ArrayKeyword::PrefixItems(self.load_schema_array(key, base_uri, value)?).into()
+            }
+            // Before 2020-12, `items` took the place of `prefixItems` when
+            // it was an array, and `additionalItems` the place of `items`
+            "items" => match value {
+                Json::Array(_) if self.dialect.draft < Draft::Draft202012 => {
+                    ArrayKeyword::PrefixItems(self.load_schema_array(key, base_uri, value)?).into()
+                }
+                _ => {
+                    let prefix = match object.get("prefixItems") {
+                        Some(Json::Array(prefix)) if self.dialect.enables("prefixItems") => {
+                            prefix.len()
+                        }
+                        _ => 0,
+                    };
+                    let schema = Box::new(self.load_schema(key, base_uri, value)?);
+                    ArrayKeyword::Items { prefix, schema }.into()
+                }
+            },
+            "additionalItems" => match object.get("items") {
+                Some(Json::Array(prefix)) => {
+                    let prefix = prefix.len();
+                    let schema = Box::new(self.load_schema(key, base_uri, value)?);
+                    ArrayKeyword::Items { prefix, schema }.into()
+                }
+                _ => return Ok(None),
+            },
+            // `minContains` and `maxContains` are folded into `contains`
+            "contains" => {
+                let schema = Box::new(self.load_schema(key, base_uri, value)?);
+                let count = |name| {
+                    object
+                        .get(name)
+                        .filter(|_| self.dialect.enables(name))
+                        .map(|value| load_count(&sibling_key(key, name), value))
+                        .transpose()
+                };
+                let min = count("minContains")?.unwrap_or(1);
+                let max = count("maxContains")?;
+                ArrayKeyword::Contains { schema, min, max }.into()
+            }
+            "unevaluatedItems" => {
+                let schema = Box::new(self.load_schema(key, base_uri, value)?);
+                let contains = self.dialect.draft >= Draft::Draft202012;
+                ArrayKeyword::UnevaluatedItems { schema, contains }.into()
+            }
+            "minItems" => ArrayKeyword::MinItems(load_count(key, value)?).into(),
+            "maxItems" => ArrayKeyword::MaxItems(load_count(key, value)?).into(),
+            "uniqueItems" => match value {
+                Json::Boolean(true) => ArrayKeyword::UniqueItems.into(),
+                Json::Boolean(false) => return Ok(None),
+                _ => return Err(invalid(key)),
+            },
+            "additionalProperties" => {
+                let mut properties: Vec<String> = match object.get("properties") {
+                    Some(Json::Object(properties)) => properties.keys().cloned().collect(),
+                    _ => Vec::new(),
+                };
+                properties.sort();
+
+                let patterns = match object.get("patternProperties") {
+                    Some(Json::Object(patterns)) => {
+                        let key = sibling_key(key, "patternProperties");
+                        let mut patterns: Vec<&String> = patterns.keys().collect();
+                        patterns.sort();
+                        patterns
+                            .into_iter()
+                            .map(|pattern| load_regex(&key, pattern))
+                            .collect::<Result<_, _>>()?
+                    }
+                    _ => Vec::new(),
+                };
+
+                let schema = Box::new(self.load_schema(key, base_uri, value)?);
+                ObjectKeyword::AdditionalProperties {
+                    properties,
+                    patterns,
+                    schema,
+                }
+                .into()
+            }
+            "patternProperties" => {
+                let mut patterns = Vec::new();
+                for (pattern, schema) in self.load_schema_map(key, base_uri, value)? {
+                    let mut pattern_key = key.copy_of();
+                    pattern_key.push(KeyPart::Identifier(pattern.clone()));
+                    patterns.push((load_regex(&pattern_key, &pattern)?, schema));
+                }
+                ObjectKeyword::PatternProperties(patterns).into()
+            }
+            "propertyNames" => {
+                let schema = self.load_schema(key, base_uri, value)?;
+                ObjectKeyword::PropertyNames(Box::new(schema)).into()
+            }
+            "minProperties" => ObjectKeyword::MinProperties(load_count(key, value)?).into(),
+            "maxProperties" => ObjectKeyword::MaxProperties(load_count(key, value)?).into(),
+            "dependentRequired" => {
+                let dependencies = match value {
+                    Json::Object(dependencies) => dependencies,
+                    _ => return Err(invalid(key)),
+                };
+
+                let mut names: Vec<&String> = dependencies.keys().collect();
+                names.sort();
+
+                let mut required = Vec::with_capacity(names.len());
+                for name in names {
+                    key.push(KeyPart::Identifier(name.clone()));
+                    required.push((name.clone(), load_strings(key, &dependencies[name])?));
+                    key.pop();
+                }
+                ObjectKeyword::DependentRequired(required).into()
+            }
+            // `dependencies` combines `dependentRequired`, for the
+            // properties that name arrays, and `dependentSchemas`
+            "dependencies" => {
+                let dependencies = match value {
+                    Json::Object(dependencies) => dependencies,
+                    _ => return Err(invalid(key)),
+                };
+
+                let mut names: Vec<&String> = dependencies.keys().collect();
+                names.sort();
+
+                let mut required = Vec::new();
+                let mut schemas = Vec::new();
+                for name in names {
+                    key.push(KeyPart::Identifier(name.clone()));
+                    match &dependencies[name] {
+                        dependency @ Json::Array(_) => {
+                            required.push((name.clone(), load_strings(key, dependency)?))
+                        }
+                        dependency => {
+                            let schema = self.load_schema(key, base_uri, dependency)?;
+                            schemas.push((name.clone(), schema));
+                        }
+                    }
+                    key.pop();
+                }
+
+                JsonSchema::Keywords(vec![
+                    ObjectKeyword::DependentRequired(required).into(),
+                    ObjectKeyword::DependentSchemas(schemas).into(),
+                ])
+            }
+            "dependentSchemas" => {
+                ObjectKeyword::DependentSchemas(self.load_schema_map(key, base_uri, value)?).into()
+            }
+            "unevaluatedProperties" => {
+                let schema = self.load_schema(key, base_uri, value)?;
+                ObjectKeyword::UnevaluatedProperties(Box::new(schema)).into()
+            }
+            // In draft-04, `exclusiveMinimum` and `exclusiveMaximum` are
+            // booleans that make `minimum` and `maximum` exclusive
+            "minimum" | "maximum" if self.dialect.draft == Draft::Draft4 => {
+                let limit = load_number(key, value)?;
+                let exclusive = if name == "minimum" {
+                    "exclusiveMinimum"
+                } else {
+                    "exclusiveMaximum"
+                };
+                let exclusive = match object.get(exclusive) {
+                    Some(Json::Boolean(exclusive)) => *exclusive,
+                    Some(_) => return Err(invalid(&sibling_key(key, exclusive))),
+                    None => false,
+                };
+
+                match (name, exclusive) {
+                    ("minimum", false) => NumberKeyword::Minimum(limit),
+                    ("minimum", true) => NumberKeyword::ExclusiveMinimum(limit),
+                    (_, false) => NumberKeyword::Maximum(limit),
+                    (_, true) => NumberKeyword::ExclusiveMaximum(limit),
+                }
+                .into()
+            }
+            "exclusiveMinimum" | "exclusiveMaximum" if self.dialect.draft == Draft::Draft4 => {
+                return Ok(None)
+            }
+            "minimum" => NumberKeyword::Minimum(load_number(key, value)?).into(),
+            "maximum" => NumberKeyword::Maximum(load_number(key, value)?).into(),
+            "exclusiveMinimum" => NumberKeyword::ExclusiveMinimum(load_number(key, value)?).into(),
+            "exclusiveMaximum" => NumberKeyword::ExclusiveMaximum(load_number(key, value)?).into(),
+            "multipleOf" => match value.as_decimal() {
+                Some(divisor) if !divisor.is_zero() && !divisor.is_negative() => {
+                    NumberKeyword::MultipleOf(load_number(key, value)?).into()
+                }
+                _ => return Err(invalid(key)),
+            },
+            "minLength" => StringKeyword::MinLength(load_count(key, value)?).into(),
+            "maxLength" => StringKeyword::MaxLength(load_count(key, value)?).into(),
+            "pattern" => StringKeyword::Pattern(load_regex(key, load_string(key, value)?)?).into(),
+            _ => return Ok(None),
+        };
+        Ok(Some(keyword))
+    }
+
+    /// Compile the subschema in the keyword `name` next to the one at
+    /// `key`, if there is one
+    fn load_sibling(
+        &mut self,
+        key: &Key,
+        base_uri: &str,
+        object: &Map,
+        name: &str,
+    ) -> Result<Option<Box<JsonSchema>>, LoadError> {
+        match object.get(name) {
+            Some(value) => {
+                let schema = self.load_schema(&mut sibling_key(key, name), base_uri, value)?;
+                Ok(Some(Box::new(schema)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn load_schema_array(
+        &mut self,
+        key: &mut Key,
+        base_uri: &str,
+        value: &Json,
+    ) -> Result<Vec<JsonSchema>, LoadError> {
+        let array = match value {
+            Json::Array(array) => array,
+            _ => return Err(LoadError::InvalidKeywordValue(key.copy_of())),
+        };
+
+        let mut schemas = Vec::with_capacity(array.len());
+        for (index, schema) in array.iter().enumerate() {
+            key.push(KeyPart::Index(index));
+            schemas.push(self.load_schema(key, base_uri, schema)?);
+            key.pop();
+        }
+        Ok(schemas)
+    }
+
+    /// Compile an object whose values are all subschemas, in the order of
+    /// their names
+    fn load_schema_map(
+        &mut self,
+        key: &mut Key,
+        base_uri: &str,
+        value: &Json,
+    ) -> Result<Vec<(String, JsonSchema)>, LoadError> {
+        let object = match value {
+            Json::Object(object) => object,
+            _ => return Err(LoadError::InvalidKeywordValue(key.copy_of())),
+        };
+
+        let mut names: Vec<&String> = object.keys().collect();
+        names.sort();
+
+        let mut schemas = Vec::with_capacity(names.len());
+        for name in names {
+            key.push(KeyPart::Identifier(name.clone()));
+            schemas.push((
+                name.clone(),
+                self.load_schema(key, base_uri, &object[name])?,
+            ));
+            key.pop();
+        }
+        Ok(schemas)
+    }
+
+    /// Compile `type`, which names either a single type or several types,
+    /// one of which the input must have
+    fn load_type(key: &mut Key, value: &Json) -> Result<JsonSchema, LoadError> {
+        let load = |key: &Key, name: &Json| match name {
+            Json::String(name) => {
+                Type::from_name(name).ok_or_else(|| LoadError::UnknownType(key.copy_of()))
+            }
+            _ => Err(LoadError::InvalidKeywordValue(key.copy_of())),
+        };
+
+        let names = match value {
+            Json::Array(names) if !names.is_empty() => names,
+            Json::Array(_) => return Err(LoadError::InvalidKeywordValue(key.copy_of())),
+            name => return load(key, name).map(JsonSchema::from),
+        };
+
+        let mut types = Vec::with_capacity(names.len());
+        for (index, name) in names.iter().enumerate() {
+            key.push(KeyPart::Index(index));
+            let ty = load(key, name)?;
+            key.pop();
+
+            if !types.contains(&ty) {
+                types.push(ty);
+            }
+        }
+        Ok(JsonSchema::Type(types))
+    }
+
+    fn load_format(&self, key: &Key, value: &Json) -> Result<Format, LoadError> {
+        let name = match value {
+            Json::String(name) => name,
+            _ => return Err(LoadError::InvalidKeywordValue(key.copy_of())),
+        };
+
+        let loader = self.loader;
+        let assert = loader.assert_formats || self.dialect.asserts_formats();
+        let format = match loader.formats.get(name) {
+            Some(checker) => Format::custom(name, checker.clone(), assert),
+            None => Format::new(name, assert),
+        };
+
+        if loader.reject_unknown_formats && !format.is_known() {
+            return Err(LoadError::UnknownFormat(key.copy_of()));
+        }
+        Ok(format)
+    }
+
+    /// Compile the content keywords of `object`, where `key` is the key to
+    /// one of them
+    fn load_content(
+        &mut self,
+        key: &Key,
+        base_uri: &str,
+        object: &Map,
+    ) -> Result<Content, LoadError> {
+        let string = |name| {
+            object
+                .get(name)
+                .filter(|_| self.dialect.enables(name))
+                .map(|value| load_string(&sibling_key(key, name), value))
+                .transpose()
+        };
+        let encoding = string("contentEncoding")?.map(Encoding::from_name);
+        let media_type = string("contentMediaType")?.map(MediaType::from_name);
+
+        let schema = match object.get("contentSchema") {
+            Some(schema) if media_type.is_some() && self.dialect.enables("contentSchema") => {
+                let key = &mut sibling_key(key, "contentSchema");
+                Some(self.load_schema(key, base_uri, schema)?)
+            }
+            _ => None,
+        };
+
+        let assert = self.loader.assert_content;
+        Ok(Content::new(encoding, media_type, schema, assert))
+    }
+
+    fn load_properties(
+        &mut self,
+        key: &mut Key,
+        base_uri: &str,
+        properties: &Json,
+        required: Option<&Json>,
+    ) -> Result<JsonSchema, LoadError> {
+        let properties = match properties {
+            Json::Object(properties) => properties,
+            _ => return Err(LoadError::InvalidKeywordValue(key.copy_of())),
+        };
+
+        let invalid_required = || {
+            let mut required_key = key.copy_of();
+            required_key.pop();
+            required_key.push(KeyPart::Identifier("required".to_string()));
+            LoadError::InvalidKeywordValue(required_key)
+        };
+
+        let required: Vec<&String> = match required {
+            Some(Json::Array(names)) => {
+                let mut required = Vec::with_capacity(names.len());
+                for name in names {
+                    match name {
+                        Json::String(name) => required.push(name),
+                        _ => return Err(invalid_required()),
+                    }
+                }
+                required
+            }
+            Some(_) => return Err(invalid_required()),
+            None => Vec::new(),
+        };
+
+        let mut names: Vec<&String> = properties.keys().collect();
+        names.sort();
+
+        let mut loaded = Vec::with_capacity(names.len());
+        for name in names {
+            key.push(KeyPart::Identifier(name.clone()));
+            let schema = self.load_schema(key, base_uri, &properties[name])?;
+            key.pop();
+
+            loaded.push(Property::new(name, schema, required.contains(&name)));
+        }
+
+        for name in required {
+            if !properties.contains_key(name) {
+                loaded.push(Property::new(name, JsonSchema::Boolean(true), true));
+            }
+        }
+
+        Ok(JsonSchema::Properties(loaded))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LoadError, Loader};
+    use crate::{json::Json, schema::Draft};
+
+    macro_rules! assert_valid {
+        ($schema: expr, $input: expr, $valid: expr) => {
+            let schema = Loader::new()
+                .load(&Json::from_string($schema).unwrap())
+                .unwrap();
+            let input = Json::from_string($input).unwrap();
+            assert_eq!(
+                schema.validate(&input).success,
+                $valid,
+                "{} against {}",
+                $input,
+                $schema
+            );
+        };
+    }
+
+    #[test]
+    fn boolean_subschemas() {
+        assert_valid!("true", r#"{"a": 1}"#, true);
+        assert_valid!("false", r#"{"a": 1}"#, false);
+        assert_valid!("{}", "[]", true);
+        assert_valid!(r#"{"not": false}"#, "12", true);
+        assert_valid!(r#"{"not": true}"#, "12", false);
+        assert_valid!(r#"{"allOf": [true, true]}"#, "null", true);
+        assert_valid!(r#"{"anyOf": [false, true]}"#, "null", true);
+        assert_valid!(r#"{"oneOf": [true, true]}"#, "null", false);
+        assert_valid!(r#"{"properties": {"a": false}}"#, r#"{"a": 1}"#, false);
+        assert_valid!(r#"{"properties": {"a": true}}"#, r#"{"a": 1}"#, true);
+    }
+
+    #[test]
+    fn properties() {
+        let schema = r#"{"type": "object", "properties": {"a": {"type": "string"}}, "required": ["a", "b"]}"#;
+        assert_valid!(schema, r#"{"a": "x", "b": null}"#, true);
+        assert_valid!(schema, r#"{"a": 1, "b": null}"#, false);
+        assert_valid!(schema, r#"{"a": "x"}"#, false);
+        assert_valid!(r#"{"required": ["a"]}"#, r#"{"a": 1}"#, true);
+        assert_valid!(r#"{"required": ["a"]}"#, r#"{"b": 1}"#, false);
+    }
+
+    #[test]
+    fn numbers() {
+        // Limits and inputs past 2^53 are compared exactly
+        assert_valid!(r#"{"multipleOf": 2}"#, "9007199254740993", false);
+        assert_valid!(r#"{"multipleOf": 2}"#, "9007199254740994", true);
+        assert_valid!(
+            r#"{"maximum": 9007199254740992}"#,
+            "9007199254740993",
+            false
+        );
+        assert_valid!(r#"{"maximum": 9007199254740992}"#, "9007199254740992", true);
+        assert_valid!(
+            r#"{"exclusiveMinimum": 18446744073709551616}"#,
+            "18446744073709551617",
+            true
+        );
+        assert_valid!(r#"{"multipleOf": 0.3}"#, "1e300", false);
+        assert_valid!(r#"{"multipleOf": 0.1}"#, "0.3", true);
+    }
+
+    #[test]
+    fn formats() {
+        let schema = Json::from_string(r#"{"format": "ipv4"}"#).unwrap();
+        let annotating = Loader::new().load(&schema).unwrap();
+        let asserting = Loader::new().assert_formats(true).load(&schema).unwrap();
+
+        let input = Json::from_string(r#""300.0.0.1""#).unwrap();
+        let result = annotating.validate(&input);
+        assert!(result.success);
+        assert_eq!(result.annotations.len(), 1);
+        assert!(!asserting.validate(&input).success);
+
+        assert!(asserting.is_valid(&Json::from_string(r#""10.0.0.1""#).unwrap()));
+        assert!(asserting.is_valid(&Json::from_string("12").unwrap()));
+    }
+
+    #[test]
+    fn custom_formats() {
+        let schema = Json::from_string(r#"{"format": "semver"}"#).unwrap();
+        let semver = |value: &str| {
+            let parts: Vec<&str> = value.split('.').collect();
+            parts.len() == 3 && parts.iter().all(|part| part.parse::<u64>().is_ok())
+        };
+        let schema = Loader::new()
+            .assert_formats(true)
+            .with_format("semver", semver)
+            .load(&schema)
+            .unwrap();
+
+        assert!(schema.is_valid(&Json::from_string(r#""1.2.3""#).unwrap()));
+        assert!(!schema.is_valid(&Json::from_string(r#""1.2""#).unwrap()));
+
+        let schema = Json::from_string(r#"{"properties": {"a": {"format": "sku"}}}"#).unwrap();
+        assert!(Loader::new().load(&schema).is_ok());
+        assert!(matches!(
+            Loader::new().reject_unknown_formats(true).load(&schema),
+            Err(LoadError::UnknownFormat(_))
+        ));
+        assert!(Loader::new()
+            .reject_unknown_formats(true)
+            .with_format("sku", |value: &str| value.starts_with("SKU-"))
+            .load(&schema)
+            .is_ok());
+    }
+
+    #[test]
+    fn invalid_schemas() {
+        let load = |schema: &str| {
+            Loader::new()
+                .validate_schemas(false)
+                .load(&Json::from_string(schema).unwrap())
+        };
+
+        assert!(matches!(load("12"), Err(LoadError::InvalidSchema(_))));
+        assert!(matches!(
+            load(r#"{"not": "x"}"#),
+            Err(LoadError::InvalidSchema(_))
+        ));
+        assert!(matches!(
+            load(r#"{"type": "strnig"}"#),
+            Err(LoadError::UnknownType(_))
+        ));
+        assert!(matches!(
+            load(r#"{"anyOf": []}"#),
+            Err(LoadError::EmptySchemaArray(_))
+        ));
+        assert!(matches!(
+            load(r#"{"pattern": "(a"}"#),
+            Err(LoadError::InvalidRegex(_))
+        ));
+        assert!(matches!(
+            load(r##"{"$ref": "#/$defs/missing"}"##),
+            Err(LoadError::UnresolvedReference(_))
+        ));
+    }
+
+    #[test]
+    fn metaschema() {
+        let errors = |schema: &str| match Loader::new().load(&Json::from_string(schema).unwrap()) {
+            Err(LoadError::Metaschema(errors)) => errors
+                .iter()
+                .map(|error| error.to_string())
+                .collect::<Vec<_>>(),
+            result => panic!("expected metaschema errors, found {:?}", result),
+        };
+
+        assert_eq!(
+            errors(r#"{"minimum": "5", "properties": {"a": {"minLength": -1}}}"#),
+            vec![
+                r#"/minimum: expected number, found string "5""#,
+                "/properties/a/minLength: expected at least 0, found number -1",
+            ]
+        );
+        assert_eq!(
+            errors("12"),
+            vec!["/: expected object or boolean, found number 12"]
+        );
+        assert!(!errors(r#"{"type": "strnig"}"#).is_empty());
+
+        let error = Loader::new()
+            .load(&Json::from_string(r#"{"minimum": "5", "maxLength": -1}"#).unwrap())
+            .unwrap_err();
+        assert_eq!(error.key().unwrap().to_string(), "/maxLength");
+        assert_eq!(
+            error.to_string(),
+            "/maxLength: expected at least 0, found number -1\n\
+             /minimum: expected number, found string \"5\""
+        );
+        assert!(LoadError::Metaschema(Vec::new()).key().is_none());
+        assert_eq!(
+            LoadError::Metaschema(Vec::new()).to_string(),
+            "schema is not valid against its metaschema"
+        );
+
+        let unknown = r#"{"$schema": "https://example.com/schema"}"#;
+        let error = Loader::new()
+            .load(&Json::from_string(unknown).unwrap())
+            .unwrap_err();
+        assert!(matches!(error, LoadError::UnknownMetaschema(_)));
+        assert_eq!(error.to_string(), "/$schema: unknown metaschema");
+
+        let explicit = r#"{"$schema": "https://json-schema.org/draft/2020-12/schema#"}"#;
+        assert!(Loader::new()
+            .load(&Json::from_string(explicit).unwrap())
+            .is_ok());
+    }
+
+    #[test]
+    fn drafts() {
+        let draft4 = r##"{
+            "$schema": "http://json-schema.org/draft-04/schema#",
+            "properties": {
+                "a": {"maximum": 5, "exclusiveMaximum": true},
+                "b": {"$ref": "#/definitions/pair"}
+            },
+            "definitions": {
+                "pair": {"items": [{"type": "number"}], "additionalItems": false}
+            }
+        }"##;
+        assert_valid!(draft4, r#"{"a": 4, "b": [1]}"#, true);
+        assert_valid!(draft4, r#"{"a": 5}"#, false);
+        assert_valid!(draft4, r#"{"b": [1, 2]}"#, false);
+
+        // `$ref` overrides the keywords next to it before 2019-09
+        let draft7 = r##"{
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "definitions": {"name": {"type": "string"}},
+            "$ref": "#/definitions/name",
+            "maxLength": 1
+        }"##;
+        assert_valid!(draft7, r#""abc""#, true);
+        assert_valid!(draft7, "1", false);
+
+        // Keywords from later drafts are ignored
+        let schema = Json::from_string(r#"{"dependencies": {"a": ["b"]}, "const": 1}"#).unwrap();
+        let schema = Loader::new()
+            .default_draft(Draft::Draft4)
+            .load(&schema)
+            .unwrap();
+        assert!(schema.is_valid(&Json::from_string(r#"{"a": 1, "b": 2}"#).unwrap()));
+        assert!(!schema.is_valid(&Json::from_string(r#"{"a": 1}"#).unwrap()));
+
+        let invalid =
+            r#"{"$schema": "http://json-schema.org/draft-04/schema#", "exclusiveMinimum": 1}"#;
+        assert!(matches!(
+            Loader::new().load(&Json::from_string(invalid).unwrap()),
+            Err(LoadError::Metaschema(_))
+        ));
+    }
+
+    #[test]
+    fn unevaluated() {
+        // Every way of validating agrees, whether or not it stops early
+        let check = |draft: Draft, schema: &str, input: &str, valid: bool| {
+            let schema = Loader::new()
+                .default_draft(draft)
+                .load(&Json::from_string(schema).unwrap())
+                .unwrap();
+            let input = Json::from_string(input).unwrap();
+            let outcomes = [
+                schema.is_valid(&input),
+                schema.validate(&input).success,
+                schema.iter_errors(&input).next().is_none(),
+            ];
+            assert_eq!(outcomes, [valid; 3], "{:?} against {:?}", input, schema);
+        };
+        let both = |schema: &str, input: &str, valid: bool| {
+            check(Draft::Draft201909, schema, input, valid);
+            check(Draft::Draft202012, schema, input, valid);
+        };
+
+        let closed = r#"{"properties": {"a": true}, "unevaluatedProperties": false}"#;
+        both(closed, r#"{"a": 1}"#, true);
+        both(closed, r#"{"a": 1, "b": 2}"#, false);
+        both(r#"{"unevaluatedProperties": false}"#, r#"{"a": 1}"#, false);
+        both(r#"{"unevaluatedProperties": false}"#, "[1]", true);
+        both(
+            r#"{"unevaluatedProperties": {"type": "string"}}"#,
+            r#"{"a": "b"}"#,
+            true,
+        );
+
+        // Subschemas that are valid count, and the others do not
+        let applicators = r#"{
+            "allOf": [{"properties": {"a": true}}],
+            "anyOf": [{"properties": {"b": true}}, {"patternProperties": {"^c": true}}],
+            "if": {"properties": {"d": {"const": 1}}},
+            "then": {"properties": {"e": true}},
+            "else": {"properties": {"f": true}},
+            "not": {"properties": {"g": {"const": 1}}, "required": ["h"]},
+            "unevaluatedProperties": false
+        }"#;
+        both(applicators, r#"{"a": 1, "b": 2, "c": 3}"#, true);
+        both(applicators, r#"{"d": 1, "e": 1}"#, true);
+        both(applicators, r#"{"d": 2, "e": 1}"#, false);
+        // `d` is only evaluated by `if` when it is valid
+        both(applicators, r#"{"d": 2, "f": 1}"#, false);
+        both(applicators, r#"{"f": 1}"#, false);
+        both(applicators, r#"{"g": 1}"#, false);
+        both(
+            r#"{"anyOf": [{"properties": {"a": {"const": 1}}}, {"properties": {"b": true}}],
+                "unevaluatedProperties": false}"#,
+            r#"{"a": 2, "b": 1}"#,
+            false,
+        );
+
+        // Adjacent keywords count, but the subschemas of a sibling cannot
+        // see each other
+        let cousins = r#"{
+            "allOf": [
+                {"properties": {"a": true}},
+                {"unevaluatedProperties": false}
+            ]
+        }"#;
+        both(cousins, r#"{"a": 1}"#, false);
+        both(cousins, "{}", true);
+
+        // References and dependent schemas apply to the same value
+        let referenced = r##"{
+            "$ref": "#/$defs/a",
+            "dependentSchemas": {"b": {"properties": {"c": true}}},
+            "$defs": {"a": {"properties": {"a": true}}},
+            "unevaluatedProperties": false
+        }"##;
+        both(referenced, r#"{"a": 1, "b": 1, "c": 1}"#, false);
+        both(referenced, r#"{"a": 1, "c": 1}"#, false);
+        let referenced =
+            referenced.replace(r#""properties": {"c""#, r#""properties": {"b": true, "c""#);
+        both(&referenced, r#"{"a": 1, "b": 1, "c": 1}"#, true);
+
+        // Properties of nested objects are evaluated separately
+        let nested = r#"{
+            "properties": {"a": {"properties": {"b": true}, "unevaluatedProperties": false}},
+            "unevaluatedProperties": false
+        }"#;
+        both(nested, r#"{"a": {"b": 1}}"#, true);
+        both(nested, r#"{"a": {"b": 1, "c": 1}}"#, false);
+
+        let items = r#"{"prefixItems": [true], "unevaluatedItems": false}"#;
+        check(Draft::Draft202012, items, "[1]", true);
+        check(Draft::Draft202012, items, "[1, 2]", false);
+        let items =
+            r#"{"allOf": [{"items": [true, true]}], "items": [true], "unevaluatedItems": false}"#;
+        check(Draft::Draft201909, items, "[1, 2]", true);
+        check(Draft::Draft201909, items, "[1, 2, 3]", false);
+        let items = items.replace("items\": [", "prefixItems\": [");
+        check(Draft::Draft202012, &items, "[1, 2]", true);
+        check(Draft::Draft202012, &items, "[1, 2, 3]", false);
+        both(
+            r#"{"anyOf": [{"items": true}], "unevaluatedItems": false}"#,
+            "[1, 2]",
+            true,
+        );
+        both(
+            r#"{"unevaluatedItems": {"type": "number"}}"#,
+            "[1, 2]",
+            true,
+        );
+        both(
+            r#"{"unevaluatedItems": {"type": "number"}}"#,
+            r#"[1, "a"]"#,
+            false,
+        );
+
+        // The items that `contains` matches only count from 2020-12 on
+        let contains =
+            r#"{"contains": {"type": "string"}, "unevaluatedItems": {"type": "number"}}"#;
+        check(Draft::Draft202012, contains, r#"[1, "a", "b"]"#, true);
+        check(Draft::Draft201909, contains, r#"[1, "a", "b"]"#, false);
+        check(Draft::Draft202012, contains, r#"[1, "a", true]"#, false);
+    }
+
+    #[test]
+    fn recursive_references() {
+        let schema = r##"{
+            "$schema": "https://json-schema.org/draft/2019-09/schema",
+            "$id": "https://example.com/strict-tree",
+            "$recursiveAnchor": true,
+            "$ref": "tree",
+            "propertyNames": {"enum": ["nodes", "value"]},
+            "$defs": {
+                "tree": {
+                    "$id": "tree",
+                    "$recursiveAnchor": true,
+                    "properties": {
+                        "nodes": {"items": {"$recursiveRef": "#"}}
+                    }
+                }
+            }
+        }"##;
+        assert_valid!(schema, r#"{"nodes": [{"value": 1}]}"#, true);
+        assert_valid!(schema, r#"{"nodes": [{"name": 1}]}"#, false);
+    }
+
+    #[test]
+    fn vocabularies() {
+        let metaschema = |vocabularies: &str| {
+            let metaschema = format!(
+                r#"{{
+                    "$schema": "https://json-schema.org/draft/2020-12/schema",
+                    "$id": "https://example.com/meta",
+                    "$vocabulary": {{
+                        "https://json-schema.org/draft/2020-12/vocab/core": true,
+                        {}
+                    }},
+                    "$dynamicAnchor": "meta",
+                    "allOf": [
+                        {{"$ref": "https://json-schema.org/draft/2020-12/meta/core"}},
+                        {{"$ref": "https://json-schema.org/draft/2020-12/meta/applicator"}},
+                        {{"$ref": "https://json-schema.org/draft/2020-12/meta/validation"}}
+                    ]
+                }}"#,
+                vocabularies
+            );
+            Loader::new().with_document(
+                "https://example.com/meta",
+                Json::from_string(&metaschema).unwrap(),
+            )
+        };
+        let schema = Json::from_string(
+            r#"{
+                "$schema": "https://example.com/meta",
+                "properties": {"a": {"format": "ipv4", "minimum": 1}}
+            }"#,
+        )
+        .unwrap();
+        let valid = |loader: &Loader, input: &str| {
+            let schema = loader.load(&schema).unwrap();
+            schema.is_valid(&Json::from_string(input).unwrap())
+        };
+
+        let loader = metaschema(
+            r#""https://json-schema.org/draft/2020-12/vocab/applicator": true,
+            "https://json-schema.org/draft/2020-12/vocab/validation": true"#,
+        );
+        assert!(valid(&loader, r#"{"a": "300.0.0.1"}"#));
+        assert!(!valid(&loader, r#"{"a": 0}"#));
+
+        let loader = metaschema(
+            r#""https://json-schema.org/draft/2020-12/vocab/applicator": true,
+            "https://json-schema.org/draft/2020-12/vocab/format-assertion": true"#,
+        );
+        assert!(!valid(&loader, r#"{"a": "300.0.0.1"}"#));
+        assert!(valid(&loader, r#"{"a": 0}"#));
+
+        let loader = metaschema(
+            r#""https://json-schema.org/draft/2020-12/vocab/applicator": true,
+            "https://example.com/vocab/units": false"#,
+        );
+        assert!(valid(&loader, r#"{"a": 0}"#));
+
+        let loader = metaschema(r#""https://example.com/vocab/units": true"#);
+        assert!(matches!(
+            loader.load(&schema),
+            Err(LoadError::UnknownVocabulary(_))
+        ));
+
+        // Schemas are still validated against the custom metaschema
+        let invalid = r#"{"$schema": "https://example.com/meta", "minimum": "1"}"#;
+        assert!(matches!(
+            metaschema(r#""https://json-schema.org/draft/2020-12/vocab/validation": true"#)
+                .load(&Json::from_string(invalid).unwrap()),
+            Err(LoadError::Metaschema(_))
+        ));
+    }
+}
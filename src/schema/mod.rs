@@ -1,18 +1,54 @@
 pub mod keywords;
 
-use crate::json::{Json, Key};
+mod context;
+use context::{Evaluated, LocationPart, ValidationContext};
+
+mod draft;
+pub use draft::Draft;
+
+mod errors;
+pub use errors::ErrorIter;
+
+mod loader;
+pub use loader::{LoadError, Loader, SchemaError};
+
+mod output;
+pub use output::OutputFormat;
+
+mod regex;
+pub use regex::{Regex, RegexError};
+
+mod registry;
+pub use registry::Registry;
+
+mod uri;
+
+mod vocabulary;
+pub use vocabulary::Vocabulary;
+
+use std::{collections::HashMap, fmt::Display};
+
+use crate::json::{Json, Key, Map};
 
 use self::keywords::{
-    annotations::{LogicError, PropertyError, TypeError},
-    LogicApplier, Property, Type,
+    annotations::{
+        ArrayError, ContentError, CustomAnnotation, FormatError, LogicError, NumberError,
+        ObjectError, PropertyError, ReferenceError, StringError, TypeError,
+    },
+    ArrayKeyword, Content, Custom, Format, LogicApplier, Metadata, NumberKeyword, ObjectKeyword,
+    Property, Reference, StringKeyword, Type,
 };
 
+/// Validates input against a schema
+///
+/// Annotations may borrow from both the schema and the input, so the input
+/// must live at least as long as the schema.
 trait JsonSchemaValidator {
     fn validate_json<'schema>(
         &'schema self,
         key_to_input: &mut Key,
-        input: &Json,
-        annotations: &mut Vec<Annotation<'schema>>,
+        input: &'schema Json,
+        context: &mut ValidationContext<'schema>,
     ) -> bool;
 }
 
@@ -38,131 +74,626 @@ where
     }
 }
 
+/// Writes a human-readable description of an annotation, without the
+/// location in the input that it applies to
+trait Describe {
+    fn describe(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result;
+}
+
+/// The location in the input that `key` points to, for use in messages
+fn describe_location(key: &Key) -> String {
+    let pointer = key.to_string();
+    if pointer.is_empty() {
+        "/".to_string()
+    } else {
+        pointer
+    }
+}
+
+/// A short description of `value`, such as `string "ten"` or `object`
+fn describe_value(value: &Json) -> String {
+    match value {
+        Json::Object(_) | Json::Array(_) | Json::Null => Type::from(value).to_string(),
+        _ => format!("{} {}", Type::from(value), value),
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Annotation<'schema> {
     LogicError(LogicError<'schema>),
     PropertyError(PropertyError<'schema>),
-    TypeError(TypeError),
+    TypeError(TypeError<'schema>),
+    FormatError(FormatError<'schema>),
+    ContentError(ContentError<'schema>),
+    ArrayError(ArrayError<'schema>),
+    ObjectError(ObjectError<'schema>),
+    NumberError(NumberError<'schema>),
+    StringError(StringError<'schema>),
+    ReferenceError(ReferenceError<'schema>),
+    /// Produced by a keyword that was registered with the [`Loader`]
+    Custom(CustomAnnotation<'schema>),
     Unequal {
-        schema: &'schema JsonSchema<'schema>,
+        schema: &'schema JsonSchema,
+        key: Key,
+        value: &'schema Json,
+    },
+    /// The input was checked against the `false` schema, which rejects everything
+    FalseSchema {
+        key: Key,
+    },
+    /// A metadata keyword such as `title` or `default` applies to the input
+    Metadata {
+        metadata: &'schema Metadata,
         key: Key,
     },
 }
 
+impl<'schema> Annotation<'schema> {
+    /// The location in the input that this annotation applies to
+    pub fn key(&self) -> &Key {
+        match self {
+            Annotation::LogicError(error) => &error.key,
+            Annotation::PropertyError(error) => &error.key,
+            Annotation::TypeError(error) => &error.key,
+            Annotation::FormatError(error) => &error.key,
+            Annotation::ContentError(error) => &error.key,
+            Annotation::ArrayError(error) => &error.key,
+            Annotation::ObjectError(error) => &error.key,
+            Annotation::NumberError(error) => &error.key,
+            Annotation::StringError(error) => &error.key,
+            Annotation::ReferenceError(error) => &error.key,
+            Annotation::Custom(annotation) => &annotation.key,
+            Annotation::Unequal { key, .. }
+            | Annotation::FalseSchema { key }
+            | Annotation::Metadata { key, .. } => key,
+        }
+    }
+}
+
+impl Describe for Annotation<'_> {
+    fn describe(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Annotation::LogicError(error) => error.describe(f),
+            Annotation::PropertyError(error) => error.describe(f),
+            Annotation::TypeError(error) => error.describe(f),
+            Annotation::FormatError(error) => error.describe(f),
+            Annotation::ContentError(error) => error.describe(f),
+            Annotation::ArrayError(error) => error.describe(f),
+            Annotation::ObjectError(error) => error.describe(f),
+            Annotation::NumberError(error) => error.describe(f),
+            Annotation::StringError(error) => error.describe(f),
+            Annotation::ReferenceError(error) => error.describe(f),
+            Annotation::Custom(annotation) => annotation.describe(f),
+            Annotation::Unequal { schema, value, .. } => match schema {
+                JsonSchema::Primitive(expected) => {
+                    write!(f, "expected {}, found {}", expected, describe_value(value))
+                }
+                JsonSchema::Enum(values) => {
+                    let values: Vec<String> = values.iter().map(Json::to_string).collect();
+                    let values = values.join(", ");
+                    write!(
+                        f,
+                        "expected one of {}, found {}",
+                        values,
+                        describe_value(value)
+                    )
+                }
+                _ => write!(f, "unexpected {}", describe_value(value)),
+            },
+            Annotation::FalseSchema { .. } => write!(f, "no value is allowed here"),
+            Annotation::Metadata { metadata, .. } => {
+                let value: Json = (*metadata).clone().into();
+                write!(f, "{} is {}", metadata.keyword(), value)
+            }
+        }
+    }
+}
+
+impl Display for Annotation<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", describe_location(self.key()))?;
+        self.describe(f)
+    }
+}
+
+impl Annotation<'_> {
+    /// The description of this annotation, without the location in the
+    /// input that it applies to
+    pub fn message(&self) -> String {
+        struct Message<'a, 'schema>(&'a Annotation<'schema>);
+
+        impl Display for Message<'_, '_> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                self.0.describe(f)
+            }
+        }
+
+        Message(self).to_string()
+    }
+}
+
+impl<'schema> AnnotationValue for Annotation<'schema> {
+    fn is_error(&self) -> bool {
+        match self {
+            Annotation::LogicError(error) => error.is_error(),
+            Annotation::PropertyError(error) => error.is_error(),
+            Annotation::FormatError(error) => error.is_error(),
+            Annotation::ContentError(error) => error.is_error(),
+            Annotation::Custom(annotation) => annotation.is_error(),
+            Annotation::TypeError(_)
+            | Annotation::ArrayError(_)
+            | Annotation::ObjectError(_)
+            | Annotation::NumberError(_)
+            | Annotation::StringError(_)
+            | Annotation::ReferenceError(_)
+            | Annotation::Unequal { .. }
+            | Annotation::FalseSchema { .. } => true,
+            Annotation::Metadata { .. } => false,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
-pub enum JsonSchema<'schema> {
+pub enum JsonSchema {
+    /// `true` accepts every input, `false` rejects every input
+    Boolean(bool),
     Primitive(Json),
-    Logic(LogicApplier<'schema>),
-    Properties(Vec<Property<'schema>>),
-    Type(Type),
+    /// `enum`, which requires the input to be equal to one of the values
+    Enum(Vec<Json>),
+    Logic(LogicApplier),
+    Properties(Vec<Property>),
+    /// `type`, which allows any of a set of types
+    Type(Vec<Type>),
+    Format(Format),
+    /// `contentEncoding`, `contentMediaType` and `contentSchema`
+    Content(Content),
+    Array(ArrayKeyword),
+    Object(ObjectKeyword),
+    Number(NumberKeyword),
+    String(StringKeyword),
+    Reference(Reference),
+    Metadata(Metadata),
+    /// A keyword that was registered with the [`Loader`]
+    Custom(Custom),
+    /// A schema object consisting of several keywords, all of which must
+    /// be valid
+    Keywords(Vec<JsonSchema>),
+    /// A schema that is identified by an absolute URI through `$id`
+    Resource {
+        id: String,
+        schema: Box<JsonSchema>,
+    },
+    /// A schema document, along with the schemas that the references in
+    /// it resolve to
+    Root {
+        schema: Box<JsonSchema>,
+        registry: Box<Registry>,
+    },
 }
 
-impl<'schema> JsonSchema<'schema> {
-    fn validate_json<'input>(
+impl JsonSchema {
+    /// The name of the keyword that this schema represents, if it
+    /// represents a single keyword
+    pub fn keyword(&self) -> Option<&str> {
+        let keyword = match self {
+            JsonSchema::Primitive(_) => "const",
+            JsonSchema::Enum(_) => "enum",
+            JsonSchema::Logic(LogicApplier::AllOf(_)) => "allOf",
+            JsonSchema::Logic(LogicApplier::AnyOf(_)) => "anyOf",
+            JsonSchema::Logic(LogicApplier::OneOf(_)) => "oneOf",
+            JsonSchema::Logic(LogicApplier::Not(_)) => "not",
+            JsonSchema::Logic(LogicApplier::If { .. }) => "if",
+            JsonSchema::Properties(_) => "properties",
+            JsonSchema::Type(_) => "type",
+            JsonSchema::Format(_) => "format",
+            JsonSchema::Content(content) => content.keyword(),
+            JsonSchema::Array(keyword) => keyword.keyword(),
+            JsonSchema::Object(keyword) => keyword.keyword(),
+            JsonSchema::Number(keyword) => keyword.keyword(),
+            JsonSchema::String(keyword) => keyword.keyword(),
+            JsonSchema::Reference(reference) => reference.keyword(),
+            JsonSchema::Metadata(metadata) => metadata.keyword(),
+            JsonSchema::Custom(custom) => custom.name(),
+            JsonSchema::Boolean(_)
+            | JsonSchema::Keywords(_)
+            | JsonSchema::Resource { .. }
+            | JsonSchema::Root { .. } => return None,
+        };
+        Some(keyword)
+    }
+
+    fn validate_json<'schema>(
         &'schema self,
         key_to_input: &mut Key,
-        input: &'input Json,
-        annotations: &mut Vec<Annotation<'schema>>,
+        input: &'schema Json,
+        context: &mut ValidationContext<'schema>,
     ) -> bool {
+        match self {
+            JsonSchema::Resource { id, schema } => {
+                context.enter_resource(id, "");
+                let success = schema.validate_json(key_to_input, input, context);
+                context.exit_resource();
+                return success;
+            }
+            JsonSchema::Root { schema, registry } => {
+                context.enter_registry(registry);
+                context.enter_resource("", "");
+                let success = schema.validate_json(key_to_input, input, context);
+                context.exit_resource();
+                return success;
+            }
+            _ => {}
+        }
+
+        let tracks = self.has_unevaluated();
+        context.enter_schema(tracks);
+        context.enter(key_to_input);
+
+        let mut success = true;
+        match self {
+            JsonSchema::Boolean(accept) => {
+                if !accept {
+                    success = false;
+                    context.annotate(|| Annotation::FalseSchema {
+                        key: key_to_input.copy_of(),
+                    });
+                }
+            }
+            JsonSchema::Keywords(keywords) => {
+                for keyword in keywords {
+                    if !keyword.validate_keyword(key_to_input, input, context) {
+                        success = false;
+                        if context.is_fail_fast() {
+                            break;
+                        }
+                    }
+                }
+            }
+            _ => success = self.validate_keyword(key_to_input, input, context),
+        }
+
+        context.exit(success);
+        context.exit_schema(success, tracks);
+        success
+    }
+
+    /// Whether this schema has `unevaluatedProperties` or
+    /// `unevaluatedItems`, which depend on what its other keywords evaluate
+    fn has_unevaluated(&self) -> bool {
+        let is_unevaluated = |schema: &JsonSchema| {
+            matches!(
+                schema,
+                JsonSchema::Object(ObjectKeyword::UnevaluatedProperties(_))
+                    | JsonSchema::Array(ArrayKeyword::UnevaluatedItems { .. })
+            )
+        };
+        match self {
+            JsonSchema::Keywords(keywords) => keywords.iter().any(is_unevaluated),
+            schema => is_unevaluated(schema),
+        }
+    }
+
+    fn validate_keyword<'schema>(
+        &'schema self,
+        key_to_input: &mut Key,
+        input: &'schema Json,
+        context: &mut ValidationContext<'schema>,
+    ) -> bool {
+        let keyword = if let Some(keyword) = self.keyword() {
+            keyword
+        } else {
+            return self.validate_json(key_to_input, input, context);
+        };
+
+        context.push_location(LocationPart::Keyword(keyword));
+        context.enter(key_to_input);
+
         let mut success = true;
         match self {
             JsonSchema::Primitive(primitive) => {
                 if input != primitive {
                     success = false;
-                    annotations.push(
-                        Annotation::Unequal {
-                            schema: self,
-                            key: key_to_input.copy_of(),
-                        }
-                        .into(),
-                    );
+                    context.annotate(|| Annotation::Unequal {
+                        schema: self,
+                        key: key_to_input.copy_of(),
+                        value: input,
+                    });
+                }
+            }
+            JsonSchema::Enum(values) => {
+                if !values.contains(input) {
+                    success = false;
+                    context.annotate(|| Annotation::Unequal {
+                        schema: self,
+                        key: key_to_input.copy_of(),
+                        value: input,
+                    });
                 }
             }
             JsonSchema::Logic(logic) => {
-                if !logic.validate_json(key_to_input, input, annotations) {
+                if !logic.validate_json(key_to_input, input, context) {
                     success = false;
                 }
             }
-            JsonSchema::Properties(properties) => {
+            // Like the other keywords that apply to objects, `properties`
+            // and `required` ignore any other input
+            JsonSchema::Properties(properties) if matches!(input, Json::Object(_)) => {
                 for property in properties {
-                    if !property.validate_json(key_to_input, input, annotations) {
+                    if !property.validate_json(key_to_input, input, context) {
                         success = false;
+                        if context.is_fail_fast() {
+                            break;
+                        }
                     }
                 }
             }
+            JsonSchema::Properties(_) => {}
             JsonSchema::Type(ty) => {
-                if !ty.validate_json(key_to_input, input, annotations) {
+                if !ty.validate_json(key_to_input, input, context) {
+                    success = false;
+                }
+            }
+            JsonSchema::Format(format) => {
+                if !format.validate_json(key_to_input, input, context) {
+                    success = false;
+                }
+            }
+            JsonSchema::Content(content) => {
+                success = content.validate_json(key_to_input, input, context);
+            }
+            JsonSchema::Array(keyword) => {
+                success = keyword.validate_json(key_to_input, input, context);
+            }
+            JsonSchema::Object(keyword) => {
+                success = keyword.validate_json(key_to_input, input, context);
+            }
+            JsonSchema::Number(keyword) => {
+                success = keyword.validate_json(key_to_input, input, context);
+            }
+            JsonSchema::String(keyword) => {
+                success = keyword.validate_json(key_to_input, input, context);
+            }
+            JsonSchema::Reference(reference) => {
+                success = reference.validate_json(key_to_input, input, context);
+            }
+            JsonSchema::Metadata(metadata) => {
+                metadata.validate_json(key_to_input, input, context);
+            }
+            JsonSchema::Custom(custom) => {
+                if !custom.validate_json(key_to_input, input, context) {
                     success = false;
                 }
             }
+            JsonSchema::Boolean(_)
+            | JsonSchema::Keywords(_)
+            | JsonSchema::Resource { .. }
+            | JsonSchema::Root { .. } => unreachable!(),
         }
 
+        context.exit(success);
+        context.pop_location();
         success
     }
 }
 
-impl<'schema> JsonSchema<'schema> {
+impl JsonSchema {
     pub fn validate<'a>(&'a self, input: &'a Json) -> ValidationResult<'a> {
-        let mut annotations = Vec::new();
+        let mut context = ValidationContext::new();
         let key_to_input = &mut Key::default();
-        let validation_success = self.validate_json(key_to_input, input, &mut annotations);
+        let validation_success = self.validate_json(key_to_input, input, &mut context);
         ValidationResult {
             success: validation_success,
-            annotations,
+            annotations: context.into_annotations(),
         }
     }
+
+    /// Check whether `input` is valid against this schema
+    ///
+    /// This is cheaper than [`JsonSchema::validate`]: no annotations are
+    /// produced and evaluation stops as soon as the outcome is known.
+    pub fn is_valid(&self, input: &Json) -> bool {
+        let mut context = ValidationContext::fail_fast();
+        self.validate_json(&mut Key::default(), input, &mut context)
+    }
+
+    /// Lazily validate `input`, producing the errors that make it invalid
+    /// one at a time
+    pub fn iter_errors<'a>(&'a self, input: &'a Json) -> ErrorIter<'a> {
+        ErrorIter::new(self, input)
+    }
+
+    /// Validate `input` and describe the result in one of the standard
+    /// output formats
+    pub fn output(&self, input: &Json, format: OutputFormat) -> Json {
+        if format == OutputFormat::Flag {
+            let mut output = Map::new();
+            output.insert("valid".to_string(), Json::Boolean(self.is_valid(input)));
+            return Json::Object(output);
+        }
+
+        let mut context = ValidationContext::recording();
+        let key_to_input = &mut Key::default();
+        self.validate_json(key_to_input, input, &mut context);
+
+        let root = context
+            .into_output()
+            .expect("Evaluating a schema always produces an output node");
+        output::render(&root, format)
+    }
 }
 
-impl<'schema> From<Json> for JsonSchema<'schema> {
+impl From<Json> for JsonSchema {
     fn from(input: Json) -> Self {
         Self::Primitive(input)
     }
 }
 
-impl<'schema> From<&str> for JsonSchema<'schema> {
+impl From<&str> for JsonSchema {
     fn from(input: &str) -> Self {
         Json::from(input).into()
     }
 }
 
+impl From<bool> for JsonSchema {
+    fn from(input: bool) -> Self {
+        Self::Boolean(input)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ValidationResult<'schema> {
     pub success: bool,
     pub annotations: Vec<Annotation<'schema>>,
 }
 
+impl<'schema> ValidationResult<'schema> {
+    /// The annotations that caused validation to fail
+    pub fn errors(&self) -> impl Iterator<Item = &Annotation<'schema>> {
+        self.annotations
+            .iter()
+            .filter(|annotation| annotation.is_error())
+    }
+
+    /// The values of metadata keywords such as `title` and `default`,
+    /// keyed by the JSON Pointer to the part of the input they apply to
+    ///
+    /// Metadata from schemas that were not valid is not included.
+    pub fn metadata(&self) -> HashMap<String, Vec<&'schema Metadata>> {
+        let mut metadata: HashMap<String, Vec<&'schema Metadata>> = HashMap::new();
+        for annotation in &self.annotations {
+            if let Annotation::Metadata {
+                metadata: value,
+                key,
+            } = annotation
+            {
+                metadata.entry(key.to_string()).or_default().push(value);
+            }
+        }
+        metadata
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
-        json::{Lexer, Parser},
+        json::{Json, Parser},
         schema::{
-            keywords::{Property, Type},
-            JsonSchema,
+            keywords::{LogicApplier, Property, Type},
+            JsonSchema, Loader,
         },
     };
 
     #[test]
     fn big_test() {
         let input = r#"{"first_key": "value", "second_key": {"first_nested_key": 1e23, "second_nested_key": 123}}"#;
-        let tokens = &mut Vec::new();
-        Lexer::new(Some(input))
-            .lex_into(input.chars(), tokens)
-            .unwrap();
-
-        let input = Parser::parse_tokens(&tokens).unwrap().unwrap();
+        let input = Parser::parse_str(input).unwrap();
 
         let second_level = JsonSchema::Properties(vec![
-            Property::new("first_nested_key", &JsonSchema::Type(Type::Number), false),
-            Property::new("second_nested_key", &JsonSchema::Type(Type::String), false),
+            Property::new("first_nested_key", Type::Number.into(), false),
+            Property::new("second_nested_key", Type::String.into(), false),
         ]);
 
         let first_level = JsonSchema::Properties(vec![
-            Property::new("first_key", &JsonSchema::Type(Type::String), false),
-            Property::new("second_key", &second_level, false),
+            Property::new("first_key", Type::String.into(), false),
+            Property::new("second_key", second_level, false),
         ]);
 
-        let annotations = first_level.validate(&input);
+        let result = first_level.validate(&input);
+        assert!(!result.success);
 
-        panic!("{:#?}", annotations.annotations);
+        let messages: Vec<String> = result.errors().map(|error| error.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "/second_key/second_nested_key: expected string, found number 123",
+                r#"/second_key/second_nested_key: value of property "second_nested_key" is invalid"#,
+                r#"/second_key: value of property "second_key" is invalid"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn boolean_schemas() {
+        let input = Json::from_string(r#"{"x": [1, "two", null]}"#).unwrap();
+
+        assert!(JsonSchema::Boolean(true).validate(&input).success);
+        assert!(!JsonSchema::Boolean(false).validate(&input).success);
+
+        let schema = JsonSchema::Properties(vec![Property::new("x", false.into(), false)]);
+        assert!(!schema.validate(&input).success);
+
+        let schema: JsonSchema = LogicApplier::Not(Box::new(false.into())).into();
+        assert!(schema.validate(&input).success);
+    }
+
+    #[test]
+    fn is_valid() {
+        let schema = Json::from_string(
+            r#"{
+                "properties": {
+                    "a": {"anyOf": [{"type": "string"}, {"type": "integer"}]},
+                    "b": {"not": {"const": 1}},
+                    "c": {"oneOf": [{"type": "number"}, {"type": "integer"}]}
+                },
+                "required": ["a"]
+            }"#,
+        )
+        .unwrap();
+        let schema = Loader::new().load(&schema).unwrap();
+
+        for input in [
+            r#"{"a": "x"}"#,
+            r#"{"a": 1, "b": 2}"#,
+            r#"{"a": 1.5}"#,
+            r#"{"a": "x", "b": 1}"#,
+            r#"{"a": "x", "c": 1}"#,
+            r#"{"a": "x", "c": 1.5}"#,
+            r#"{"b": 2}"#,
+            "[]",
+        ] {
+            let input = Json::from_string(input).unwrap();
+            assert_eq!(
+                schema.is_valid(&input),
+                schema.validate(&input).success,
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn messages() {
+        let schema = Json::from_string(
+            r#"{
+                "properties": {
+                    "age": {"type": "integer"},
+                    "kind": {"const": "person"},
+                    "tags": {"anyOf": [{"type": "array"}, {"type": "null"}]}
+                },
+                "required": ["name"]
+            }"#,
+        )
+        .unwrap();
+        let schema = Loader::new().load(&schema).unwrap();
+
+        let input = Json::from_string(r#"{"age": "ten", "kind": "cat", "tags": {}}"#).unwrap();
+        let result = schema.validate(&input);
+        let messages: Vec<String> = result.errors().map(|error| error.to_string()).collect();
+
+        for expected in [
+            r#"/age: expected integer, found string "ten""#,
+            r#"/age: value of property "age" is invalid"#,
+            r#"/kind: expected "person", found string "cat""#,
+            r#"/tags: expected array, found object"#,
+            r#"/tags: not valid against any of the 2 subschemas in anyOf"#,
+            r#"/: required property "name" is missing"#,
+        ] {
+            assert!(
+                messages.iter().any(|message| message == expected),
+                "{} not in {:#?}",
+                expected,
+                messages
+            );
+        }
     }
 }
@@ -0,0 +1,275 @@
+use crate::json::{Json, Map};
+
+use super::{
+    context::OutputNode,
+    keywords::annotations::{CustomAnnotation, CustomAnnotationKind},
+    Annotation, AnnotationValue,
+};
+
+/// The output formats described in section 12.4 of the JSON Schema
+/// specification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Only whether validation succeeded
+    Flag,
+    /// A flat list of errors
+    Basic,
+    /// A condensed hierarchy of errors that follows the structure of the
+    /// schema
+    Detailed,
+    /// The full hierarchy of every schema and keyword that was evaluated
+    Verbose,
+}
+
+fn unit(node: &OutputNode) -> Map {
+    let mut unit = Map::new();
+    unit.insert("valid".to_string(), Json::Boolean(node.valid));
+    unit.insert(
+        "keywordLocation".to_string(),
+        Json::String(node.keyword_location.clone()),
+    );
+    if let Some(absolute) = &node.absolute_keyword_location {
+        unit.insert(
+            "absoluteKeywordLocation".to_string(),
+            Json::String(absolute.clone()),
+        );
+    }
+    unit.insert(
+        "instanceLocation".to_string(),
+        Json::String(node.instance_location.to_string()),
+    );
+    unit
+}
+
+/// One unit for every error (or, if `errors` is false, for every
+/// metadata or custom annotation) that was produced by `node` itself
+fn leaf_units(node: &OutputNode, errors: bool) -> Vec<Json> {
+    node.annotations
+        .iter()
+        .filter_map(|annotation| {
+            let mut unit = unit(node);
+            unit.insert(
+                "instanceLocation".to_string(),
+                Json::String(annotation.key().to_string()),
+            );
+
+            match annotation {
+                Annotation::Metadata { metadata, .. } if !errors => {
+                    unit.insert("annotation".to_string(), (*metadata).clone().into());
+                }
+                Annotation::Custom(CustomAnnotation {
+                    kind: CustomAnnotationKind::Value(value),
+                    ..
+                }) if !errors => {
+                    unit.insert("annotation".to_string(), value.clone());
+                }
+                annotation if errors && annotation.is_error() => {
+                    unit.insert("valid".to_string(), Json::Boolean(false));
+                    unit.insert("error".to_string(), Json::String(annotation.message()));
+                }
+                _ => return None,
+            }
+
+            Some(Json::Object(unit))
+        })
+        .collect()
+}
+
+fn nested_name(errors: bool) -> String {
+    if errors { "errors" } else { "annotations" }.to_string()
+}
+
+fn basic(node: &OutputNode, errors: bool, units: &mut Vec<Json>) {
+    units.extend(leaf_units(node, errors));
+    for child in node.children.iter().filter(|child| child.valid != errors) {
+        basic(child, errors, units);
+    }
+}
+
+fn detailed(node: &OutputNode, errors: bool) -> Option<Json> {
+    let mut units = leaf_units(node, errors);
+    units.extend(
+        node.children
+            .iter()
+            .filter(|child| child.valid != errors)
+            .filter_map(|child| detailed(child, errors)),
+    );
+
+    if units.len() <= 1 {
+        units.pop()
+    } else {
+        let mut unit = unit(node);
+        unit.insert(nested_name(errors), Json::Array(units));
+        Some(Json::Object(unit))
+    }
+}
+
+fn verbose(node: &OutputNode) -> Json {
+    let mut nested = leaf_units(node, !node.valid);
+    if nested.len() == 1 && node.children.is_empty() {
+        return nested.pop().unwrap();
+    }
+
+    nested.extend(node.children.iter().map(verbose));
+
+    let mut unit = unit(node);
+    if !nested.is_empty() {
+        unit.insert(nested_name(!node.valid), Json::Array(nested));
+    }
+    Json::Object(unit)
+}
+
+pub(crate) fn render(root: &OutputNode, format: OutputFormat) -> Json {
+    let errors = !root.valid;
+
+    let output = match format {
+        OutputFormat::Flag => {
+            let mut output = Map::new();
+            output.insert("valid".to_string(), Json::Boolean(root.valid));
+            output
+        }
+        OutputFormat::Basic => {
+            let mut units = Vec::new();
+            basic(root, errors, &mut units);
+
+            let mut output = Map::new();
+            output.insert("valid".to_string(), Json::Boolean(root.valid));
+            if !units.is_empty() {
+                output.insert(nested_name(errors), Json::Array(units));
+            }
+            output
+        }
+        OutputFormat::Detailed => {
+            let units: Vec<Json> = leaf_units(root, errors)
+                .into_iter()
+                .chain(
+                    root.children
+                        .iter()
+                        .filter(|child| child.valid != errors)
+                        .filter_map(|child| detailed(child, errors)),
+                )
+                .collect();
+
+            let mut output = unit(root);
+            if !units.is_empty() {
+                output.insert(nested_name(errors), Json::Array(units));
+            }
+            output
+        }
+        OutputFormat::Verbose => return verbose(root),
+    };
+
+    Json::Object(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OutputFormat;
+    use crate::{json::Json, schema::Loader};
+
+    fn field<'a>(unit: &'a Json, name: &str) -> &'a Json {
+        match unit {
+            Json::Object(unit) => &unit[name],
+            _ => panic!("Output unit is not an object: {}", unit),
+        }
+    }
+
+    fn errors(unit: &Json) -> &Vec<Json> {
+        match field(unit, "errors") {
+            Json::Array(errors) => errors,
+            other => panic!("Errors are not an array: {}", other),
+        }
+    }
+
+    fn output(input: &str, format: OutputFormat) -> Json {
+        let schema = r#"{
+            "$id": "https://example.com/schema",
+            "properties": {"a": {"type": "string"}},
+            "required": ["b"]
+        }"#;
+        let schema = Loader::new()
+            .load(&Json::from_string(schema).unwrap())
+            .unwrap();
+        schema.output(&Json::from_string(input).unwrap(), format)
+    }
+
+    #[test]
+    fn flag() {
+        let flag = output(r#"{"a": "x", "b": 1}"#, OutputFormat::Flag);
+        assert_eq!(field(&flag, "valid"), &Json::Boolean(true));
+
+        let flag = output(r#"{"a": 1}"#, OutputFormat::Flag);
+        assert_eq!(field(&flag, "valid"), &Json::Boolean(false));
+    }
+
+    #[test]
+    fn basic() {
+        let basic = output(r#"{"a": 1}"#, OutputFormat::Basic);
+        assert_eq!(field(&basic, "valid"), &Json::Boolean(false));
+
+        let errors = errors(&basic);
+        let type_error = errors
+            .iter()
+            .find(|error| field(error, "keywordLocation") == &"/properties/a/type".into())
+            .unwrap();
+        assert_eq!(
+            field(type_error, "absoluteKeywordLocation"),
+            &"https://example.com/schema#/properties/a/type".into()
+        );
+        assert_eq!(field(type_error, "instanceLocation"), &"/a".into());
+
+        assert!(errors
+            .iter()
+            .any(|error| field(error, "keywordLocation") == &"/required".into()));
+    }
+
+    #[test]
+    fn detailed() {
+        let detailed = output(r#"{"a": 1, "b": 2}"#, OutputFormat::Detailed);
+        let errors = errors(&detailed);
+        assert_eq!(errors.len(), 1);
+
+        let properties = &errors[0];
+        assert_eq!(field(properties, "keywordLocation"), &"/properties".into());
+        assert_eq!(self::errors(properties).len(), 2);
+    }
+
+    #[test]
+    fn verbose() {
+        let verbose = output(r#"{"a": "x", "b": 1}"#, OutputFormat::Verbose);
+        assert_eq!(field(&verbose, "valid"), &Json::Boolean(true));
+        assert_eq!(field(&verbose, "keywordLocation"), &"".into());
+
+        let annotations = match field(&verbose, "annotations") {
+            Json::Array(annotations) => annotations,
+            other => panic!("{}", other),
+        };
+        assert_eq!(annotations.len(), 1);
+        assert_eq!(
+            field(&annotations[0], "keywordLocation"),
+            &"/properties".into()
+        );
+    }
+
+    #[test]
+    fn annotations() {
+        let schema = r#"{"properties": {"a": {"title": "A", "type": "string"}}}"#;
+        let schema = Loader::new()
+            .load(&Json::from_string(schema).unwrap())
+            .unwrap();
+        let input = Json::from_string(r#"{"a": "x"}"#).unwrap();
+
+        let basic = schema.output(&input, OutputFormat::Basic);
+        let annotations = match field(&basic, "annotations") {
+            Json::Array(annotations) => annotations,
+            other => panic!("{}", other),
+        };
+        assert_eq!(annotations.len(), 1);
+        assert_eq!(field(&annotations[0], "annotation"), &"A".into());
+        assert_eq!(
+            field(&annotations[0], "keywordLocation"),
+            &"/properties/a/title".into()
+        );
+        assert_eq!(field(&annotations[0], "instanceLocation"), &"/a".into());
+    }
+}
@@ -0,0 +1,1243 @@
+//! Regular expressions in the dialect of ECMA-262, with the `u` flag, as
+//! used by `pattern`, `patternProperties` and the `regex` format
+//!
+//! Patterns are parsed into a syntax tree and compiled into a program of
+//! instructions, which is matched against input by backtracking. The
+//! alternatives left to try are kept on a stack rather than in recursive
+//! calls, and matching gives up after a limited number of steps, which
+//! counts as no match.
+
+use std::collections::HashMap;
+
+/// A position in a pattern at which it could not be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegexError {
+    pub position: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Set {
+    Digit,
+    Word,
+    Space,
+    Letter,
+    Uppercase,
+    Lowercase,
+    Number,
+    Punctuation,
+}
+
+impl Set {
+    fn from_property(name: &str) -> Option<Self> {
+        let set = match name {
+            "L" | "Letter" | "Alphabetic" | "Alpha" => Self::Letter,
+            "Lu" | "Uppercase_Letter" | "Uppercase" => Self::Uppercase,
+            "Ll" | "Lowercase_Letter" | "Lowercase" => Self::Lowercase,
+            "N" | "Number" | "Nd" | "Decimal_Number" | "digit" => Self::Number,
+            "P" | "Punctuation" | "punct" => Self::Punctuation,
+            "White_Space" | "space" => Self::Space,
+            _ => return None,
+        };
+        Some(set)
+    }
+
+    fn contains(&self, c: char) -> bool {
+        match self {
+            Self::Digit => c.is_ascii_digit(),
+            Self::Word => is_word(c),
+            Self::Space => c.is_whitespace() || c == '\u{feff}',
+            Self::Letter => c.is_alphabetic(),
+            Self::Uppercase => c.is_uppercase(),
+            Self::Lowercase => c.is_lowercase(),
+            Self::Number => c.is_numeric(),
+            Self::Punctuation => c.is_ascii_punctuation() || (!c.is_ascii() && is_punctuation(c)),
+        }
+    }
+}
+
+fn is_word(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn is_punctuation(c: char) -> bool {
+    matches!(c, '\u{2010}'..='\u{2027}' | '\u{2030}'..='\u{205e}' | '\u{3001}'..='\u{3003}')
+}
+
+fn is_line_terminator(c: char) -> bool {
+    matches!(c, '\n' | '\r' | '\u{2028}' | '\u{2029}')
+}
+
+#[derive(Debug, Clone)]
+enum ClassItem {
+    Char(char),
+    Range(char, char),
+    Set { set: Set, negated: bool },
+}
+
+impl ClassItem {
+    fn contains(&self, c: char) -> bool {
+        match self {
+            Self::Char(expected) => c == *expected,
+            Self::Range(start, end) => (*start..=*end).contains(&c),
+            Self::Set { set, negated } => set.contains(c) != *negated,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Empty,
+    Char(char),
+    /// `.`, which matches everything but line terminators
+    Any,
+    Class {
+        items: Vec<ClassItem>,
+        negated: bool,
+    },
+    Start,
+    End,
+    WordBoundary {
+        negated: bool,
+    },
+    Group {
+        node: Box<Node>,
+        index: Option<usize>,
+    },
+    Concat(Vec<Node>),
+    Alternation(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: u32,
+        max: Option<u32>,
+        greedy: bool,
+    },
+    Backreference(usize),
+    NamedBackreference(String),
+    Lookaround {
+        node: Box<Node>,
+        ahead: bool,
+        negated: bool,
+    },
+}
+
+impl Node {
+    /// Whether this node always matches exactly one character, if any
+    fn single_char(&self, c: char) -> Option<bool> {
+        match self {
+            Node::Char(expected) => Some(c == *expected),
+            Node::Any => Some(!is_line_terminator(c)),
+            Node::Class { items, negated } => {
+                Some(items.iter().any(|item| item.contains(c)) != *negated)
+            }
+            _ => None,
+        }
+    }
+}
+
+struct Parser<'a> {
+    chars: &'a [char],
+    position: usize,
+    groups: usize,
+    names: HashMap<String, usize>,
+    /// The number of capturing groups in the whole pattern
+    total_groups: usize,
+}
+
+impl Parser<'_> {
+    fn error<T>(&self) -> Result<T, RegexError> {
+        Err(RegexError {
+            position: self.position,
+        })
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        if c.is_some() {
+            self.position += 1;
+        }
+        c
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), RegexError> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            self.error()
+        }
+    }
+
+    fn disjunction(&mut self) -> Result<Node, RegexError> {
+        let mut alternatives = vec![self.alternative()?];
+        while self.eat('|') {
+            alternatives.push(self.alternative()?);
+        }
+
+        if alternatives.len() == 1 {
+            Ok(alternatives.pop().unwrap())
+        } else {
+            Ok(Node::Alternation(alternatives))
+        }
+    }
+
+    fn alternative(&mut self) -> Result<Node, RegexError> {
+        let mut terms = Vec::new();
+        while !matches!(self.peek(), None | Some('|' | ')')) {
+            terms.push(self.term()?);
+        }
+
+        match terms.len() {
+            0 => Ok(Node::Empty),
+            1 => Ok(terms.pop().unwrap()),
+            _ => Ok(Node::Concat(terms)),
+        }
+    }
+
+    fn term(&mut self) -> Result<Node, RegexError> {
+        let (atom, quantifiable) = match self.next() {
+            Some('^') => (Node::Start, false),
+            Some('$') => (Node::End, false),
+            Some('.') => (Node::Any, true),
+            Some('(') => self.group()?,
+            Some('[') => (self.class()?, true),
+            Some('\\') => match self.peek() {
+                Some('b') | Some('B') => {
+                    let negated = self.next() == Some('B');
+                    (Node::WordBoundary { negated }, false)
+                }
+                _ => (self.atom_escape()?, true),
+            },
+            Some('*' | '+' | '?' | '{' | '}' | ']' | ')') | None => {
+                self.position = self.position.saturating_sub(1);
+                return self.error();
+            }
+            Some(c) => (Node::Char(c), true),
+        };
+
+        let start = self.position;
+        let (min, max) = match self.quantifier()? {
+            Some(bounds) => bounds,
+            None => return Ok(atom),
+        };
+
+        if !quantifiable {
+            self.position = start;
+            return self.error();
+        }
+
+        let greedy = !self.eat('?');
+        Ok(Node::Repeat {
+            node: Box::new(atom),
+            min,
+            max,
+            greedy,
+        })
+    }
+
+    fn quantifier(&mut self) -> Result<Option<(u32, Option<u32>)>, RegexError> {
+        let bounds = if self.eat('*') {
+            (0, None)
+        } else if self.eat('+') {
+            (1, None)
+        } else if self.eat('?') {
+            (0, Some(1))
+        } else if self.eat('{') {
+            let min = match self.number() {
+                Some(min) => min,
+                None => return self.error(),
+            };
+            let max = if self.eat(',') {
+                self.number()
+            } else {
+                Some(min)
+            };
+            self.expect('}')?;
+
+            if max.is_some_and(|max| max < min) {
+                return self.error();
+            }
+            (min, max)
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(bounds))
+    }
+
+    fn number(&mut self) -> Option<u32> {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+        let digits: String = self.chars[start..self.position].iter().collect();
+        // Bounds that do not fit are effectively unbounded
+        digits.parse().ok().or(if digits.is_empty() {
+            None
+        } else {
+            Some(u32::MAX)
+        })
+    }
+
+    /// The part of a group after the opening parenthesis, and whether it
+    /// can be quantified
+    fn group(&mut self) -> Result<(Node, bool), RegexError> {
+        let (index, lookaround) = if self.eat('?') {
+            match self.next() {
+                Some(':') => (None, None),
+                Some('=') => (None, Some((true, false))),
+                Some('!') => (None, Some((true, true))),
+                Some('<') if self.eat('=') => (None, Some((false, false))),
+                Some('<') if self.eat('!') => (None, Some((false, true))),
+                Some('<') => {
+                    let name = self.group_name()?;
+                    self.groups += 1;
+                    if self.names.insert(name, self.groups).is_some() {
+                        return self.error();
+                    }
+                    (Some(self.groups), None)
+                }
+                _ => return self.error(),
+            }
+        } else {
+            self.groups += 1;
+            (Some(self.groups), None)
+        };
+
+        let node = Box::new(self.disjunction()?);
+        self.expect(')')?;
+
+        Ok(match lookaround {
+            Some((ahead, negated)) => (
+                Node::Lookaround {
+                    node,
+                    ahead,
+                    negated,
+                },
+                false,
+            ),
+            None => (Node::Group { node, index }, true),
+        })
+    }
+
+    /// A group name followed by `>`
+    fn group_name(&mut self) -> Result<String, RegexError> {
+        let mut name = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_alphanumeric() || c == '_' || c == '$' {
+                name.push(c);
+                self.position += 1;
+            } else {
+                break;
+            }
+        }
+
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+            return self.error();
+        }
+        self.expect('>')?;
+        Ok(name)
+    }
+
+    fn hex(&mut self, digits: usize) -> Result<u32, RegexError> {
+        let mut value = 0;
+        for _ in 0..digits {
+            match self.next().and_then(|c| c.to_digit(16)) {
+                Some(digit) => value = value * 16 + digit,
+                None => return self.error(),
+            }
+        }
+        Ok(value)
+    }
+
+    /// A `\u` escape, after the `u`
+    fn unicode_escape(&mut self) -> Result<char, RegexError> {
+        if self.eat('{') {
+            let mut value: u32 = 0;
+            let start = self.position;
+            while let Some(digit) = self.peek().and_then(|c| c.to_digit(16)) {
+                value = value.saturating_mul(16).saturating_add(digit);
+                self.position += 1;
+            }
+            if self.position == start {
+                return self.error();
+            }
+            self.expect('}')?;
+            return char::from_u32(value).map_or_else(|| self.error(), Ok);
+        }
+
+        let value = self.hex(4)?;
+        if (0xd800..0xdc00).contains(&value) {
+            // A surrogate pair, written as two escapes
+            let start = self.position;
+            if self.eat('\\') && self.eat('u') {
+                if let Ok(low) = self.hex(4) {
+                    if (0xdc00..0xe000).contains(&low) {
+                        let value = 0x10000 + ((value - 0xd800) << 10) + (low - 0xdc00);
+                        return char::from_u32(value).map_or_else(|| self.error(), Ok);
+                    }
+                }
+            }
+            self.position = start;
+        }
+        char::from_u32(value).map_or_else(|| self.error(), Ok)
+    }
+
+    /// An escape that stands for a set of characters, such as `\d`
+    fn set_escape(&mut self, c: char) -> Result<Option<ClassItem>, RegexError> {
+        let (set, negated) = match c {
+            'd' => (Set::Digit, false),
+            'D' => (Set::Digit, true),
+            'w' => (Set::Word, false),
+            'W' => (Set::Word, true),
+            's' => (Set::Space, false),
+            'S' => (Set::Space, true),
+            'p' | 'P' => {
+                self.expect('{')?;
+                let mut name = String::new();
+                while let Some(c) = self.peek() {
+                    if c == '}' {
+                        break;
+                    }
+                    name.push(c);
+                    self.position += 1;
+                }
+                self.expect('}')?;
+
+                let name = name.rsplit('=').next().unwrap_or_default();
+                match Set::from_property(name) {
+                    Some(set) => (set, c == 'P'),
+                    None => return self.error(),
+                }
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(ClassItem::Set { set, negated }))
+    }
+
+    /// An escape that stands for a single character, after the backslash
+    fn char_escape(&mut self, c: char, in_class: bool) -> Result<char, RegexError> {
+        let c = match c {
+            't' => '\t',
+            'n' => '\n',
+            'v' => '\u{b}',
+            'f' => '\u{c}',
+            'r' => '\r',
+            'b' if in_class => '\u{8}',
+            '-' if in_class => '-',
+            '0' if !self.peek().is_some_and(|c| c.is_ascii_digit()) => '\0',
+            'c' => match self.next() {
+                Some(c) if c.is_ascii_alphabetic() => char::from(c as u8 % 32),
+                _ => return self.error(),
+            },
+            'x' => char::from_u32(self.hex(2)?).unwrap_or_default(),
+            'u' => self.unicode_escape()?,
+            '^' | '$' | '\\' | '.' | '*' | '+' | '?' | '(' | ')' | '[' | ']' | '{' | '}' | '|'
+            | '/' => c,
+            _ => {
+                self.position -= 1;
+                return self.error();
+            }
+        };
+        Ok(c)
+    }
+
+    /// The part of an escape outside of a class after the backslash
+    fn atom_escape(&mut self) -> Result<Node, RegexError> {
+        let c = match self.next() {
+            Some(c) => c,
+            None => return self.error(),
+        };
+
+        if let Some(item) = self.set_escape(c)? {
+            return Ok(Node::Class {
+                items: vec![item],
+                negated: false,
+            });
+        }
+
+        match c {
+            '1'..='9' => {
+                let mut index = c.to_digit(10).unwrap_or_default() as usize;
+                while let Some(digit) = self.peek().and_then(|c| c.to_digit(10)) {
+                    index = index.saturating_mul(10).saturating_add(digit as usize);
+                    self.position += 1;
+                }
+                if index > self.total_groups {
+                    return self.error();
+                }
+                Ok(Node::Backreference(index))
+            }
+            'k' => {
+                self.expect('<')?;
+                Ok(Node::NamedBackreference(self.group_name()?))
+            }
+            c => Ok(Node::Char(self.char_escape(c, false)?)),
+        }
+    }
+
+    /// The part of a character class after the opening bracket
+    fn class(&mut self) -> Result<Node, RegexError> {
+        let negated = self.eat('^');
+        let mut items = Vec::new();
+
+        loop {
+            let item = match self.next() {
+                Some(']') => break,
+                Some('\\') => self.class_escape()?,
+                Some(c) => ClassItem::Char(c),
+                None => return self.error(),
+            };
+
+            let is_range = self.peek() == Some('-')
+                && !matches!(self.chars.get(self.position + 1), Some(']') | None);
+            if !is_range {
+                items.push(item);
+                continue;
+            }
+
+            self.position += 1;
+            let end = match self.next() {
+                Some('\\') => self.class_escape()?,
+                Some(c) => ClassItem::Char(c),
+                None => return self.error(),
+            };
+
+            match (item, end) {
+                (ClassItem::Char(start), ClassItem::Char(end)) if start <= end => {
+                    items.push(ClassItem::Range(start, end))
+                }
+                _ => return self.error(),
+            }
+        }
+
+        Ok(Node::Class { items, negated })
+    }
+
+    /// The part of an escape inside of a class after the backslash
+    fn class_escape(&mut self) -> Result<ClassItem, RegexError> {
+        let c = match self.next() {
+            Some(c) => c,
+            None => return self.error(),
+        };
+
+        match self.set_escape(c)? {
+            Some(item) => Ok(item),
+            None => Ok(ClassItem::Char(self.char_escape(c, true)?)),
+        }
+    }
+}
+
+/// Count the capturing groups in `chars`, so that backreferences to
+/// groups that come after them can be checked
+fn count_groups(chars: &[char]) -> usize {
+    let mut count = 0;
+    let mut in_class = false;
+    let mut idx = 0;
+    while idx < chars.len() {
+        match chars[idx] {
+            '\\' => idx += 1,
+            '[' => in_class = true,
+            ']' => in_class = false,
+            '(' if !in_class => {
+                let named = chars.get(idx + 1) == Some(&'?')
+                    && chars.get(idx + 2) == Some(&'<')
+                    && !matches!(chars.get(idx + 3), Some('=' | '!'));
+                if chars.get(idx + 1) != Some(&'?') || named {
+                    count += 1;
+                }
+            }
+            _ => {}
+        }
+        idx += 1;
+    }
+    count
+}
+
+/// The number of steps that matching takes at most, unless
+/// [`Regex::step_limit`] sets another
+pub const DEFAULT_STEP_LIMIT: usize = 10_000_000;
+
+/// A compiled regular expression
+#[derive(Debug, Clone)]
+pub struct Regex {
+    source: String,
+    /// The program of the whole pattern, followed by one for each lookaround
+    programs: Vec<Vec<Inst>>,
+    groups: usize,
+    registers: usize,
+    step_limit: usize,
+}
+
+impl PartialEq for Regex {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl std::fmt::Display for Regex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Self, RegexError> {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut parser = Parser {
+            chars: &chars,
+            position: 0,
+            groups: 0,
+            names: HashMap::new(),
+            total_groups: count_groups(&chars),
+        };
+
+        let node = parser.disjunction()?;
+        if parser.position != chars.len() {
+            return parser.error();
+        }
+        if !names_resolve(&node, &parser.names) {
+            return Err(RegexError {
+                position: chars.len(),
+            });
+        }
+
+        // Groups keep where they start in the register of their index
+        let mut compiler = Compiler {
+            names: &parser.names,
+            programs: Vec::new(),
+            registers: parser.groups + 1,
+        };
+        compiler.program(&node);
+
+        Ok(Self {
+            source: pattern.to_string(),
+            programs: compiler.programs,
+            groups: parser.groups,
+            registers: compiler.registers,
+            step_limit: DEFAULT_STEP_LIMIT,
+        })
+    }
+
+    /// Give up matching after `steps` steps, which counts as no match.
+    /// Without a limit, patterns like `^(a+)+$` take time exponential in
+    /// the length of the input that they don't match.
+    pub fn step_limit(mut self, steps: usize) -> Self {
+        self.step_limit = steps;
+        self
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Whether this expression matches any part of `haystack`
+    pub fn is_match(&self, haystack: &str) -> bool {
+        let input: Vec<char> = haystack.chars().collect();
+        let mut matcher = Matcher {
+            programs: &self.programs,
+            input: &input,
+            captures: vec![None; self.groups + 1],
+            registers: vec![0; self.registers],
+            steps: self.step_limit,
+        };
+
+        for start in 0..=input.len() {
+            match matcher.run(0, start, None) {
+                Ok(true) => return true,
+                Ok(false) => {}
+                Err(Exhausted) => return false,
+            }
+        }
+        false
+    }
+}
+
+fn names_resolve(node: &Node, names: &HashMap<String, usize>) -> bool {
+    match node {
+        Node::NamedBackreference(name) => names.contains_key(name),
+        Node::Group { node, .. } | Node::Repeat { node, .. } | Node::Lookaround { node, .. } => {
+            names_resolve(node, names)
+        }
+        Node::Concat(nodes) | Node::Alternation(nodes) => {
+            nodes.iter().all(|node| names_resolve(node, names))
+        }
+        _ => true,
+    }
+}
+
+/// An instruction of a compiled pattern. Each one either fails, or goes on
+/// with the next one unless it says otherwise.
+#[derive(Debug, Clone)]
+enum Inst {
+    /// A node that matches a single character
+    Char(Node),
+    Start,
+    End,
+    WordBoundary {
+        negated: bool,
+    },
+    /// Go on at `first`, and at `second` if that fails
+    Split {
+        first: usize,
+        second: usize,
+    },
+    Jump(usize),
+    GroupStart {
+        register: usize,
+    },
+    GroupEnd {
+        index: usize,
+        register: usize,
+    },
+    Backreference(usize),
+    RepeatInit {
+        counter: usize,
+    },
+    /// Start another iteration of a repetition, or end it by going on at
+    /// `exit`
+    Repeat {
+        counter: usize,
+        min: u32,
+        max: Option<u32>,
+        greedy: bool,
+        exit: usize,
+    },
+    IterationStart {
+        start: usize,
+    },
+    /// Count an iteration and go back to the [`Inst::Repeat`] at `head`
+    IterationEnd {
+        counter: usize,
+        start: usize,
+        min: u32,
+        head: usize,
+    },
+    /// A repetition of a node that matches a single character, which is
+    /// matched without an instruction for every character
+    Run {
+        node: Node,
+        min: u32,
+        max: Option<u32>,
+        greedy: bool,
+    },
+    Lookaround {
+        program: usize,
+        ahead: bool,
+        negated: bool,
+    },
+    Match,
+}
+
+struct Compiler<'a> {
+    names: &'a HashMap<String, usize>,
+    programs: Vec<Vec<Inst>>,
+    registers: usize,
+}
+
+impl Compiler<'_> {
+    /// Compile `node` into a program of its own, and return its index
+    fn program(&mut self, node: &Node) -> usize {
+        let index = self.programs.len();
+        self.programs.push(Vec::new());
+        let mut program = Vec::new();
+        self.node(node, &mut program);
+        program.push(Inst::Match);
+        self.programs[index] = program;
+        index
+    }
+
+    fn register(&mut self) -> usize {
+        self.registers += 1;
+        self.registers - 1
+    }
+
+    fn node(&mut self, node: &Node, program: &mut Vec<Inst>) {
+        match node {
+            Node::Empty => {}
+            Node::Char(_) | Node::Any | Node::Class { .. } => {
+                program.push(Inst::Char(node.clone()))
+            }
+            Node::Start => program.push(Inst::Start),
+            Node::End => program.push(Inst::End),
+            Node::WordBoundary { negated } => {
+                program.push(Inst::WordBoundary { negated: *negated })
+            }
+            Node::Group {
+                node,
+                index: Some(index),
+            } => {
+                program.push(Inst::GroupStart { register: *index });
+                self.node(node, program);
+                program.push(Inst::GroupEnd {
+                    index: *index,
+                    register: *index,
+                });
+            }
+            Node::Group { node, index: None } => self.node(node, program),
+            Node::Concat(nodes) => {
+                for node in nodes {
+                    self.node(node, program);
+                }
+            }
+            Node::Alternation(nodes) => {
+                let mut jumps = Vec::new();
+                for (idx, node) in nodes.iter().enumerate() {
+                    if idx + 1 == nodes.len() {
+                        self.node(node, program);
+                        break;
+                    }
+
+                    let split = program.len();
+                    program.push(Inst::Jump(0));
+                    self.node(node, program);
+                    jumps.push(program.len());
+                    program.push(Inst::Jump(0));
+                    program[split] = Inst::Split {
+                        first: split + 1,
+                        second: program.len(),
+                    };
+                }
+                let end = program.len();
+                for jump in jumps {
+                    program[jump] = Inst::Jump(end);
+                }
+            }
+            Node::Repeat {
+                node,
+                min,
+                max,
+                greedy,
+            } => {
+                if matches!(**node, Node::Char(_) | Node::Any | Node::Class { .. }) {
+                    program.push(Inst::Run {
+                        node: (**node).clone(),
+                        min: *min,
+                        max: *max,
+                        greedy: *greedy,
+                    });
+                    return;
+                }
+                if *max == Some(0) {
+                    return;
+                }
+
+                let counter = self.register();
+                let start = self.register();
+                program.push(Inst::RepeatInit { counter });
+                let head = program.len();
+                program.push(Inst::Jump(0));
+                program.push(Inst::IterationStart { start });
+                self.node(node, program);
+                program.push(Inst::IterationEnd {
+                    counter,
+                    start,
+                    min: *min,
+                    head,
+                });
+                program[head] = Inst::Repeat {
+                    counter,
+                    min: *min,
+                    max: *max,
+                    greedy: *greedy,
+                    exit: program.len(),
+                };
+            }
+            Node::Backreference(index) => program.push(Inst::Backreference(*index)),
+            Node::NamedBackreference(name) => program.push(Inst::Backreference(self.names[name])),
+            Node::Lookaround {
+                node,
+                ahead,
+                negated,
+            } => {
+                let lookaround = self.program(node);
+                program.push(Inst::Lookaround {
+                    program: lookaround,
+                    ahead: *ahead,
+                    negated: *negated,
+                });
+            }
+        }
+    }
+}
+
+/// Where to go on when matching fails, or what to undo on the way there
+enum Backtrack {
+    Resume {
+        pc: usize,
+        position: usize,
+    },
+    /// Go on at `pc` after the run from `start` on, one character shorter
+    /// or longer than `len`
+    Run {
+        pc: usize,
+        start: usize,
+        len: usize,
+        min: usize,
+        max: usize,
+        greedy: bool,
+    },
+    Register {
+        register: usize,
+        value: usize,
+    },
+    Capture {
+        index: usize,
+        value: Option<(usize, usize)>,
+    },
+}
+
+/// Matching took more steps than its limit
+struct Exhausted;
+
+/// Matches programs by backtracking, with the alternatives left to try on
+/// a stack rather than in recursive calls, so that long input doesn't
+/// overflow the call stack
+struct Matcher<'a> {
+    programs: &'a [Vec<Inst>],
+    input: &'a [char],
+    captures: Vec<Option<(usize, usize)>>,
+    registers: Vec<usize>,
+    steps: usize,
+}
+
+impl Matcher<'_> {
+    fn step(&mut self, steps: usize) -> Result<(), Exhausted> {
+        self.steps = self.steps.checked_sub(steps).ok_or(Exhausted)?;
+        Ok(())
+    }
+
+    fn set_register(&mut self, stack: &mut Vec<Backtrack>, register: usize, value: usize) {
+        stack.push(Backtrack::Register {
+            register,
+            value: self.registers[register],
+        });
+        self.registers[register] = value;
+    }
+
+    /// Whether `program` matches from `start` on, up to `end` if given.
+    /// A match leaves its captures set, no match leaves them as they were.
+    fn run(&mut self, program: usize, start: usize, end: Option<usize>) -> Result<bool, Exhausted> {
+        let programs = self.programs;
+        let program = &programs[program];
+        let mut stack = Vec::new();
+        let mut pc = 0;
+        let mut position = start;
+
+        loop {
+            self.step(1)?;
+            let next = match &program[pc] {
+                Inst::Char(node) => self
+                    .input
+                    .get(position)
+                    .filter(|c| node.single_char(**c) == Some(true))
+                    .map(|_| (pc + 1, position + 1)),
+                Inst::Start => (position == 0).then_some((pc + 1, position)),
+                Inst::End => (position == self.input.len()).then_some((pc + 1, position)),
+                Inst::WordBoundary { negated } => {
+                    let before = position > 0 && is_word(self.input[position - 1]);
+                    let after = self.input.get(position).is_some_and(|c| is_word(*c));
+                    ((before != after) != *negated).then_some((pc + 1, position))
+                }
+                Inst::Split { first, second } => {
+                    stack.push(Backtrack::Resume {
+                        pc: *second,
+                        position,
+                    });
+                    Some((*first, position))
+                }
+                Inst::Jump(target) => Some((*target, position)),
+                Inst::GroupStart { register } => {
+                    self.set_register(&mut stack, *register, position);
+                    Some((pc + 1, position))
+                }
+                Inst::GroupEnd { index, register } => {
+                    stack.push(Backtrack::Capture {
+                        index: *index,
+                        value: self.captures[*index],
+                    });
+                    self.captures[*index] = Some((self.registers[*register], position));
+                    Some((pc + 1, position))
+                }
+                Inst::Backreference(index) => match self.captures[*index] {
+                    Some((start, end)) => {
+                        let len = end - start;
+                        self.step(len)?;
+                        let matches = self.input.get(position..position + len)
+                            == Some(&self.input[start..end]);
+                        matches.then_some((pc + 1, position + len))
+                    }
+                    None => Some((pc + 1, position)),
+                },
+                Inst::RepeatInit { counter } => {
+                    self.set_register(&mut stack, *counter, 0);
+                    Some((pc + 1, position))
+                }
+                Inst::Repeat {
+                    counter,
+                    min,
+                    max,
+                    greedy,
+                    exit,
+                } => {
+                    let count = self.registers[*counter];
+                    if count < *min as usize {
+                        Some((pc + 1, position))
+                    } else if max.is_some_and(|max| count >= max as usize) {
+                        Some((*exit, position))
+                    } else if *greedy {
+                        stack.push(Backtrack::Resume {
+                            pc: *exit,
+                            position,
+                        });
+                        Some((pc + 1, position))
+                    } else {
+                        stack.push(Backtrack::Resume {
+                            pc: pc + 1,
+                            position,
+                        });
+                        Some((*exit, position))
+                    }
+                }
+                Inst::IterationStart { start } => {
+                    self.set_register(&mut stack, *start, position);
+                    Some((pc + 1, position))
+                }
+                Inst::IterationEnd {
+                    counter,
+                    start,
+                    min,
+                    head,
+                } => {
+                    // An iteration that matches nothing could repeat forever
+                    let count = self.registers[*counter];
+                    if count >= *min as usize && position == self.registers[*start] {
+                        None
+                    } else {
+                        self.set_register(&mut stack, *counter, count + 1);
+                        Some((*head, position))
+                    }
+                }
+                Inst::Run {
+                    node,
+                    min,
+                    max,
+                    greedy,
+                } => {
+                    let len = self.input[position..]
+                        .iter()
+                        .take(max.map_or(usize::MAX, |max| max as usize))
+                        .take_while(|c| node.single_char(**c) == Some(true))
+                        .count();
+                    self.step(len)?;
+
+                    let min = *min as usize;
+                    if len < min {
+                        None
+                    } else {
+                        let taken = if *greedy { len } else { min };
+                        if len > min {
+                            stack.push(Backtrack::Run {
+                                pc: pc + 1,
+                                start: position,
+                                len: taken,
+                                min,
+                                max: len,
+                                greedy: *greedy,
+                            });
+                        }
+                        Some((pc + 1, position + taken))
+                    }
+                }
+                Inst::Lookaround {
+                    program,
+                    ahead,
+                    negated,
+                } => {
+                    let captures = self.captures.clone();
+                    let mut matched = false;
+                    if *ahead {
+                        matched = self.run(*program, position, None)?;
+                    } else {
+                        for start in (0..=position).rev() {
+                            if self.run(*program, start, Some(position))? {
+                                matched = true;
+                                break;
+                            }
+                        }
+                    }
+
+                    if matched && !*negated {
+                        for (index, value) in captures.into_iter().enumerate() {
+                            if self.captures[index] != value {
+                                stack.push(Backtrack::Capture { index, value });
+                            }
+                        }
+                    } else {
+                        self.captures = captures;
+                    }
+                    (matched != *negated).then_some((pc + 1, position))
+                }
+                Inst::Match => {
+                    if end.is_none_or(|end| position == end) {
+                        return Ok(true);
+                    }
+                    None
+                }
+            };
+
+            match next.or_else(|| self.backtrack(&mut stack)) {
+                Some((next_pc, next_position)) => {
+                    pc = next_pc;
+                    position = next_position;
+                }
+                None => return Ok(false),
+            }
+        }
+    }
+
+    /// Undo changes up to the latest alternative on `stack`, and return
+    /// where it goes on
+    fn backtrack(&mut self, stack: &mut Vec<Backtrack>) -> Option<(usize, usize)> {
+        loop {
+            match stack.pop()? {
+                Backtrack::Resume { pc, position } => return Some((pc, position)),
+                Backtrack::Run {
+                    pc,
+                    start,
+                    len,
+                    min,
+                    max,
+                    greedy,
+                } => {
+                    let len = if greedy { len - 1 } else { len + 1 };
+                    if (greedy && len > min) || (!greedy && len < max) {
+                        stack.push(Backtrack::Run {
+                            pc,
+                            start,
+                            len,
+                            min,
+                            max,
+                            greedy,
+                        });
+                    }
+                    return Some((pc, start + len));
+                }
+                Backtrack::Register { register, value } => self.registers[register] = value,
+                Backtrack::Capture { index, value } => self.captures[index] = value,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Regex;
+
+    fn matches(pattern: &str, haystack: &str) -> bool {
+        Regex::new(pattern).unwrap().is_match(haystack)
+    }
+
+    #[test]
+    fn syntax() {
+        for pattern in [
+            "([abc])+\\s+$",
+            "^(?:a|b){2,3}?$",
+            "[\\b\\-]",
+            "(?<name>x)\\k<name>",
+            "\\p{Letter}+",
+            "(a)\\1",
+            "[^]",
+            "\\u{1F600}",
+        ] {
+            assert!(Regex::new(pattern).is_ok(), "{}", pattern);
+        }
+
+        for pattern in [
+            "^(abc]",
+            "\\a",
+            "a**",
+            "{",
+            "(?<=x",
+            "a{3,1}",
+            "(?=a)*",
+            "\\2(a)",
+            "[z-a]",
+            "\\k<x>",
+            "\\p{Nope}",
+        ] {
+            assert!(Regex::new(pattern).is_err(), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn matching() {
+        assert!(matches("^a*$", "aaa"));
+        assert!(matches("^a*$", ""));
+        assert!(!matches("^a*$", "aab"));
+        assert!(matches("b", "abc"));
+        assert!(matches("^[A-Za-z_][-A-Za-z0-9._]*$", "foo-bar.1"));
+        assert!(!matches("^[A-Za-z_][-A-Za-z0-9._]*$", "1foo"));
+        assert!(matches("^[^#]*#?$", "http://example.com#"));
+        assert!(!matches("^[^#]*#?$", "http://example.com#a"));
+        assert!(matches("^(ab|a)c$", "ac"));
+        assert!(matches("^(a+)+b$", "aaab"));
+        assert!(matches("^(\\w)\\1$", "xx"));
+        assert!(!matches("^(\\w)\\1$", "xy"));
+        assert!(matches("^\\d{3}-\\d{4}$", "555-1234"));
+        assert!(!matches("^\\d{3}-\\d{4}$", "555-12345"));
+        assert!(matches("a(?=b)", "ab"));
+        assert!(!matches("a(?!b)", "ab"));
+        assert!(matches("(?<=a)b", "ab"));
+        assert!(matches("^.$", "\u{1F600}"));
+        assert!(!matches("^.$", "\n"));
+        assert!(matches("\\bfoo\\b", "a foo b"));
+        assert!(matches("^a{2,}?$", "aaaa"));
+        assert!(matches("^\\p{L}+$", "héllo"));
+        assert!(!matches("^\\d$", "\u{0967}"));
+        assert!(matches("^(?:ab){2,3}$", "ababab"));
+        assert!(!matches("^(?:ab){2,3}$", "abababab"));
+        assert!(matches("^(?:a|b)+?c$", "abac"));
+        assert!(matches("^(a|ab)(c|bcd)(d*)$", "abcd"));
+        assert!(matches("^(?:(a)|b)*\\1$", "aba"));
+        assert!(matches("^(?:a*)*$", "aaa"));
+        assert!(!matches("(?<!a)b", "ab"));
+        assert!(matches("^(?=(a+))\\1b$", "aab"));
+    }
+
+    #[test]
+    fn long_input() {
+        let haystack = "a".repeat(100_000);
+        assert!(matches("^a*$", &haystack));
+        assert!(matches("^[a-z]+$", &haystack));
+    }
+
+    #[test]
+    fn deep_repetition() {
+        let haystack = "ab".repeat(200_000);
+        assert!(matches("^(?:ab)*$", &haystack));
+        assert!(matches("^(ab)+$", &haystack));
+        assert!(!matches("^(?:ab)*$", &(haystack + "a")));
+    }
+
+    #[test]
+    fn step_limit() {
+        assert!(!matches("^(a+)+$", &("a".repeat(26) + "b")));
+        assert!(!matches("^(a+)+$", &("a".repeat(40) + "b")));
+        assert!(matches("^(a+)+$", &"a".repeat(40)));
+
+        let regex = Regex::new("^(a|aa)+$").unwrap();
+        assert!(regex.is_match(&"a".repeat(20)));
+        assert!(!regex.clone().step_limit(10).is_match(&"a".repeat(20)));
+    }
+}
@@ -0,0 +1,66 @@
+//! The schemas that `$ref` and `$dynamicRef` resolve to
+
+use std::collections::{HashMap, HashSet};
+
+use super::JsonSchema;
+
+/// A schema that a reference resolves to
+#[derive(Debug, Clone)]
+pub(crate) struct Target {
+    /// The base URI of the schema resource that the schema is part of
+    pub base_uri: String,
+    /// The JSON Pointer to the schema from the root of that resource
+    pub pointer: String,
+    pub schema: JsonSchema,
+}
+
+/// Every schema that the references in a compiled schema resolve to, by
+/// absolute URI
+#[derive(Debug, Clone, Default)]
+pub struct Registry {
+    targets: HashMap<String, Target>,
+    /// The URIs of the schemas that declare a `$dynamicAnchor`, in the form
+    /// `base#name`
+    dynamic_anchors: HashSet<String>,
+}
+
+impl Registry {
+    pub(crate) fn insert(&mut self, uri: String, target: Target) {
+        self.targets.insert(uri, target);
+    }
+
+    pub(crate) fn add_dynamic_anchor(&mut self, uri: String) {
+        self.dynamic_anchors.insert(uri);
+    }
+
+    pub(crate) fn contains(&self, uri: &str) -> bool {
+        self.targets.contains_key(uri)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+
+    /// Find the schema that a reference to `uri` resolves to
+    ///
+    /// A dynamic reference to a dynamic anchor resolves to the outermost
+    /// resource in `scope` that declares a dynamic anchor of the same name.
+    pub(crate) fn resolve<'a, S>(&self, uri: &str, dynamic: bool, scope: S) -> Option<&Target>
+    where
+        S: IntoIterator<Item = &'a str>,
+    {
+        if dynamic && self.dynamic_anchors.contains(uri) {
+            if let Some((_, name)) = uri.split_once('#') {
+                let outermost = scope
+                    .into_iter()
+                    .map(|base_uri| format!("{}#{}", base_uri, name))
+                    .find(|uri| self.dynamic_anchors.contains(uri));
+
+                if let Some(target) = outermost.and_then(|uri| self.targets.get(&uri)) {
+                    return Some(target);
+                }
+            }
+        }
+        self.targets.get(uri)
+    }
+}
@@ -0,0 +1,183 @@
+//! Just enough of RFC 3986 to resolve `$id`s against their base URI
+
+struct Parts<'a> {
+    scheme: Option<&'a str>,
+    authority: Option<&'a str>,
+    path: &'a str,
+    query: Option<&'a str>,
+    fragment: Option<&'a str>,
+}
+
+impl<'a> Parts<'a> {
+    fn parse(mut uri: &'a str) -> Self {
+        let fragment = uri.find('#').map(|idx| {
+            let fragment = &uri[idx + 1..];
+            uri = &uri[..idx];
+            fragment
+        });
+
+        let query = uri.find('?').map(|idx| {
+            let query = &uri[idx + 1..];
+            uri = &uri[..idx];
+            query
+        });
+
+        let scheme = uri
+            .find([':', '/'])
+            .filter(|idx| uri[*idx..].starts_with(':') && *idx > 0)
+            .map(|idx| {
+                let scheme = &uri[..idx];
+                uri = &uri[idx + 1..];
+                scheme
+            });
+
+        let authority = if let Some(rest) = uri.strip_prefix("//") {
+            let end = rest.find('/').unwrap_or(rest.len());
+            uri = &rest[end..];
+            Some(&rest[..end])
+        } else {
+            None
+        };
+
+        Self {
+            scheme,
+            authority,
+            path: uri,
+            query,
+            fragment,
+        }
+    }
+}
+
+impl std::fmt::Display for Parts<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(scheme) = self.scheme {
+            write!(f, "{}:", scheme)?;
+        }
+        if let Some(authority) = self.authority {
+            write!(f, "//{}", authority)?;
+        }
+        write!(f, "{}", self.path)?;
+        if let Some(query) = self.query {
+            write!(f, "?{}", query)?;
+        }
+        if let Some(fragment) = self.fragment {
+            write!(f, "#{}", fragment)?;
+        }
+        Ok(())
+    }
+}
+
+fn remove_dot_segments(path: &str) -> String {
+    let mut output: Vec<&str> = Vec::new();
+    let segments: Vec<&str> = path.split('/').collect();
+    for (idx, segment) in segments.iter().enumerate() {
+        let last = idx == segments.len() - 1;
+        match *segment {
+            "." => {
+                if last {
+                    output.push("");
+                }
+            }
+            ".." => {
+                if output.len() > 1 || output.first().map(|s| !s.is_empty()) == Some(true) {
+                    output.pop();
+                }
+                if last {
+                    output.push("");
+                }
+            }
+            segment => output.push(segment),
+        }
+    }
+    output.join("/")
+}
+
+fn merge(base: &Parts, reference: &str) -> String {
+    if base.authority.is_some() && base.path.is_empty() {
+        format!("/{}", reference)
+    } else if let Some(idx) = base.path.rfind('/') {
+        format!("{}{}", &base.path[..=idx], reference)
+    } else {
+        reference.to_string()
+    }
+}
+
+/// Resolve `reference` against `base` as described in RFC 3986 section 5.2
+pub(crate) fn resolve(base: &str, reference: &str) -> String {
+    let base = Parts::parse(base);
+    let parsed = Parts::parse(reference);
+
+    let path;
+    let target = if parsed.scheme.is_some() {
+        path = remove_dot_segments(parsed.path);
+        Parts {
+            path: &path,
+            ..parsed
+        }
+    } else if parsed.authority.is_some() {
+        path = remove_dot_segments(parsed.path);
+        Parts {
+            scheme: base.scheme,
+            path: &path,
+            ..parsed
+        }
+    } else if parsed.path.is_empty() {
+        Parts {
+            scheme: base.scheme,
+            authority: base.authority,
+            path: base.path,
+            query: parsed.query.or(base.query),
+            fragment: parsed.fragment,
+        }
+    } else {
+        path = if parsed.path.starts_with('/') {
+            remove_dot_segments(parsed.path)
+        } else {
+            remove_dot_segments(&merge(&base, parsed.path))
+        };
+        Parts {
+            scheme: base.scheme,
+            authority: base.authority,
+            path: &path,
+            query: parsed.query,
+            fragment: parsed.fragment,
+        }
+    };
+
+    target.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::resolve;
+
+    #[test]
+    fn rfc_examples() {
+        let base = "http://a/b/c/d;p?q";
+        let examples = [
+            ("g:h", "g:h"),
+            ("g", "http://a/b/c/g"),
+            ("./g", "http://a/b/c/g"),
+            ("g/", "http://a/b/c/g/"),
+            ("/g", "http://a/g"),
+            ("//g", "http://g"),
+            ("?y", "http://a/b/c/d;p?y"),
+            ("g?y", "http://a/b/c/g?y"),
+            ("#s", "http://a/b/c/d;p?q#s"),
+            ("g#s", "http://a/b/c/g#s"),
+            ("", "http://a/b/c/d;p?q"),
+            (".", "http://a/b/c/"),
+            ("..", "http://a/b/"),
+            ("../g", "http://a/b/g"),
+            ("../..", "http://a/"),
+            ("../../../g", "http://a/g"),
+            ("/./g", "http://a/g"),
+            ("g/../h", "http://a/b/c/h"),
+        ];
+
+        for (reference, expected) in examples {
+            assert_eq!(resolve(base, reference), expected, "{}", reference);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! The groups of keywords that metaschemas can enable with `$vocabulary`
+
+/// A group of keywords defined by the specification
+///
+/// The keywords of the core vocabulary, like `$ref` and `$id`, are always
+/// enabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Vocabulary {
+    Core,
+    Applicator,
+    Unevaluated,
+    Validation,
+    MetaData,
+    /// `format`, which only produces annotations
+    FormatAnnotation,
+    /// `format`, which fails validation for values that do not have the
+    /// format
+    FormatAssertion,
+    Content,
+}
+
+impl Vocabulary {
+    /// Look up a vocabulary by the URI it is declared with in `$vocabulary`
+    pub fn from_uri(uri: &str) -> Option<Self> {
+        let name = uri
+            .strip_prefix("https://json-schema.org/draft/2020-12/vocab/")
+            .or_else(|| uri.strip_prefix("https://json-schema.org/draft/2019-09/vocab/"))?;
+
+        let vocabulary = match name {
+            "core" => Self::Core,
+            "applicator" => Self::Applicator,
+            "unevaluated" => Self::Unevaluated,
+            "validation" => Self::Validation,
+            "meta-data" => Self::MetaData,
+            "format-annotation" => Self::FormatAnnotation,
+            "format-assertion" => Self::FormatAssertion,
+            // 2019-09 has a single format vocabulary
+            "format" if uri.contains("2019-09") => Self::FormatAnnotation,
+            "content" => Self::Content,
+            _ => return None,
+        };
+        Some(vocabulary)
+    }
+
+    /// The vocabulary that `keyword` is part of, if it is a keyword that
+    /// the specification defines
+    ///
+    /// `format` is part of both format vocabularies, and is reported as
+    /// part of the annotation one.
+    pub fn of(keyword: &str) -> Option<Self> {
+        let vocabulary = match keyword {
+            "$id" | "id" | "$schema" | "$ref" | "$anchor" | "$dynamicRef" | "$dynamicAnchor"
+            | "$recursiveRef" | "$recursiveAnchor" | "$vocabulary" | "$comment" | "$defs"
+            | "definitions" => Self::Core,
+            "prefixItems"
+            | "items"
+            | "additionalItems"
+            | "contains"
+            | "additionalProperties"
+            | "properties"
+            | "patternProperties"
+            | "dependentSchemas"
+            | "dependencies"
+            | "propertyNames"
+            | "if"
+            | "then"
+            | "else"
+            | "allOf"
+            | "anyOf"
+            | "oneOf"
+            | "not" => Self::Applicator,
+            "unevaluatedItems" | "unevaluatedProperties" => Self::Unevaluated,
+            "type" | "enum" | "const" | "multipleOf" | "maximum" | "exclusiveMaximum"
+            | "minimum" | "exclusiveMinimum" | "maxLength" | "minLength" | "pattern"
+            | "maxItems" | "minItems" | "uniqueItems" | "maxContains" | "minContains"
+            | "maxProperties" | "minProperties" | "required" | "dependentRequired" => {
+                Self::Validation
+            }
+            "title" | "description" | "default" | "deprecated" | "readOnly" | "writeOnly"
+            | "examples" => Self::MetaData,
+            "format" => Self::FormatAnnotation,
+            "contentEncoding" | "contentMediaType" | "contentSchema" => Self::Content,
+            _ => return None,
+        };
+        Some(vocabulary)
+    }
+
+    /// The vocabularies that schemas written against the metaschema of a
+    /// draft use, which include every vocabulary but format assertion
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            Self::Core,
+            Self::Applicator,
+            Self::Unevaluated,
+            Self::Validation,
+            Self::MetaData,
+            Self::FormatAnnotation,
+            Self::Content,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Vocabulary;
+
+    #[test]
+    fn uris() {
+        assert_eq!(
+            Vocabulary::from_uri("https://json-schema.org/draft/2020-12/vocab/format-assertion"),
+            Some(Vocabulary::FormatAssertion)
+        );
+        assert_eq!(
+            Vocabulary::from_uri("https://json-schema.org/draft/2019-09/vocab/format"),
+            Some(Vocabulary::FormatAnnotation)
+        );
+        assert_eq!(
+            Vocabulary::from_uri("https://example.com/vocab/units"),
+            None
+        );
+    }
+
+    #[test]
+    fn keywords() {
+        assert_eq!(Vocabulary::of("$ref"), Some(Vocabulary::Core));
+        assert_eq!(Vocabulary::of("required"), Some(Vocabulary::Validation));
+        assert_eq!(Vocabulary::of("properties"), Some(Vocabulary::Applicator));
+        assert_eq!(Vocabulary::of("x-unit"), None);
+    }
+}
@@ -0,0 +1,75 @@
+//! Runs the parsing test cases of JSONTestSuite
+//!
+//! The cases are vendored in `tests/JSONTestSuite/test_parsing`, where the
+//! prefix of the name of each file says whether it must be accepted (`y_`),
+//! must be rejected (`n_`), or may be either (`i_`). The outcome of the
+//! cases that may be either is reported, so that changes to it are noticed.
+//! Every case that is accepted must also be written back as it was read by
+//! a concrete syntax tree.
+
+use std::{fs, path::Path};
+
+use jsonschema::json::{Cst, Parser, ParserOptions};
+
+const SUITE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/JSONTestSuite/test_parsing"
+);
+
+#[test]
+fn json_test_suite() {
+    let mut paths: Vec<_> = fs::read_dir(SUITE)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .collect();
+    paths.sort();
+
+    let mut failures = vec![];
+    for path in &paths {
+        let name = path.file_name().unwrap().to_string_lossy();
+        let input = fs::read(path).unwrap();
+        let result = Parser::parse_bytes(&input);
+
+        if result.is_ok() {
+            match Cst::parse(&input, ParserOptions::default()) {
+                Ok(cst) if cst.to_string().as_bytes() == input => {}
+                Ok(cst) => {
+                    failures.push(format!("{}: written back as {:?}", name, cst.to_string()))
+                }
+                Err(error) => failures.push(format!("{}: no syntax tree, {:?}", name, error)),
+            }
+        }
+
+        match (&name[..2], result) {
+            ("y_", Err(error)) => failures.push(format!("{}: rejected with {:?}", name, error)),
+            ("n_", Ok(json)) => failures.push(format!("{}: accepted as {}", name, json)),
+            ("i_", result) => println!(
+                "{:<56} {}",
+                name,
+                if result.is_ok() {
+                    "accepted"
+                } else {
+                    "rejected"
+                }
+            ),
+            _ => {}
+        }
+    }
+
+    assert!(
+        !paths.is_empty(),
+        "no cases in {}",
+        Path::new(SUITE).display()
+    );
+    assert!(
+        failures.is_empty(),
+        "{} of {} cases failed:\n{}",
+        failures.len(),
+        paths.len(),
+        failures.join("\n")
+    );
+}
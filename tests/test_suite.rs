@@ -0,0 +1,228 @@
+//! Runs the test cases of the JSON-Schema-Test-Suite
+//!
+//! The cases are vendored in `tests/JSON-Schema-Test-Suite`, in the layout of
+//! the upstream suite: `tests/<draft>/<keyword>.json` holds an array of
+//! groups, each a schema with the instances that are and are not valid
+//! against it, and `remotes/` holds the documents that schemas refer to as
+//! `http://localhost:1234/<path>`.
+//!
+//! Every case is checked against each way of validating, and the outcome is
+//! reported per draft and keyword. Cases that exercise features that are
+//! not implemented yet are listed in [`SKIPPED`]. The report only measures
+//! how much of the specification is met if the cases are an upstream
+//! snapshot, whose commit the README of the suite records.
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use jsonschema::{
+    json::Json,
+    schema::{Draft, Loader},
+};
+
+const SUITE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/JSON-Schema-Test-Suite");
+
+const DRAFTS: &[(&str, Draft)] = &[
+    ("draft4", Draft::Draft4),
+    ("draft6", Draft::Draft6),
+    ("draft7", Draft::Draft7),
+    ("draft2019-09", Draft::Draft201909),
+    ("draft2020-12", Draft::Draft202012),
+];
+
+/// Known gaps, as the draft, the path of the file without its extension,
+/// and the description of a group, or `None` to skip the whole file
+const SKIPPED: &[(&str, &str, Option<&str>)] = &[];
+
+fn is_skipped(draft: &str, keyword: &str, group: &str) -> bool {
+    SKIPPED
+        .iter()
+        .any(|(skipped_draft, skipped_keyword, skipped_group)| {
+            *skipped_draft == draft
+                && *skipped_keyword == keyword
+                && skipped_group.is_none_or(|skipped_group| skipped_group == group)
+        })
+}
+
+/// The upstream commit that the vendored cases are a snapshot of, from the
+/// `Upstream commit:` line of the README of the suite
+fn upstream_commit(suite: &Path) -> Option<String> {
+    let readme = fs::read_to_string(suite.join("README.md")).ok()?;
+    readme.lines().find_map(|line| {
+        let commit = line.strip_prefix("Upstream commit:")?.trim();
+        let is_hash = commit.len() == 40 && commit.bytes().all(|byte| byte.is_ascii_hexdigit());
+        is_hash.then(|| commit.to_string())
+    })
+}
+
+fn read_json(path: &Path) -> Json {
+    let text = fs::read_to_string(path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+    Json::from_string(&text).unwrap_or_else(|| panic!("{}: not valid JSON", path.display()))
+}
+
+/// The paths of the files below `directory`, in a fixed order
+fn files(directory: &Path) -> Vec<std::path::PathBuf> {
+    let mut files = vec![];
+    let mut directories = vec![directory.to_path_buf()];
+    while let Some(directory) = directories.pop() {
+        for entry in fs::read_dir(&directory).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                directories.push(path);
+            } else if path
+                .extension()
+                .is_some_and(|extension| extension == "json")
+            {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+fn field<'a>(object: &'a Json, name: &str) -> &'a Json {
+    match object {
+        Json::Object(object) => &object[name],
+        _ => panic!("expected an object with \"{}\"", name),
+    }
+}
+
+fn string(json: &Json) -> &str {
+    match json {
+        Json::String(string) => string,
+        _ => panic!("expected a string, found {:?}", json),
+    }
+}
+
+fn array(json: &Json) -> &[Json] {
+    match json {
+        Json::Array(items) => items,
+        _ => panic!("expected an array, found {:?}", json),
+    }
+}
+
+#[derive(Default)]
+struct Tally {
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+}
+
+#[test]
+fn test_suite() {
+    let suite = Path::new(SUITE);
+
+    let remotes = suite.join("remotes");
+    let remotes: Vec<(String, Json)> = files(&remotes)
+        .into_iter()
+        .map(|path| {
+            let name = path.strip_prefix(&remotes).unwrap().to_string_lossy();
+            (format!("http://localhost:1234/{}", name), read_json(&path))
+        })
+        .collect();
+
+    let mut tallies: BTreeMap<(&str, String), Tally> = BTreeMap::new();
+    let mut failures = vec![];
+
+    for (name, draft) in DRAFTS {
+        let loader = |assert_formats| {
+            let loader = Loader::new()
+                .default_draft(*draft)
+                .assert_formats(assert_formats);
+            remotes.iter().fold(loader, |loader, (uri, document)| {
+                loader.with_document(uri, document.clone())
+            })
+        };
+        // The cases for formats expect them to be asserted
+        let (annotating, asserting) = (loader(false), loader(true));
+
+        let directory = suite.join("tests").join(name);
+        for path in files(&directory) {
+            let keyword = path
+                .strip_prefix(&directory)
+                .unwrap()
+                .with_extension("")
+                .to_string_lossy()
+                .into_owned();
+            let tally = tallies.entry((name, keyword.clone())).or_default();
+            let loader = if keyword.starts_with("optional/format/") {
+                &asserting
+            } else {
+                &annotating
+            };
+
+            for group in array(&read_json(&path)) {
+                let description = string(field(group, "description"));
+                let tests = array(field(group, "tests"));
+                if is_skipped(name, &keyword, description) {
+                    tally.skipped += tests.len();
+                    continue;
+                }
+
+                let schema = match loader.load(field(group, "schema")) {
+                    Ok(schema) => schema,
+                    Err(error) => {
+                        tally.failed += tests.len();
+                        failures.push(format!(
+                            "{}/{}: {}: schema did not load: {:?}",
+                            name, keyword, description, error
+                        ));
+                        continue;
+                    }
+                };
+
+                for test in tests {
+                    let data = field(test, "data");
+                    let expected = field(test, "valid") == &Json::Boolean(true);
+
+                    let outcomes = [
+                        ("is_valid", schema.is_valid(data)),
+                        ("validate", schema.validate(data).success),
+                        ("iter_errors", schema.iter_errors(data).next().is_none()),
+                    ];
+                    let wrong: Vec<&str> = outcomes
+                        .iter()
+                        .filter(|(_, valid)| *valid != expected)
+                        .map(|(method, _)| *method)
+                        .collect();
+
+                    if wrong.is_empty() {
+                        tally.passed += 1;
+                    } else {
+                        tally.failed += 1;
+                        failures.push(format!(
+                            "{}/{}: {}: {}: expected {} from {}",
+                            name,
+                            keyword,
+                            description,
+                            string(field(test, "description")),
+                            if expected { "valid" } else { "invalid" },
+                            wrong.join(", ")
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    match upstream_commit(suite) {
+        Some(commit) => println!("JSON-Schema-Test-Suite at upstream commit {}", commit),
+        None => println!(
+            "JSON-Schema-Test-Suite: no upstream commit is recorded, so the cases are not an \
+             upstream snapshot and the counts below do not measure compliance"
+        ),
+    }
+    for ((draft, keyword), tally) in &tallies {
+        println!(
+            "{:<14} {:<32} {:>4} passed {:>4} failed {:>4} skipped",
+            draft, keyword, tally.passed, tally.failed, tally.skipped
+        );
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} cases failed:\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+}